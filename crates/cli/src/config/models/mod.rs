@@ -2,6 +2,8 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 
 use alloy::primitives::Address;
+use color_eyre::eyre;
+use karak_kms::keystore::uri::KeystoreUri;
 use serde::{Deserialize, Serialize};
 use strum_macros::{Display, EnumString, FromRepr, VariantNames};
 
@@ -65,6 +67,56 @@ pub enum Keystore {
     #[serde(rename = "aws")]
     #[strum(serialize = "aws")]
     Aws { secret: String, profile: String },
+
+    #[serde(rename = "gcp")]
+    #[strum(serialize = "gcp")]
+    Gcp { project: String, secret: String },
+
+    /// A secret in a HashiCorp Vault KV v2 engine
+    #[serde(rename = "vault")]
+    #[strum(serialize = "vault")]
+    Vault {
+        address: String,
+        mount: String,
+        path: String,
+    },
+
+    /// A secp256k1 key held by AWS KMS, which signs without the key ever leaving KMS
+    #[serde(rename = "aws-kms")]
+    #[strum(serialize = "aws-kms")]
+    AwsKms { key_id: String, profile: String },
+}
+
+impl Keystore {
+    /// The location of the keypair, to resolve the keystore with `KeystoreUri::resolve`
+    pub fn uri(&self) -> eyre::Result<KeystoreUri> {
+        Ok(match self {
+            Keystore::Local { path } => KeystoreUri::Local { path: path.clone() },
+            Keystore::Aws { secret, profile } => KeystoreUri::Aws {
+                profile: profile.clone(),
+                secret_name: secret.clone(),
+                version_id: None,
+            },
+            Keystore::Gcp { project, secret } => KeystoreUri::Gcp {
+                project_id: project.clone(),
+                secret_id: secret.clone(),
+                version: None,
+            },
+            Keystore::Vault {
+                address,
+                mount,
+                path,
+            } => KeystoreUri::Vault {
+                address: url::Url::parse(address)?,
+                mount: mount.clone(),
+                path: path.clone(),
+                version: None,
+            },
+            Keystore::AwsKms { key_id, .. } => {
+                eyre::bail!("AWS KMS key {key_id} only signs, it does not store a keypair")
+            }
+        })
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
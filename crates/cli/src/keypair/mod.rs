@@ -2,6 +2,7 @@ pub mod processor;
 
 use clap::{Args, Parser, Subcommand};
 use std::path::PathBuf;
+use strum_macros::{Display, EnumString, VariantNames};
 
 use crate::config::models::{Curve, Keystore};
 
@@ -23,6 +24,27 @@ pub enum Keypair {
         /// Passphrase to encrypt keypair
         #[arg(long)]
         passphrase: Option<String>,
+
+        /// Derive the keypair from a new BIP-39 mnemonic, printed once for backup
+        #[arg(long)]
+        mnemonic: bool,
+
+        /// Derivation path, EIP-2333 for BN254 and BIP-32 for SECP256k1 keypairs
+        #[arg(long, requires = "mnemonic")]
+        derivation_path: Option<String>,
+    },
+    /// Recover keypair from a BIP-39 mnemonic
+    Recover {
+        #[command(flatten)]
+        keypair: Option<KeypairArgs>,
+
+        /// Passphrase to encrypt keypair
+        #[arg(long)]
+        passphrase: Option<String>,
+
+        /// Derivation path, EIP-2333 for BN254 and BIP-32 for SECP256k1 keypairs
+        #[arg(long)]
+        derivation_path: Option<String>,
     },
     /// List keypairs
     List {
@@ -40,6 +62,12 @@ pub enum Keypair {
 
         #[command(flatten)]
         local_config: Option<LocalKeypairConfig>,
+
+        #[command(flatten)]
+        gcp_config: Option<GcpKeypairConfig>,
+
+        #[command(flatten)]
+        vault_config: Option<VaultKeypairConfig>,
     },
     /// View public key
     Pubkey {
@@ -55,6 +83,50 @@ pub enum Keypair {
         #[arg(long, value_parser = crate::clap_enum_variants!(Curve))]
         curve: Option<Curve>,
     },
+    /// Change the passphrase of a keypair, re-encrypting it with the given KDF and cipher
+    Rekey {
+        /// Keystore name to use
+        #[arg(long)]
+        keystore_name: Option<String>,
+
+        /// Curve of the keypair
+        #[arg(long, value_parser = crate::clap_enum_variants!(Curve))]
+        curve: Option<Curve>,
+
+        /// Current passphrase of the keypair
+        #[arg(long)]
+        passphrase: Option<String>,
+
+        /// New passphrase to encrypt the keypair with
+        #[arg(long)]
+        new_passphrase: Option<String>,
+
+        /// Key derivation function, local SECP256k1 keystores are always geth keystores
+        #[arg(long, value_parser = crate::clap_enum_variants!(Kdf), default_value_t = Kdf::Scrypt)]
+        kdf: Kdf,
+
+        /// Cipher, defaults to AES-256-GCM. EIP-2335 and geth keystores always use AES-128-CTR
+        #[arg(long, value_parser = crate::clap_enum_variants!(Cipher))]
+        cipher: Option<Cipher>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, EnumString, VariantNames, Display)]
+pub enum Kdf {
+    #[strum(serialize = "scrypt")]
+    Scrypt,
+    #[strum(serialize = "pbkdf2")]
+    Pbkdf2,
+    #[strum(serialize = "argon2id")]
+    Argon2id,
+}
+
+#[derive(Debug, Clone, Copy, EnumString, VariantNames, Display)]
+pub enum Cipher {
+    #[strum(serialize = "aes-256-gcm")]
+    Aes256Gcm,
+    #[strum(serialize = "xchacha20-poly1305")]
+    XChaCha20Poly1305,
 }
 
 #[derive(Args, Debug)]
@@ -67,12 +139,42 @@ pub struct LocalKeypairConfig {
 #[derive(Args, Debug)]
 pub struct AwsKeypairConfig {
     /// AWS profile to use, if using AWS keystore
-    #[arg(long, required_if_eq("keystore", "aws"), global(true))]
+    #[arg(long, required_if_eq_any([("keystore", "aws"), ("keystore", "aws-kms")]), global(true))]
     profile: Option<String>,
 
     /// AWS secret name to use, if using AWS keystore
     #[arg(long, required_if_eq("keystore", "aws"), global(true))]
     secret_name: Option<String>,
+
+    /// AWS KMS key ID to use, if using AWS KMS keystore
+    #[arg(long, required_if_eq("keystore", "aws-kms"), global(true))]
+    key_id: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct GcpKeypairConfig {
+    /// GCP project ID, if using GCP keystore
+    #[arg(long, required_if_eq("keystore", "gcp"), global(true))]
+    gcp_project: Option<String>,
+
+    /// GCP secret name to use, if using GCP keystore
+    #[arg(long, required_if_eq("keystore", "gcp"), global(true))]
+    gcp_secret_name: Option<String>,
+}
+
+#[derive(Args, Debug)]
+pub struct VaultKeypairConfig {
+    /// Vault server address, if using Vault keystore
+    #[arg(long, required_if_eq("keystore", "vault"), global(true))]
+    vault_address: Option<String>,
+
+    /// Mount path of the KV v2 secrets engine, if using Vault keystore
+    #[arg(long, required_if_eq("keystore", "vault"), global(true))]
+    vault_mount: Option<String>,
+
+    /// Secret path within the mount, if using Vault keystore
+    #[arg(long, required_if_eq("keystore", "vault"), global(true))]
+    vault_path: Option<String>,
 }
 
 #[derive(Args, Debug)]
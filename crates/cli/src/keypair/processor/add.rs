@@ -4,12 +4,18 @@ use crate::config::{
     add_keystore_to_profile,
     models::{Keystore, Profile},
 };
-use crate::keypair::{processor::prompt, AwsKeypairConfig, KeypairArgs, LocalKeypairConfig};
+use crate::keypair::{
+    processor::prompt, AwsKeypairConfig, GcpKeypairConfig, KeypairArgs, LocalKeypairConfig,
+    VaultKeypairConfig,
+};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn process_add(
     keypair_args: Option<KeypairArgs>,
     aws_config: Option<AwsKeypairConfig>,
     local_config: Option<LocalKeypairConfig>,
+    gcp_config: Option<GcpKeypairConfig>,
+    vault_config: Option<VaultKeypairConfig>,
     profile: Profile,
     profile_name: &str,
     config_path: String,
@@ -32,6 +38,7 @@ pub async fn process_add(
             let AwsKeypairConfig {
                 secret_name,
                 profile: aws_profile,
+                ..
             } = aws_config;
 
             // values will be set by prompt, unwrap safe
@@ -50,6 +57,30 @@ pub async fn process_add(
                 config_path,
             )?;
         }
+        Keystore::AwsKms { .. } => {
+            let aws_config = prompt::prompt_aws_kms_config(aws_config).await?;
+            let AwsKeypairConfig {
+                key_id,
+                profile: aws_profile,
+                ..
+            } = aws_config;
+
+            // values will be set by prompt, unwrap safe
+            let key_id = key_id.unwrap();
+            let aws_profile = aws_profile.unwrap();
+
+            add_keystore_to_profile(
+                profile_name.to_string(),
+                profile,
+                curve,
+                Keystore::AwsKms {
+                    key_id,
+                    profile: aws_profile,
+                },
+                &keystore_name,
+                config_path,
+            )?;
+        }
         Keystore::Local { .. } => {
             let local_config = prompt::prompt_local_config(local_config).await?;
             let LocalKeypairConfig { keystore_path } = local_config;
@@ -68,6 +99,55 @@ pub async fn process_add(
                 config_path,
             )?;
         }
+        Keystore::Gcp { .. } => {
+            let gcp_config = prompt::prompt_gcp_config(gcp_config)?;
+            let GcpKeypairConfig {
+                gcp_project,
+                gcp_secret_name,
+            } = gcp_config;
+
+            // values will be set by prompt, unwrap safe
+            let gcp_project = gcp_project.unwrap();
+            let gcp_secret_name = gcp_secret_name.unwrap();
+
+            add_keystore_to_profile(
+                profile_name.to_string(),
+                profile,
+                curve,
+                Keystore::Gcp {
+                    project: gcp_project,
+                    secret: gcp_secret_name,
+                },
+                &keystore_name,
+                config_path,
+            )?;
+        }
+        Keystore::Vault { .. } => {
+            let vault_config = prompt::prompt_vault_config(vault_config)?;
+            let VaultKeypairConfig {
+                vault_address,
+                vault_mount,
+                vault_path,
+            } = vault_config;
+
+            // values will be set by prompt, unwrap safe
+            let vault_address = vault_address.unwrap();
+            let vault_mount = vault_mount.unwrap();
+            let vault_path = vault_path.unwrap();
+
+            add_keystore_to_profile(
+                profile_name.to_string(),
+                profile,
+                curve,
+                Keystore::Vault {
+                    address: vault_address,
+                    mount: vault_mount,
+                    path: vault_path,
+                },
+                &keystore_name,
+                config_path,
+            )?;
+        }
     }
     Ok(())
 }
@@ -1,15 +1,13 @@
 use std::fs;
 
 use alloy::signers::local::{LocalSigner, PrivateKeySigner};
-use color_eyre::eyre::{self, eyre};
+use color_eyre::eyre;
 use color_eyre::owo_colors::OwoColorize;
-use karak_kms::{
-    keypair::{bn254, traits::Keypair},
-    keystore::{
-        self,
-        aws::AwsKeystoreParams,
-        traits::{AsyncEncryptedKeystore, EncryptedKeystore},
-    },
+use karak_kms::keypair::{
+    bn254::{self, derivation::DerivationPath},
+    mnemonic::{Mnemonic, DEFAULT_SECP256K1_PATH, DEFAULT_WORD_COUNT},
+    secp256k1,
+    traits::Keypair,
 };
 
 use crate::config::add_keystore_to_profile;
@@ -17,14 +15,64 @@ use crate::config::models::{Keystore, Profile};
 use crate::keypair::processor::prompt;
 use crate::{config::models::Curve, keypair::KeypairArgs};
 
+/// A mnemonic to derive the keypair from, instead of generating a random one
+pub struct MnemonicSource {
+    pub mnemonic: Mnemonic,
+    pub derivation_path: Option<String>,
+}
+
+impl MnemonicSource {
+    fn bn254_keypair(&self) -> eyre::Result<bn254::Keypair> {
+        let path = match &self.derivation_path {
+            Some(path) => path.parse()?,
+            None => DerivationPath::dss(0, 0),
+        };
+        Ok(self.mnemonic.bn254_keypair(None, &path)?)
+    }
+
+    fn secp256k1_signer(&self) -> eyre::Result<PrivateKeySigner> {
+        let path = self
+            .derivation_path
+            .as_deref()
+            .unwrap_or(DEFAULT_SECP256K1_PATH);
+        Ok(self.mnemonic.secp256k1_signer(None, path)?)
+    }
+}
+
 pub async fn process_generate(
     keypair_args: Option<KeypairArgs>,
     passphrase: Option<String>,
+    mnemonic: bool,
+    derivation_path: Option<String>,
     profile: Profile,
     profile_name: &str,
     config_path: String,
 ) -> eyre::Result<()> {
-    generate_keystore(keypair_args, passphrase, profile, profile_name, config_path).await?;
+    if !mnemonic {
+        generate_keystore(keypair_args, passphrase, profile, profile_name, config_path).await?;
+        return Ok(());
+    }
+
+    let mnemonic = Mnemonic::generate(DEFAULT_WORD_COUNT)?;
+    println!(
+        "{}",
+        "Write down this mnemonic and keep it safe, it is the only way to recover the keypair:"
+            .yellow()
+    );
+    println!("\n{}\n", mnemonic.phrase());
+
+    store_keystore(
+        keypair_args,
+        passphrase,
+        Some(MnemonicSource {
+            mnemonic,
+            derivation_path,
+        }),
+        profile,
+        profile_name,
+        config_path,
+    )
+    .await?;
     Ok(())
 }
 
@@ -34,6 +82,26 @@ pub async fn generate_keystore(
     profile: Profile,
     profile_name: &str,
     config_path: String,
+) -> eyre::Result<Keystore> {
+    store_keystore(
+        keypair_args,
+        passphrase,
+        None,
+        profile,
+        profile_name,
+        config_path,
+    )
+    .await
+}
+
+/// Stores a random keypair, or the one derived from `mnemonic`, and adds it to the profile
+pub async fn store_keystore(
+    keypair_args: Option<KeypairArgs>,
+    passphrase: Option<String>,
+    mnemonic: Option<MnemonicSource>,
+    profile: Profile,
+    profile_name: &str,
+    config_path: String,
 ) -> eyre::Result<Keystore> {
     let keypair_args = prompt::prompt_keypair_args(keypair_args)?;
 
@@ -53,133 +121,114 @@ pub async fn generate_keystore(
     let generation_folder = profile.clone().key_generation_folder;
 
     println!("Generating new keypair for curve: {:?}", curve);
-    match curve {
+    let keystore = match curve {
         Curve::Bn254 => {
-            let keypair = bn254::Keypair::generate();
+            let keypair = match &mnemonic {
+                Some(source) => source.bn254_keypair()?,
+                None => bn254::Keypair::generate(),
+            };
             println!("Generated BN254 keypair with public key: {keypair}");
 
-            match keystore {
+            let keystore = match keystore {
                 Keystore::Local { path: _ } => {
                     let output_path = generation_folder.join(format!("{keypair}.bls"));
                     fs::File::create(&output_path)?;
-
-                    let local_keystore =
-                        keystore::local::LocalEncryptedKeystore::new(output_path.clone());
-                    local_keystore.store(&keypair, &passphrase)?;
-
-                    let resolved_path = output_path.canonicalize()?;
-                    let resolved_path_str =
-                        resolved_path.to_str().ok_or(eyre!("Path is invalid"))?;
-                    println!("Saved keypair to {resolved_path_str}");
-                    println!("\n{}", "Updating config profile...".blue());
-
-                    let keystore = Keystore::Local {
-                        path: resolved_path,
-                    };
-
-                    add_keystore_to_profile(
-                        profile_name.to_string(),
-                        profile,
-                        curve,
-                        keystore.clone(),
-                        &keystore_name,
-                        config_path,
-                    )?;
-
-                    Ok(keystore)
-                }
-                Keystore::Aws {
-                    secret: _,
-                    profile: _,
-                } => {
-                    let aws_profile_name = prompt::prompt_aws_profile().await?;
-                    let config = aws_config::from_env()
-                        .profile_name(aws_profile_name.clone())
-                        .load()
-                        .await;
-                    let aws_keystore = keystore::aws::AwsEncryptedKeystore::new(&config);
-
-                    let secret_name = format!("{keypair}.bls");
-
-                    aws_keystore
-                        .store(
-                            &keypair,
-                            &passphrase,
-                            &AwsKeystoreParams {
-                                secret_name: secret_name.clone(),
-                            },
-                        )
-                        .await?;
-
-                    println!("Saved keypair to {secret_name} in AWS Secrets Manager");
-                    println!("\n{}", "Updating config profile...".blue());
-
-                    let keystore = Keystore::Aws {
-                        secret: secret_name,
-                        profile: aws_profile_name,
-                    };
-
-                    add_keystore_to_profile(
-                        profile_name.to_string(),
-                        profile,
-                        curve,
-                        keystore.clone(),
-                        &keystore_name,
-                        config_path,
-                    )?;
-
-                    Ok(keystore)
+                    Keystore::Local {
+                        path: output_path.canonicalize()?,
+                    }
                 }
-            }
+                keystore => new_remote_keystore(keystore, &keypair.to_string(), "bls").await?,
+            };
+            keystore
+                .uri()?
+                .resolve()
+                .await?
+                .store(&keypair, &passphrase)
+                .await?;
+            keystore
         }
         Curve::Secp256k1 => {
-            let private_key = PrivateKeySigner::random();
+            let private_key = match &mnemonic {
+                Some(source) => source.secp256k1_signer()?,
+                None => PrivateKeySigner::random(),
+            };
             println!(
                 "Generated SECP256k1 keypair with address: {}",
                 private_key.address()
             );
 
-            let mut rng = rand::thread_rng();
             match keystore {
+                // Local SECP256k1 keypairs are geth keystores
                 Keystore::Local { path: _ } => {
                     let filename = format!("{}.json", private_key.address());
 
                     LocalSigner::encrypt_keystore(
                         generation_folder.clone(),
-                        &mut rng,
+                        &mut rand::thread_rng(),
                         private_key.to_bytes(),
-                        passphrase,
+                        &passphrase,
                         Some(&filename),
                     )?;
 
-                    let resolved_path = generation_folder.join(filename).canonicalize()?;
-                    let resolved_path_str =
-                        resolved_path.to_str().ok_or(eyre!("Path is invalid"))?;
-                    println!("Saved keypair to {resolved_path_str}");
-                    println!("\n{}", "Updating config profile...".blue());
-
-                    let keystore = Keystore::Local {
-                        path: resolved_path,
-                    };
-
-                    add_keystore_to_profile(
-                        profile_name.to_string(),
-                        profile,
-                        curve,
-                        keystore.clone(),
-                        &keystore_name,
-                        config_path,
-                    )?;
-
-                    Ok(keystore)
+                    Keystore::Local {
+                        path: generation_folder.join(filename).canonicalize()?,
+                    }
                 }
-                Keystore::Aws {
-                    secret: _,
-                    profile: _,
-                } => {
-                    todo!()
+                keystore => {
+                    let keypair = secp256k1::Keypair::from(private_key);
+                    let keystore =
+                        new_remote_keystore(keystore, &keypair.to_string(), "ecdsa").await?;
+                    keystore
+                        .uri()?
+                        .resolve()
+                        .await?
+                        .store(&keypair, &passphrase)
+                        .await?;
+                    keystore
                 }
             }
         }
-    }
+    };
+
+    println!("Saved keypair to {}", keystore.uri()?);
+    println!("\n{}", "Updating config profile...".blue());
+
+    add_keystore_to_profile(
+        profile_name.to_string(),
+        profile,
+        curve,
+        keystore.clone(),
+        &keystore_name,
+        config_path,
+    )?;
+
+    Ok(keystore)
+}
+
+/// Prompts for where to store a new keypair in a remote keystore, naming the secret after it
+async fn new_remote_keystore(
+    keystore: Keystore,
+    key: &str,
+    suffix: &str,
+) -> eyre::Result<Keystore> {
+    Ok(match keystore {
+        Keystore::Local { .. } => unreachable!("local keystores are not remote"),
+        Keystore::Aws { .. } => Keystore::Aws {
+            secret: format!("{key}.{suffix}"),
+            profile: prompt::prompt_aws_profile().await?,
+        },
+        // Secret IDs may only contain letters, digits, `-` and `_`
+        Keystore::Gcp { .. } => Keystore::Gcp {
+            project: prompt::prompt_gcp_project()?,
+            secret: format!("{key}-{suffix}"),
+        },
+        Keystore::Vault { .. } => Keystore::Vault {
+            address: prompt::prompt_vault_address()?,
+            mount: prompt::prompt_vault_mount()?,
+            path: format!("karak/{key}.{suffix}"),
+        },
+        Keystore::AwsKms { .. } => {
+            eyre::bail!("keypairs cannot be generated into AWS KMS, create the key in KMS and add it instead")
+        }
+    })
 }
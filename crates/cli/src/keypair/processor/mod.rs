@@ -3,12 +3,16 @@ pub mod generate;
 pub mod list;
 pub mod prompt;
 pub mod pubkey;
+pub mod recover;
+pub mod rekey;
 
 use add::process_add;
 use color_eyre::eyre;
 use generate::process_generate;
 use list::process_list;
 use pubkey::process_pubkey;
+use recover::process_recover;
+use rekey::process_rekey;
 
 use super::Keypair;
 use crate::config::models::Profile;
@@ -23,22 +27,73 @@ pub async fn process(
         Keypair::Generate {
             keypair: keypair_args,
             passphrase,
-        } => process_generate(keypair_args, passphrase, profile, profile_name, config_path).await,
+            mnemonic,
+            derivation_path,
+        } => {
+            process_generate(
+                keypair_args,
+                passphrase,
+                mnemonic,
+                derivation_path,
+                profile,
+                profile_name,
+                config_path,
+            )
+            .await
+        }
+        Keypair::Recover {
+            keypair: keypair_args,
+            passphrase,
+            derivation_path,
+        } => {
+            process_recover(
+                keypair_args,
+                passphrase,
+                derivation_path,
+                profile,
+                profile_name,
+                config_path,
+            )
+            .await
+        }
         Keypair::Pubkey {
             keystore_name,
             passphrase,
             curve,
         } => process_pubkey(profile, keystore_name, passphrase, curve).await,
+        Keypair::Rekey {
+            keystore_name,
+            curve,
+            passphrase,
+            new_passphrase,
+            kdf,
+            cipher,
+        } => {
+            process_rekey(
+                profile,
+                keystore_name,
+                curve,
+                passphrase,
+                new_passphrase,
+                kdf,
+                cipher,
+            )
+            .await
+        }
         Keypair::List { curve } => process_list(profile, curve).await,
         Keypair::Add {
             keypair_args,
             aws_config,
             local_config,
+            gcp_config,
+            vault_config,
         } => {
             process_add(
                 keypair_args,
                 aws_config,
                 local_config,
+                gcp_config,
+                vault_config,
                 profile,
                 profile_name,
                 config_path,
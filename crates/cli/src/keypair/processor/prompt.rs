@@ -6,9 +6,10 @@ use crate::util;
 use crate::{
     config::models::Curve,
     keypair::KeypairArgs,
-    keypair::{AwsKeypairConfig, LocalKeypairConfig},
+    keypair::{AwsKeypairConfig, GcpKeypairConfig, LocalKeypairConfig, VaultKeypairConfig},
     prompter,
 };
+use karak_kms::keystore::hashicorp::DEFAULT_KV_MOUNT;
 
 pub fn prompt_keypair_args(keypair_args: Option<KeypairArgs>) -> eyre::Result<KeypairArgs> {
     match keypair_args {
@@ -62,11 +63,85 @@ pub async fn prompt_aws_config(
             Ok(AwsKeypairConfig {
                 secret_name: Some(aws_secret_name),
                 profile: Some(aws_profile),
+                key_id: None,
             })
         }
     }
 }
 
+pub async fn prompt_aws_kms_config(
+    aws_config: Option<AwsKeypairConfig>,
+) -> eyre::Result<AwsKeypairConfig> {
+    match aws_config {
+        Some(ac) => Ok(ac),
+        None => {
+            let aws_profile = prompt_aws_profile().await?;
+            let key_id = prompter::input::<String>("Enter AWS KMS key ID", None, None)?;
+            Ok(AwsKeypairConfig {
+                secret_name: None,
+                profile: Some(aws_profile),
+                key_id: Some(key_id),
+            })
+        }
+    }
+}
+
+pub fn prompt_gcp_config(gcp_config: Option<GcpKeypairConfig>) -> eyre::Result<GcpKeypairConfig> {
+    match gcp_config {
+        Some(gc) => Ok(gc),
+        None => {
+            let gcp_project = prompt_gcp_project()?;
+            let gcp_secret_name = prompter::input::<String>("Enter GCP secret name", None, None)?;
+            Ok(GcpKeypairConfig {
+                gcp_project: Some(gcp_project),
+                gcp_secret_name: Some(gcp_secret_name),
+            })
+        }
+    }
+}
+
+pub fn prompt_gcp_project() -> eyre::Result<String> {
+    prompter::input::<String>(
+        "Enter GCP project ID",
+        std::env::var("GOOGLE_CLOUD_PROJECT").ok(),
+        None,
+    )
+}
+
+pub fn prompt_vault_config(
+    vault_config: Option<VaultKeypairConfig>,
+) -> eyre::Result<VaultKeypairConfig> {
+    match vault_config {
+        Some(vc) => Ok(vc),
+        None => {
+            let vault_address = prompt_vault_address()?;
+            let vault_mount = prompt_vault_mount()?;
+            let vault_path = prompter::input::<String>("Enter Vault secret path", None, None)?;
+            Ok(VaultKeypairConfig {
+                vault_address: Some(vault_address),
+                vault_mount: Some(vault_mount),
+                vault_path: Some(vault_path),
+            })
+        }
+    }
+}
+
+pub fn prompt_vault_address() -> eyre::Result<String> {
+    prompter::input::<String>(
+        "Enter Vault address",
+        std::env::var("VAULT_ADDR").ok(),
+        None,
+    )
+}
+
+pub fn prompt_vault_mount() -> eyre::Result<String> {
+    prompter::input::<String>(
+        "Enter Vault KV v2 mount",
+        Some(DEFAULT_KV_MOUNT.to_string()),
+        None,
+    )
+}
+
 pub async fn prompt_local_config(
     local_config: Option<LocalKeypairConfig>,
 ) -> eyre::Result<LocalKeypairConfig> {
@@ -90,6 +165,13 @@ pub fn prompt_passphrase(passphrase: Option<String>) -> eyre::Result<String> {
     }
 }
 
+pub fn prompt_new_passphrase(new_passphrase: Option<String>) -> eyre::Result<String> {
+    match new_passphrase {
+        Some(p) => Ok(p),
+        None => prompter::new_password("Enter new keypair passphrase"),
+    }
+}
+
 pub fn prompt_keystore_name(
     keystore_name: Option<String>,
     keystores: HashMap<String, Keystore>,
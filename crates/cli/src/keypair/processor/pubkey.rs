@@ -1,13 +1,6 @@
 use alloy::signers::local::LocalSigner;
 use color_eyre::eyre;
-use karak_kms::{
-    keypair::bn254,
-    keystore::{
-        self,
-        aws::AwsKeystoreParams,
-        traits::{AsyncEncryptedKeystore, EncryptedKeystore},
-    },
-};
+use karak_kms::keypair::{bn254, secp256k1};
 
 use crate::config::models::{Curve, Keystore, Profile};
 
@@ -34,48 +27,24 @@ pub async fn process_pubkey(
     }
     // unwrapping safe since keystore none is checked above
     let keystore = keystore.unwrap();
-
-    match curve {
-        Curve::Bn254 => match keystore {
-            Keystore::Local { path: p } => {
-                let local_keystore = keystore::local::LocalEncryptedKeystore::new(p.to_owned());
-
-                let keypair: bn254::Keypair = local_keystore.retrieve(&passphrase)?;
-
-                println!("Public Key (retrieved from local keystore): {keypair}");
-            }
-            Keystore::Aws {
-                secret: s,
-                profile: p,
-            } => {
-                let config = aws_config::from_env().profile_name(p).load().await;
-                let aws_keystore = keystore::aws::AwsEncryptedKeystore::new(&config);
-
-                let keypair: bn254::Keypair = aws_keystore
-                    .retrieve(
-                        &passphrase,
-                        &AwsKeystoreParams {
-                            secret_name: s.to_owned(),
-                        },
-                    )
-                    .await?;
-
-                println!("Public Key (retrieved from AWS Secrets Manager): {keypair}");
-            }
-        },
-        Curve::Secp256k1 => match keystore {
-            Keystore::Local { path: p } => {
-                let private_key = LocalSigner::decrypt_keystore(p, passphrase)?;
-                println!(
-                    "Address (retrieved from local keystore): {}",
-                    private_key.address()
-                );
-            }
-            Keystore::Aws {
-                secret: _,
-                profile: _,
-            } => todo!(),
-        },
+    let uri = keystore.uri()?;
+
+    match (curve, keystore) {
+        (Curve::Bn254, _) => {
+            let keypair: bn254::Keypair = uri.resolve().await?.retrieve(&passphrase).await?;
+
+            println!("Public Key (retrieved from {uri}): {keypair}");
+        }
+        // Local SECP256k1 keypairs are geth keystores
+        (Curve::Secp256k1, Keystore::Local { path: p }) => {
+            let private_key = LocalSigner::decrypt_keystore(p, passphrase)?;
+            println!("Address (retrieved from {uri}): {}", private_key.address());
+        }
+        (Curve::Secp256k1, _) => {
+            let keypair: secp256k1::Keypair = uri.resolve().await?.retrieve(&passphrase).await?;
+
+            println!("Address (retrieved from {uri}): {keypair}");
+        }
     }
     Ok(())
 }
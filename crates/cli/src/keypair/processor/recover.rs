@@ -0,0 +1,33 @@
+use color_eyre::eyre;
+use karak_kms::keypair::mnemonic::Mnemonic;
+
+use crate::config::models::Profile;
+use crate::keypair::processor::generate::{store_keystore, MnemonicSource};
+use crate::keypair::KeypairArgs;
+use crate::prompter;
+
+pub async fn process_recover(
+    keypair_args: Option<KeypairArgs>,
+    passphrase: Option<String>,
+    derivation_path: Option<String>,
+    profile: Profile,
+    profile_name: &str,
+    config_path: String,
+) -> eyre::Result<()> {
+    let phrase = prompter::password("Enter mnemonic phrase")?;
+    let mnemonic = Mnemonic::from_phrase(&phrase)?;
+
+    store_keystore(
+        keypair_args,
+        passphrase,
+        Some(MnemonicSource {
+            mnemonic,
+            derivation_path,
+        }),
+        profile,
+        profile_name,
+        config_path,
+    )
+    .await?;
+    Ok(())
+}
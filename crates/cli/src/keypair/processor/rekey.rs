@@ -0,0 +1,111 @@
+use std::fs;
+
+use alloy::signers::local::LocalSigner;
+use color_eyre::eyre::{self, bail, eyre};
+use karak_kms::{
+    encryption::{self, EncryptionParams, KdfParams},
+    keypair::{bn254, secp256k1},
+};
+
+use crate::config::models::{Curve, Keystore, Profile};
+use crate::keypair::{Cipher, Kdf};
+
+use super::prompt;
+
+// OWASP's recommendation for PBKDF2-HMAC-SHA256
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+fn encryption_params(kdf: Kdf, cipher: Option<Cipher>) -> EncryptionParams {
+    let kdf = match kdf {
+        Kdf::Scrypt => KdfParams::default(),
+        Kdf::Pbkdf2 => KdfParams::Pbkdf2 {
+            iterations: PBKDF2_ITERATIONS,
+        },
+        // The second recommended option of RFC 9106, for memory constrained environments
+        Kdf::Argon2id => KdfParams::Argon2id {
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
+        },
+    };
+    let cipher = cipher.map(|cipher| match cipher {
+        Cipher::Aes256Gcm => encryption::Cipher::Aes256Gcm,
+        Cipher::XChaCha20Poly1305 => encryption::Cipher::XChaCha20Poly1305,
+    });
+    EncryptionParams { kdf, cipher }
+}
+
+pub async fn process_rekey(
+    profile: Profile,
+    keystore_name: Option<String>,
+    curve: Option<Curve>,
+    passphrase: Option<String>,
+    new_passphrase: Option<String>,
+    kdf: Kdf,
+    cipher: Option<Cipher>,
+) -> eyre::Result<()> {
+    let curve = prompt::prompt_curve(curve)?;
+    let keystores = profile
+        .keystores
+        .get(&curve)
+        .ok_or(eyre!("No keystores found for curve {}", curve))?;
+    let keystore_name = prompt::prompt_keystore_name(keystore_name, keystores.clone())?;
+    let keystore = keystores
+        .get(&keystore_name)
+        .ok_or(eyre!("Keystore for name {} not found", keystore_name))?;
+
+    let passphrase = prompt::prompt_passphrase(passphrase)?;
+    let new_passphrase = prompt::prompt_new_passphrase(new_passphrase)?;
+    let params = encryption_params(kdf, cipher);
+
+    match (curve, keystore) {
+        // Local SECP256k1 keypairs are geth keystores
+        (Curve::Secp256k1, Keystore::Local { path }) => {
+            if let Some(cipher) = cipher {
+                bail!("geth keystores always use AES-128-CTR, {cipher} is not supported");
+            }
+            let private_key = LocalSigner::decrypt_keystore(path, &passphrase)?;
+
+            // Written next to the keystore and renamed over it, so a failure leaves it intact
+            let dir = path.parent().ok_or(eyre!("Keystore path is invalid"))?;
+            let file_name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or(eyre!("Keystore path is invalid"))?;
+            let tmp_name = format!(".{file_name}.tmp");
+            LocalSigner::encrypt_keystore(
+                dir,
+                &mut rand::thread_rng(),
+                private_key.to_bytes(),
+                &new_passphrase,
+                Some(&tmp_name),
+            )?;
+            fs::rename(dir.join(tmp_name), path)?;
+        }
+        (Curve::Secp256k1, _) => {
+            keystore
+                .uri()?
+                .resolve::<secp256k1::Keypair>()
+                .await?
+                .rekey(&passphrase, &new_passphrase, &params)
+                .await?
+        }
+        (Curve::Bn254, _) => {
+            keystore
+                .uri()?
+                .resolve::<bn254::Keypair>()
+                .await?
+                .rekey(&passphrase, &new_passphrase, &params)
+                .await?
+        }
+    }
+
+    println!("Re-encrypted keypair {keystore_name} with the new passphrase");
+    if !matches!(keystore, Keystore::Local { .. }) {
+        println!(
+            "Earlier versions of the secret are still encrypted with the old passphrase, destroy \
+             them if it may have been compromised"
+        );
+    }
+    Ok(())
+}
@@ -6,15 +6,12 @@ use alloy::signers::k256::ecdsa::signature::SignerMut;
 use alloy::transports::Transport;
 use color_eyre::eyre;
 use karak_contracts::Core::CoreInstance;
-use karak_kms::{
-    keypair::{
-        bn254::{
-            self,
-            bls::registration::{BlsRegistration, OperatorRegistration},
-        },
-        traits::Keypair,
+use karak_kms::keypair::{
+    bn254::{
+        self,
+        bls::registration::{BlsRegistration, OperatorRegistration},
     },
-    keystore::{self, traits::EncryptedKeystore},
+    traits::Keypair,
 };
 
 pub struct DSSRegistrationArgs<'a, T: Transport + Clone, P: Provider<T>> {
@@ -30,16 +27,13 @@ pub struct DSSRegistrationArgs<'a, T: Transport + Clone, P: Provider<T>> {
 pub async fn process_registration<T: Transport + Clone, P: Provider<T>>(
     args: DSSRegistrationArgs<'_, T, P>,
 ) -> eyre::Result<()> {
-    let mut bn254_keypair: bn254::Keypair = match args.bn254_keystore {
-        Keystore::Local { path: p } => {
-            let local_keystore = keystore::local::LocalEncryptedKeystore::new(p.clone());
-            local_keystore.retrieve(args.bn254_passphrase)?
-        }
-        Keystore::Aws {
-            secret: _,
-            profile: _,
-        } => todo!(),
-    };
+    let mut bn254_keypair: bn254::Keypair = args
+        .bn254_keystore
+        .uri()?
+        .resolve()
+        .await?
+        .retrieve(args.bn254_passphrase)
+        .await?;
 
     // TODO: Get this value from the DSS contract not from the args
     let msg_bytes = args.message_encoding.decode(args.message)?;
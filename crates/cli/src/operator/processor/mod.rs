@@ -10,12 +10,17 @@ use alloy::{
     network::EthereumWallet,
     primitives::{aliases::U48, Address, U256},
     providers::ProviderBuilder,
-    signers::{aws::AwsSigner, local::LocalSigner, Signer},
+    signers::{
+        aws::AwsSigner,
+        local::{LocalSigner, PrivateKeySigner},
+        Signer,
+    },
 };
 use karak_contracts::{
     erc20::contract::ERC20::ERC20Instance, registry::RestakingRegistry,
     vault::Vault::VaultInstance, Core::CoreInstance,
 };
+use karak_kms::keypair::secp256k1;
 
 #[cfg(feature = "testnet")]
 use karak_contracts::erc20::mintable::ERC20Mintable::ERC20MintableInstance;
@@ -44,6 +49,17 @@ pub async fn process(
             }
         }
         Some(Keystore::Aws { secret, profile }) => Keystore::Aws { secret, profile },
+        Some(Keystore::AwsKms { key_id, profile }) => Keystore::AwsKms { key_id, profile },
+        Some(Keystore::Gcp { project, secret }) => Keystore::Gcp { project, secret },
+        Some(Keystore::Vault {
+            address,
+            mount,
+            path,
+        }) => Keystore::Vault {
+            address,
+            mount,
+            path,
+        },
         None => {
             prompt_keystore_type(
                 Curve::Secp256k1,
@@ -68,16 +84,31 @@ pub async fn process(
             let operator_wallet = EthereumWallet::from(secp_256k1_signer);
             (operator_wallet, operator_address)
         }
-        // TODO: Update config to handle AWS secret and access keys
-        Keystore::Aws { secret, profile } => {
+        Keystore::AwsKms { key_id, profile } => {
             let aws_config = aws_config::from_env().profile_name(profile).load().await;
-
             let client = aws_sdk_kms::Client::new(&aws_config);
-            let signer = AwsSigner::new(client, secret, None).await?;
-
+            let signer = AwsSigner::new(client, key_id, None).await?;
             let operator_address = signer.address();
             let operator_wallet = EthereumWallet::from(signer);
+            (operator_wallet, operator_address)
+        }
+        // Secrets holding an encrypted keypair, as written by `karak keypair generate`
+        keystore @ (Keystore::Aws { .. } | Keystore::Gcp { .. } | Keystore::Vault { .. }) => {
+            let secp256k1_passphrase = match args.secp256k1_passphrase {
+                Some(passphrase) => passphrase,
+                None => prompt_secp256k1_passphrase()?,
+            };
+
+            let keypair: secp256k1::Keypair = keystore
+                .uri()?
+                .resolve()
+                .await?
+                .retrieve(&secp256k1_passphrase)
+                .await?;
 
+            let secp_256k1_signer = PrivateKeySigner::from(keypair);
+            let operator_address = secp_256k1_signer.address();
+            let operator_wallet = EthereumWallet::from(secp_256k1_signer);
             (operator_wallet, operator_address)
         }
     };
@@ -110,6 +141,19 @@ pub async fn process(
                     }
                 }
                 Some(Keystore::Aws { secret, profile }) => Keystore::Aws { secret, profile },
+                Some(Keystore::AwsKms { .. }) => {
+                    eyre::bail!("AWS KMS does not support BN254 keys")
+                }
+                Some(Keystore::Gcp { project, secret }) => Keystore::Gcp { project, secret },
+                Some(Keystore::Vault {
+                    address,
+                    mount,
+                    path,
+                }) => Keystore::Vault {
+                    address,
+                    mount,
+                    path,
+                },
                 None => {
                     prompt_keystore_type(
                         Curve::Bn254,
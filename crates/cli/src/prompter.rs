@@ -78,6 +78,15 @@ pub fn password(prompt: &str) -> eyre::Result<String> {
         .map_err(|e| eyre::eyre!(e))
 }
 
+/// Asks for the password twice so that a typo does not lock the user out
+pub fn new_password(prompt: &str) -> eyre::Result<String> {
+    Password::new()
+        .with_prompt(prompt)
+        .with_confirmation("Confirm passphrase", "Passphrases do not match")
+        .interact()
+        .map_err(|e| eyre::eyre!(e))
+}
+
 pub fn multi_select<T: ToString>(prompt: &str, items: &[T]) -> eyre::Result<Vec<usize>> {
     println!("(Use <space> to select, <enter> to confirm)");
     MultiSelect::with_theme(&ColorfulTheme::default())
@@ -28,7 +28,7 @@ pub struct Root {
 pub enum Command {
     /// Keypair management
     #[command(subcommand)]
-    Keypair(Keypair),
+    Keypair(Box<Keypair>),
 
     /// BLS operations
     #[cfg(feature = "bls")]
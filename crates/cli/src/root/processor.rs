@@ -30,8 +30,13 @@ pub async fn process(root: Root) -> eyre::Result<()> {
 
             match root.command {
                 Some(Command::Keypair(keypair)) => {
-                    keypair::processor::process(keypair, profile, profile_name, config_path.clone())
-                        .await
+                    keypair::processor::process(
+                        *keypair,
+                        profile,
+                        profile_name,
+                        config_path.clone(),
+                    )
+                    .await
                 }
 
                 #[cfg(feature = "bls")]
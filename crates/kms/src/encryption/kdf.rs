@@ -0,0 +1,219 @@
+use argon2::{Algorithm, Argon2, Version};
+use scrypt::{scrypt, Params};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+use super::EncryptionError;
+
+/// Upper bounds on the parameters read from a payload, so that a corrupted or malicious keystore
+/// cannot make key derivation allocate unbounded memory or run practically forever
+pub const MAX_KDF_MEMORY_BYTES: u64 = 4 << 30;
+pub const MAX_KDF_PARALLELISM: u32 = 64;
+pub const MAX_KDF_ITERATIONS: u32 = 1 << 24;
+
+/// Key derivation function used to turn a passphrase into the encryption key
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KdfParams {
+    Scrypt {
+        log_n: u8,
+        r: u32,
+        p: u32,
+    },
+    /// PBKDF2-HMAC-SHA256
+    Pbkdf2 {
+        iterations: u32,
+    },
+    Argon2id {
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    },
+}
+
+impl KdfParams {
+    pub const SCRYPT_KDF: &'static str = "scrypt";
+    pub const PBKDF2_KDF: &'static str = "pbkdf2";
+    pub const ARGON2ID_KDF: &'static str = "argon2id";
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KdfParams::Scrypt { .. } => Self::SCRYPT_KDF,
+            KdfParams::Pbkdf2 { .. } => Self::PBKDF2_KDF,
+            KdfParams::Argon2id { .. } => Self::ARGON2ID_KDF,
+        }
+    }
+
+    pub(crate) fn derive_key(
+        &self,
+        auth: &[u8],
+        salt: &[u8],
+        dklen: usize,
+    ) -> Result<Zeroizing<Vec<u8>>, EncryptionError> {
+        self.check_limits()?;
+        let mut derived_key = Zeroizing::new(vec![0u8; dklen]);
+
+        match *self {
+            KdfParams::Scrypt { log_n, r, p } => {
+                scrypt(
+                    auth,
+                    salt,
+                    &Params::new(log_n, r, p, dklen)?,
+                    &mut derived_key,
+                )?;
+            }
+            KdfParams::Pbkdf2 { iterations } => {
+                if iterations == 0 {
+                    return Err(EncryptionError::InvalidKdfParams(
+                        "PBKDF2 iterations must be non-zero".to_string(),
+                    ));
+                }
+                pbkdf2::pbkdf2_hmac::<Sha256>(auth, salt, iterations, &mut derived_key);
+            }
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                let params = argon2::Params::new(memory_kib, iterations, parallelism, Some(dklen))?;
+                Argon2::new(Algorithm::Argon2id, Version::V0x13, params).hash_password_into(
+                    auth,
+                    salt,
+                    &mut derived_key,
+                )?;
+            }
+        }
+
+        Ok(derived_key)
+    }
+
+    fn check_limits(&self) -> Result<(), EncryptionError> {
+        let exceeds = |what: &str| {
+            Err(EncryptionError::InvalidKdfParams(format!(
+                "{} {what} exceeds the supported maximum",
+                self.name()
+            )))
+        };
+
+        match *self {
+            KdfParams::Scrypt { log_n, r, p } => {
+                // scrypt needs 128 * r * 2^log_n bytes
+                let memory = 2u64
+                    .checked_pow(log_n.into())
+                    .and_then(|n| n.checked_mul(128 * u64::from(r)));
+                if memory.map_or(true, |memory| memory > MAX_KDF_MEMORY_BYTES) {
+                    return exceeds("memory");
+                }
+                if p > MAX_KDF_PARALLELISM {
+                    return exceeds("parallelism");
+                }
+            }
+            KdfParams::Pbkdf2 { iterations } => {
+                if iterations > MAX_KDF_ITERATIONS {
+                    return exceeds("iterations");
+                }
+            }
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => {
+                if u64::from(memory_kib) * 1024 > MAX_KDF_MEMORY_BYTES {
+                    return exceeds("memory");
+                }
+                if iterations > MAX_KDF_ITERATIONS {
+                    return exceeds("iterations");
+                }
+                if parallelism > MAX_KDF_PARALLELISM {
+                    return exceeds("parallelism");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// geth's standard scrypt parameters
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams::Scrypt {
+            log_n: 18,
+            r: 8,
+            p: 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_kdf_params() {
+        assert!(matches!(
+            KdfParams::Pbkdf2 { iterations: 0 }.derive_key(b"passphrase", b"salt", 32),
+            Err(EncryptionError::InvalidKdfParams(_))
+        ));
+        assert!(matches!(
+            KdfParams::Argon2id {
+                memory_kib: 1,
+                iterations: 1,
+                parallelism: 1,
+            }
+            .derive_key(b"passphrase", b"saltsalt", 32),
+            Err(EncryptionError::Argon2Error(_))
+        ));
+        assert!(matches!(
+            KdfParams::Scrypt {
+                log_n: 10,
+                r: 8,
+                p: 0
+            }
+            .derive_key(b"passphrase", b"salt", 32),
+            Err(EncryptionError::InvalidParams(_))
+        ));
+    }
+
+    #[test]
+    fn test_kdf_limits() {
+        let too_expensive = [
+            KdfParams::Scrypt {
+                log_n: 63,
+                r: 8,
+                p: 1,
+            },
+            KdfParams::Scrypt {
+                log_n: 23,
+                r: 8,
+                p: 1,
+            },
+            KdfParams::Scrypt {
+                log_n: 10,
+                r: 8,
+                p: u32::MAX,
+            },
+            KdfParams::Pbkdf2 {
+                iterations: u32::MAX,
+            },
+            KdfParams::Argon2id {
+                memory_kib: u32::MAX,
+                iterations: 1,
+                parallelism: 1,
+            },
+            KdfParams::Argon2id {
+                memory_kib: 64,
+                iterations: u32::MAX,
+                parallelism: 1,
+            },
+        ];
+        for params in too_expensive {
+            assert!(matches!(
+                params.derive_key(b"passphrase", b"saltsalt", 32),
+                Err(EncryptionError::InvalidKdfParams(_))
+            ));
+        }
+
+        assert!(KdfParams::default().check_limits().is_ok());
+    }
+}
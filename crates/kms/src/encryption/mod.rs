@@ -1,140 +1,206 @@
-use aes::{
-    cipher::{KeyIvInit, StreamCipher},
-    Aes128,
-};
-use ctr::Ctr64BE;
-use hex::encode;
-use rand::RngCore;
-use scrypt::{scrypt, Params};
-use serde::{Deserialize, Serialize};
-use sha3::{Digest, Keccak256};
-use std::collections::HashMap;
+mod kdf;
+mod v3;
+mod v4;
+
+pub use kdf::*;
+pub use v3::*;
+pub use v4::*;
+
 use thiserror::Error;
+use zeroize::Zeroizing;
 
-type Aes128Ctr64BE = Ctr64BE<Aes128>;
-const SCRYPT_DKLEN: usize = 32;
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct EncryptDataV3Payload {
-    pub cipher: String,
-    pub cipher_text: String,
-    pub cipher_params: HashMap<String, String>,
-    pub kdf: String,
-    pub kdf_params: HashMap<String, String>,
-    pub mac: String,
-}
+const DKLEN: usize = 32;
 
+/// Marks a versioned payload, a legacy v3 payload starts with the length of its cipher name
+const PAYLOAD_MAGIC: &[u8; 3] = b"KRK";
+
+#[deprecated(note = "renamed to `EncryptionError`")]
+pub type EncryptDataV3Error = EncryptionError;
+
+/// Errors from encrypting or decrypting a payload of any version
 #[derive(Debug, Error)]
-pub enum EncryptDataV3Error {
+pub enum EncryptionError {
     #[error("Invalid output length")]
     InvalidOutputLen(#[from] scrypt::errors::InvalidOutputLen),
     #[error("Invalid scrypt parameters")]
     InvalidParams(#[from] scrypt::errors::InvalidParams),
+    #[error("Argon2 error: {0}")]
+    Argon2Error(#[from] argon2::Error),
+    #[error("Invalid KDF parameters: {0}")]
+    InvalidKdfParams(String),
+    #[error("Unsupported KDF {0}")]
+    UnsupportedKdf(String),
+    #[error("Unsupported cipher {0}")]
+    UnsupportedCipher(String),
+    #[error("Unsupported payload version {0}")]
+    UnsupportedVersion(u8),
+    #[error("Missing parameter {0}")]
+    MissingParam(String),
+    #[error("Invalid {field} length, expected {expected} bytes, got {actual}")]
+    InvalidLength {
+        field: &'static str,
+        expected: usize,
+        actual: usize,
+    },
     #[error("Invalid cipher length")]
     InvalidCipherLength(aes::cipher::InvalidLength),
     #[error("Parse error: {0}")]
     ParseError(#[from] std::num::ParseIntError),
     #[error("Failed to decode hex string: {0}")]
     HexDecodeError(#[from] hex::FromHexError),
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] bincode::Error),
+    #[error("Encryption failed")]
+    EncryptionFailed,
     #[error("MAC verification failed")]
     MacVerificationFailed,
 }
 
-impl From<aes::cipher::InvalidLength> for EncryptDataV3Error {
+impl From<aes::cipher::InvalidLength> for EncryptionError {
     fn from(_: aes::cipher::InvalidLength) -> Self {
-        EncryptDataV3Error::InvalidCipherLength(aes::cipher::InvalidLength)
+        EncryptionError::InvalidCipherLength(aes::cipher::InvalidLength)
     }
 }
 
-pub fn encrypt_data_v3(
-    data: &[u8],
-    auth: &[u8],
-    scrypt_log_n: u8,
-    scrypt_p: u32,
-) -> Result<EncryptDataV3Payload, EncryptDataV3Error> {
-    let scrypt_r: u32 = 8;
-
-    let mut salt = [0u8; 32];
-    let mut rng = rand::thread_rng();
-    rng.fill_bytes(&mut salt);
-
-    let mut derived_key = [0u8; SCRYPT_DKLEN];
-    let scrypt_params = Params::new(scrypt_log_n, scrypt_r, scrypt_p, SCRYPT_DKLEN)?;
-    scrypt(auth, &salt, &scrypt_params, &mut derived_key)?;
-
-    let encrypt_key = &derived_key[..16];
-
-    let mut iv = [0u8; 16];
-    rng.fill_bytes(&mut iv);
-
-    let mut cipher = Aes128Ctr64BE::new_from_slices(encrypt_key, &iv)?;
-    let mut cipher_text = data.to_vec();
-    cipher.apply_keystream(&mut cipher_text);
-
-    let mac = {
-        let mut hasher = Keccak256::new();
-        hasher.update(&derived_key[16..32]);
-        hasher.update(&cipher_text);
-        hasher.finalize()
-    };
-
-    let mut scrypt_params_json = HashMap::new();
-    scrypt_params_json.insert("log_n".to_string(), scrypt_log_n.to_string());
-    scrypt_params_json.insert("r".to_string(), scrypt_r.to_string());
-    scrypt_params_json.insert("p".to_string(), scrypt_p.to_string());
-    scrypt_params_json.insert("dklen".to_string(), SCRYPT_DKLEN.to_string());
-    scrypt_params_json.insert("salt".to_string(), encode(salt));
-
-    let mut cipher_params_json = HashMap::new();
-    cipher_params_json.insert("iv".to_string(), encode(iv));
-
-    let crypto_struct = EncryptDataV3Payload {
-        cipher: "aes-128-ctr".to_string(),
-        cipher_text: encode(cipher_text),
-        cipher_params: cipher_params_json,
-        kdf: "scrypt".to_string(),
-        kdf_params: scrypt_params_json,
-        mac: encode(mac),
-    };
-
-    Ok(crypto_struct)
+/// How new payloads are encrypted
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EncryptionParams {
+    pub kdf: KdfParams,
+    /// `None` leaves the cipher to the format, which is AES-256-GCM for the latest payload version
+    pub cipher: Option<Cipher>,
 }
 
-pub fn decrypt_data_v3(
-    crypto: &EncryptDataV3Payload,
-    auth: &[u8],
-) -> Result<Vec<u8>, EncryptDataV3Error> {
-    // Extract parameters from the crypto struct
-    let scrypt_log_n: u8 = crypto.kdf_params["log_n"].parse()?;
-    let scrypt_r: u32 = crypto.kdf_params["r"].parse()?;
-    let scrypt_p: u32 = crypto.kdf_params["p"].parse()?;
-    let scrypt_dklen: usize = crypto.kdf_params["dklen"].parse()?;
-    let salt = hex::decode(&crypto.kdf_params["salt"])?;
-    let iv = hex::decode(&crypto.cipher_params["iv"])?;
-
-    // Derive the key using scrypt
-    let scrypt_params = Params::new(scrypt_log_n, scrypt_r, scrypt_p, scrypt_dklen)?;
-    let mut derived_key = vec![0u8; scrypt_dklen];
-    scrypt(auth, &salt, &scrypt_params, &mut derived_key)?;
-
-    let decrypt_key = &derived_key[..16];
-    let mac_key = &derived_key[16..32];
-
-    // Verify the MAC
-    let cipher_text = hex::decode(&crypto.cipher_text)?;
-    let mut hasher = Keccak256::new();
-    hasher.update(mac_key);
-    hasher.update(&cipher_text);
-    let expected_mac = hasher.finalize();
-
-    if expected_mac.as_slice() != hex::decode(&crypto.mac)? {
-        return Err(EncryptDataV3Error::MacVerificationFailed);
+impl From<KdfParams> for EncryptionParams {
+    fn from(kdf: KdfParams) -> Self {
+        Self { kdf, cipher: None }
     }
+}
 
-    // Decrypt the cipher text
-    let mut cipher = Aes128Ctr64BE::new_from_slices(decrypt_key, &iv)?;
-    let mut plain_text = cipher_text.to_vec();
-    cipher.apply_keystream(&mut plain_text);
+/// An encrypted secret in any supported payload version
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncryptedPayload {
+    V3(EncryptDataV3Payload),
+    V4(EncryptDataV4Payload),
+}
+
+impl EncryptedPayload {
+    /// New payloads are always the latest version
+    pub fn encrypt(
+        data: &[u8],
+        auth: &[u8],
+        params: &EncryptionParams,
+    ) -> Result<Self, EncryptionError> {
+        Ok(EncryptedPayload::V4(encrypt_data_v4(
+            data,
+            auth,
+            &params.kdf,
+            params.cipher.unwrap_or_default(),
+        )?))
+    }
+
+    pub fn version(&self) -> u8 {
+        match self {
+            EncryptedPayload::V3(_) => 3,
+            EncryptedPayload::V4(_) => 4,
+        }
+    }
 
-    Ok(plain_text)
+    pub fn decrypt(&self, auth: &[u8]) -> Result<Zeroizing<Vec<u8>>, EncryptionError> {
+        match self {
+            EncryptedPayload::V3(payload) => decrypt_data_v3(payload, auth),
+            EncryptedPayload::V4(payload) => decrypt_data_v4(payload, auth),
+        }
+    }
+
+    /// v3 payloads are plain bincode, later versions are prefixed with a magic and their version
+    pub fn to_bytes(&self) -> Result<Vec<u8>, EncryptionError> {
+        match self {
+            EncryptedPayload::V3(payload) => Ok(bincode::serialize(payload)?),
+            EncryptedPayload::V4(payload) => {
+                let mut bytes = PAYLOAD_MAGIC.to_vec();
+                bytes.push(self.version());
+                bincode::serialize_into(&mut bytes, payload)?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, EncryptionError> {
+        let Some(versioned) = bytes.strip_prefix(PAYLOAD_MAGIC) else {
+            return Ok(EncryptedPayload::V3(bincode::deserialize(bytes)?));
+        };
+
+        match versioned.split_first() {
+            Some((4, payload)) => Ok(EncryptedPayload::V4(bincode::deserialize(payload)?)),
+            Some((version, _)) => Err(EncryptionError::UnsupportedVersion(*version)),
+            None => Err(EncryptionError::InvalidLength {
+                field: "payload",
+                expected: PAYLOAD_MAGIC.len() + 1,
+                actual: bytes.len(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIGHT_KDFS: [KdfParams; 3] = [
+        KdfParams::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        },
+        KdfParams::Pbkdf2 { iterations: 1_000 },
+        KdfParams::Argon2id {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        },
+    ];
+
+    #[test]
+    fn test_roundtrip_with_each_kdf() {
+        for kdf in LIGHT_KDFS {
+            let v3 = EncryptedPayload::V3(encrypt_data_v3(b"secret", b"passphrase", &kdf).unwrap());
+            let v4 = EncryptedPayload::encrypt(b"secret", b"passphrase", &kdf.into()).unwrap();
+
+            for payload in [v3, v4] {
+                let decoded = EncryptedPayload::from_bytes(&payload.to_bytes().unwrap()).unwrap();
+                assert_eq!(decoded, payload);
+                assert_eq!(
+                    decoded.decrypt(b"passphrase").unwrap().as_slice(),
+                    b"secret"
+                );
+                assert!(matches!(
+                    decoded.decrypt(b"wrong passphrase"),
+                    Err(EncryptionError::MacVerificationFailed)
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn test_payload_versions() {
+        let payload =
+            EncryptedPayload::encrypt(b"secret", b"passphrase", &LIGHT_KDFS[1].into()).unwrap();
+        assert_eq!(payload.version(), 4);
+
+        let mut bytes = payload.to_bytes().unwrap();
+        assert!(bytes.starts_with(PAYLOAD_MAGIC));
+        bytes[PAYLOAD_MAGIC.len()] = 5;
+        assert!(matches!(
+            EncryptedPayload::from_bytes(&bytes),
+            Err(EncryptionError::UnsupportedVersion(5))
+        ));
+        assert!(matches!(
+            EncryptedPayload::from_bytes(PAYLOAD_MAGIC),
+            Err(EncryptionError::InvalidLength { .. })
+        ));
+        assert!(matches!(
+            EncryptedPayload::from_bytes(&[0; 8]),
+            Err(EncryptionError::SerializationError(_))
+        ));
+    }
 }
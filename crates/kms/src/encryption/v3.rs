@@ -0,0 +1,280 @@
+use aes::{
+    cipher::{KeyIvInit, StreamCipher},
+    Aes128,
+};
+use ctr::Ctr64BE;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use std::collections::HashMap;
+use zeroize::Zeroizing;
+
+use super::{EncryptionError, KdfParams, DKLEN};
+
+type Aes128Ctr64BE = Ctr64BE<Aes128>;
+
+const CIPHER: &str = "aes-128-ctr";
+const PBKDF2_PRF: &str = "hmac-sha256";
+
+/// The legacy payload, AES-128-CTR with a Keccak-256 MAC over the ciphertext
+///
+/// Serialized as string maps of parameters so that existing keystores keep decoding.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawV3Payload", into = "RawV3Payload")]
+pub struct EncryptDataV3Payload {
+    pub kdf: KdfParams,
+    pub salt: Vec<u8>,
+    pub iv: [u8; 16],
+    pub cipher_text: Vec<u8>,
+    pub mac: [u8; 32],
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawV3Payload {
+    cipher: String,
+    cipher_text: String,
+    cipher_params: HashMap<String, String>,
+    kdf: String,
+    kdf_params: HashMap<String, String>,
+    mac: String,
+}
+
+fn param<'a>(
+    params: &'a HashMap<String, String>,
+    key: &str,
+) -> Result<&'a String, EncryptionError> {
+    params
+        .get(key)
+        .ok_or_else(|| EncryptionError::MissingParam(key.to_string()))
+}
+
+fn decode_fixed<const N: usize>(
+    field: &'static str,
+    value: &str,
+) -> Result<[u8; N], EncryptionError> {
+    let bytes = hex::decode(value)?;
+    bytes
+        .as_slice()
+        .try_into()
+        .map_err(|_| EncryptionError::InvalidLength {
+            field,
+            expected: N,
+            actual: bytes.len(),
+        })
+}
+
+impl TryFrom<RawV3Payload> for EncryptDataV3Payload {
+    type Error = EncryptionError;
+
+    fn try_from(raw: RawV3Payload) -> Result<Self, Self::Error> {
+        if raw.cipher != CIPHER {
+            return Err(EncryptionError::UnsupportedCipher(raw.cipher));
+        }
+
+        let params = &raw.kdf_params;
+        let kdf = match raw.kdf.as_str() {
+            KdfParams::SCRYPT_KDF => KdfParams::Scrypt {
+                log_n: param(params, "log_n")?.parse()?,
+                r: param(params, "r")?.parse()?,
+                p: param(params, "p")?.parse()?,
+            },
+            KdfParams::PBKDF2_KDF => {
+                let prf = param(params, "prf")?;
+                if prf != PBKDF2_PRF {
+                    return Err(EncryptionError::InvalidKdfParams(format!(
+                        "Unsupported PBKDF2 PRF {prf}"
+                    )));
+                }
+                KdfParams::Pbkdf2 {
+                    iterations: param(params, "c")?.parse()?,
+                }
+            }
+            KdfParams::ARGON2ID_KDF => KdfParams::Argon2id {
+                memory_kib: param(params, "m")?.parse()?,
+                iterations: param(params, "t")?.parse()?,
+                parallelism: param(params, "p")?.parse()?,
+            },
+            other => return Err(EncryptionError::UnsupportedKdf(other.to_string())),
+        };
+
+        let dklen: usize = param(params, "dklen")?.parse()?;
+        if dklen != DKLEN {
+            return Err(EncryptionError::InvalidLength {
+                field: "dklen",
+                expected: DKLEN,
+                actual: dklen,
+            });
+        }
+
+        Ok(Self {
+            kdf,
+            salt: hex::decode(param(params, "salt")?)?,
+            iv: decode_fixed("iv", param(&raw.cipher_params, "iv")?)?,
+            cipher_text: hex::decode(&raw.cipher_text)?,
+            mac: decode_fixed("mac", &raw.mac)?,
+        })
+    }
+}
+
+impl From<EncryptDataV3Payload> for RawV3Payload {
+    fn from(payload: EncryptDataV3Payload) -> Self {
+        let params = match payload.kdf {
+            KdfParams::Scrypt { log_n, r, p } => vec![
+                ("log_n", log_n.to_string()),
+                ("r", r.to_string()),
+                ("p", p.to_string()),
+            ],
+            KdfParams::Pbkdf2 { iterations } => vec![
+                ("c", iterations.to_string()),
+                ("prf", PBKDF2_PRF.to_string()),
+            ],
+            KdfParams::Argon2id {
+                memory_kib,
+                iterations,
+                parallelism,
+            } => vec![
+                ("m", memory_kib.to_string()),
+                ("t", iterations.to_string()),
+                ("p", parallelism.to_string()),
+            ],
+        };
+        let mut kdf_params: HashMap<String, String> = params
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+        kdf_params.insert("dklen".to_string(), DKLEN.to_string());
+        kdf_params.insert("salt".to_string(), hex::encode(&payload.salt));
+
+        let mut cipher_params = HashMap::new();
+        cipher_params.insert("iv".to_string(), hex::encode(payload.iv));
+
+        Self {
+            cipher: CIPHER.to_string(),
+            cipher_text: hex::encode(&payload.cipher_text),
+            cipher_params,
+            kdf: payload.kdf.name().to_string(),
+            kdf_params,
+            mac: hex::encode(payload.mac),
+        }
+    }
+}
+
+fn mac(mac_key: &[u8], cipher_text: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(mac_key);
+    hasher.update(cipher_text);
+    hasher.finalize().into()
+}
+
+pub fn encrypt_data_v3(
+    data: &[u8],
+    auth: &[u8],
+    kdf_params: &KdfParams,
+) -> Result<EncryptDataV3Payload, EncryptionError> {
+    let mut salt = [0u8; 32];
+    let mut rng = rand::thread_rng();
+    rng.fill_bytes(&mut salt);
+
+    let derived_key = kdf_params.derive_key(auth, &salt, DKLEN)?;
+
+    let mut iv = [0u8; 16];
+    rng.fill_bytes(&mut iv);
+
+    let mut cipher = Aes128Ctr64BE::new_from_slices(&derived_key[..16], &iv)?;
+    let mut cipher_text = data.to_vec();
+    cipher.apply_keystream(&mut cipher_text);
+
+    Ok(EncryptDataV3Payload {
+        kdf: *kdf_params,
+        salt: salt.to_vec(),
+        iv,
+        mac: mac(&derived_key[16..32], &cipher_text),
+        cipher_text,
+    })
+}
+
+/// The returned plaintext is wiped when dropped
+pub fn decrypt_data_v3(
+    crypto: &EncryptDataV3Payload,
+    auth: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, EncryptionError> {
+    let derived_key = crypto.kdf.derive_key(auth, &crypto.salt, DKLEN)?;
+
+    if mac(&derived_key[16..32], &crypto.cipher_text) != crypto.mac {
+        return Err(EncryptionError::MacVerificationFailed);
+    }
+
+    let mut cipher = Aes128Ctr64BE::new_from_slices(&derived_key[..16], &crypto.iv)?;
+    let mut plain_text = Zeroizing::new(crypto.cipher_text.clone());
+    cipher.apply_keystream(&mut plain_text);
+
+    Ok(plain_text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A bincode payload written before the params were typed, with scrypt log_n = 4
+    const LEGACY_PAYLOAD: &str = "0b000000000000006165732d3132382d6374721a00000000000000373362636565633633343261346536316663386566313965383301000000000000000200000000000000697620000000000000003966663035663033353562363537653133376464613737353462376537623331060000000000000073637279707405000000000000000500000000000000646b6c656e0200000000000000333201000000000000007201000000000000003805000000000000006c6f675f6e010000000000000034040000000000000073616c74400000000000000039646433636561636536363765356465653564373632316166326164373733396561393233623762366637626261363539373263646235623061623537383239010000000000000070010000000000000031400000000000000065323832643936626265346434326666323836376534653361323965313333383562386333393539646565383961363134373565353430663631376138626663";
+
+    #[test]
+    fn test_legacy_payload() {
+        let payload: EncryptDataV3Payload =
+            bincode::deserialize(&hex::decode(LEGACY_PAYLOAD).unwrap()).unwrap();
+        assert_eq!(
+            payload.kdf,
+            KdfParams::Scrypt {
+                log_n: 4,
+                r: 8,
+                p: 1
+            }
+        );
+        assert_eq!(
+            decrypt_data_v3(&payload, b"passphrase").unwrap().as_slice(),
+            b"legacy secret"
+        );
+
+        let reencoded: EncryptDataV3Payload =
+            bincode::deserialize(&bincode::serialize(&payload).unwrap()).unwrap();
+        assert_eq!(reencoded, payload);
+    }
+
+    #[test]
+    fn test_malformed_payload() {
+        let payload = encrypt_data_v3(
+            b"secret",
+            b"passphrase",
+            &KdfParams::Pbkdf2 { iterations: 1_000 },
+        )
+        .unwrap();
+
+        let mut raw = RawV3Payload::from(payload.clone());
+        raw.kdf = "bcrypt".to_string();
+        assert!(matches!(
+            EncryptDataV3Payload::try_from(raw),
+            Err(EncryptionError::UnsupportedKdf(_))
+        ));
+
+        let mut raw = RawV3Payload::from(payload.clone());
+        raw.kdf_params.remove("c");
+        assert!(matches!(
+            EncryptDataV3Payload::try_from(raw),
+            Err(EncryptionError::MissingParam(_))
+        ));
+
+        let mut raw = RawV3Payload::from(payload.clone());
+        raw.kdf_params.insert("c".to_string(), "many".to_string());
+        assert!(matches!(
+            EncryptDataV3Payload::try_from(raw),
+            Err(EncryptionError::ParseError(_))
+        ));
+
+        let mut raw = RawV3Payload::from(payload);
+        raw.cipher_params.insert("iv".to_string(), "00".to_string());
+        assert!(matches!(
+            EncryptDataV3Payload::try_from(raw),
+            Err(EncryptionError::InvalidLength { field: "iv", .. })
+        ));
+    }
+}
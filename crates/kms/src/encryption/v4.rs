@@ -0,0 +1,195 @@
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm,
+};
+use chacha20poly1305::XChaCha20Poly1305;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use super::{EncryptionError, KdfParams, DKLEN, PAYLOAD_MAGIC};
+
+/// The authenticated ciphers of v4 payloads, both keyed with the full 32 byte derived key
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Cipher {
+    #[default]
+    Aes256Gcm,
+    /// Its 24 byte nonce makes random nonces safe for any number of encryptions
+    XChaCha20Poly1305,
+}
+
+impl Cipher {
+    pub fn nonce_len(&self) -> usize {
+        match self {
+            Cipher::Aes256Gcm => 12,
+            Cipher::XChaCha20Poly1305 => 24,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncryptDataV4Payload {
+    pub cipher: Cipher,
+    pub nonce: Vec<u8>,
+    pub kdf: KdfParams,
+    pub salt: Vec<u8>,
+    /// The ciphertext followed by the authentication tag
+    pub cipher_text: Vec<u8>,
+}
+
+impl EncryptDataV4Payload {
+    /// Everything but the ciphertext, authenticated alongside it so the header cannot be swapped
+    fn associated_data(&self) -> Result<Vec<u8>, EncryptionError> {
+        header_bytes(self.cipher, &self.kdf, &self.salt, &self.nonce)
+    }
+}
+
+fn header_bytes(
+    cipher: Cipher,
+    kdf: &KdfParams,
+    salt: &[u8],
+    nonce: &[u8],
+) -> Result<Vec<u8>, EncryptionError> {
+    let mut bytes = PAYLOAD_MAGIC.to_vec();
+    bytes.push(4);
+    bincode::serialize_into(&mut bytes, &(cipher, kdf, salt, nonce))?;
+    Ok(bytes)
+}
+
+pub fn encrypt_data_v4(
+    data: &[u8],
+    auth: &[u8],
+    kdf_params: &KdfParams,
+    cipher: Cipher,
+) -> Result<EncryptDataV4Payload, EncryptionError> {
+    let mut salt = [0u8; 32];
+    let mut rng = rand::thread_rng();
+    rng.fill_bytes(&mut salt);
+
+    let mut nonce = vec![0u8; cipher.nonce_len()];
+    rng.fill_bytes(&mut nonce);
+
+    let key = kdf_params.derive_key(auth, &salt, DKLEN)?;
+    let aad = header_bytes(cipher, kdf_params, &salt, &nonce)?;
+    let payload = Payload {
+        msg: data,
+        aad: &aad,
+    };
+    let cipher_text = match cipher {
+        Cipher::Aes256Gcm => {
+            Aes256Gcm::new_from_slice(&key)?.encrypt(nonce.as_slice().into(), payload)
+        }
+        Cipher::XChaCha20Poly1305 => {
+            XChaCha20Poly1305::new_from_slice(&key)?.encrypt(nonce.as_slice().into(), payload)
+        }
+    }
+    .map_err(|_| EncryptionError::EncryptionFailed)?;
+
+    Ok(EncryptDataV4Payload {
+        cipher,
+        nonce,
+        kdf: *kdf_params,
+        salt: salt.to_vec(),
+        cipher_text,
+    })
+}
+
+/// The returned plaintext is wiped when dropped
+pub fn decrypt_data_v4(
+    crypto: &EncryptDataV4Payload,
+    auth: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, EncryptionError> {
+    if crypto.nonce.len() != crypto.cipher.nonce_len() {
+        return Err(EncryptionError::InvalidLength {
+            field: "nonce",
+            expected: crypto.cipher.nonce_len(),
+            actual: crypto.nonce.len(),
+        });
+    }
+
+    let key = crypto.kdf.derive_key(auth, &crypto.salt, DKLEN)?;
+    let nonce = crypto.nonce.as_slice();
+    let aad = crypto.associated_data()?;
+    let payload = Payload {
+        msg: &crypto.cipher_text,
+        aad: &aad,
+    };
+    let plain_text = match crypto.cipher {
+        Cipher::Aes256Gcm => Aes256Gcm::new_from_slice(&key)?.decrypt(nonce.into(), payload),
+        Cipher::XChaCha20Poly1305 => {
+            XChaCha20Poly1305::new_from_slice(&key)?.decrypt(nonce.into(), payload)
+        }
+    }
+    // A wrong passphrase and a tampered payload are indistinguishable
+    .map_err(|_| EncryptionError::MacVerificationFailed)?;
+
+    Ok(Zeroizing::new(plain_text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KDF: KdfParams = KdfParams::Argon2id {
+        memory_kib: 1024,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_roundtrip_with_each_cipher() {
+        for cipher in [Cipher::Aes256Gcm, Cipher::XChaCha20Poly1305] {
+            let payload = encrypt_data_v4(b"secret", b"passphrase", &KDF, cipher).unwrap();
+            assert_eq!(payload.nonce.len(), cipher.nonce_len());
+            // 16 byte tag
+            assert_eq!(payload.cipher_text.len(), 6 + 16);
+
+            assert_eq!(
+                decrypt_data_v4(&payload, b"passphrase").unwrap().as_slice(),
+                b"secret"
+            );
+            assert!(matches!(
+                decrypt_data_v4(&payload, b"wrong passphrase"),
+                Err(EncryptionError::MacVerificationFailed)
+            ));
+
+            let mut tampered = payload.clone();
+            tampered.cipher_text[0] ^= 1;
+            assert!(matches!(
+                decrypt_data_v4(&tampered, b"passphrase"),
+                Err(EncryptionError::MacVerificationFailed)
+            ));
+
+            let mut truncated = payload;
+            truncated.nonce.pop();
+            assert!(matches!(
+                decrypt_data_v4(&truncated, b"passphrase"),
+                Err(EncryptionError::InvalidLength { field: "nonce", .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_header_is_authenticated() {
+        // The right key and nonce, sealed without the header as associated data
+        let key = KDF.derive_key(b"passphrase", &[0; 32], DKLEN).unwrap();
+        let nonce = [0u8; 12];
+        let aad = header_bytes(Cipher::Aes256Gcm, &KDF, &[0; 32], &nonce).unwrap();
+        let cipher_text = Aes256Gcm::new_from_slice(&key)
+            .unwrap()
+            .encrypt(nonce.as_slice().into(), b"secret".as_slice())
+            .unwrap();
+        let payload = EncryptDataV4Payload {
+            cipher: Cipher::Aes256Gcm,
+            nonce: nonce.to_vec(),
+            kdf: KDF,
+            salt: vec![0; 32],
+            cipher_text,
+        };
+        assert_eq!(payload.associated_data().unwrap(), aad);
+        assert!(matches!(
+            decrypt_data_v4(&payload, b"passphrase"),
+            Err(EncryptionError::MacVerificationFailed)
+        ));
+    }
+}
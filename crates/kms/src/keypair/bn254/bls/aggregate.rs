@@ -0,0 +1,302 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt::Display,
+};
+
+use ark_bn254::{Bn254, G1Affine, G2Affine, G2Projective};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::One;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::keypair::bn254::G2Pubkey;
+
+use super::{
+    hash_to_curve::{HashToCurveError, HashToCurveScheme},
+    signature::Signature,
+};
+
+/// Determines which aggregate verifications are safe against rogue-key attacks
+/// see https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-bls-signature-05#section-3
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AggregationScheme {
+    /// Messages in an aggregate must be pairwise distinct
+    #[default]
+    Basic,
+    /// Every public key has a verified proof of possession, so messages may repeat
+    ProofOfPossession,
+}
+
+#[derive(Debug, Error)]
+pub enum AggregateVerificationError {
+    #[error("No public keys or messages to verify against")]
+    EmptyAggregate,
+    #[error("Duplicate message at index {0}")]
+    DuplicateMessage(usize),
+    #[error("Invalid aggregate signature")]
+    InvalidSignature,
+    #[error("Hash to curve error: {0}")]
+    HashToCurveError(#[from] HashToCurveError),
+}
+
+/// The sum of several BLS signatures, possibly over different messages
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AggregateSignature(pub Signature);
+
+impl AggregateSignature {
+    pub fn aggregate<'a, I: IntoIterator<Item = &'a Signature>>(signatures: I) -> Self {
+        AggregateSignature(signatures.into_iter().sum())
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.0
+    }
+
+    /// Verifies the aggregate against `(pubkey, message)` pairs with pairwise distinct messages,
+    /// hashing messages with the legacy try-and-increment method
+    pub fn verify_distinct(
+        &self,
+        entries: &[(G2Pubkey, &[u8])],
+    ) -> Result<(), AggregateVerificationError> {
+        self.verify_distinct_with_scheme(
+            entries,
+            &HashToCurveScheme::TryAndIncrement,
+            AggregationScheme::Basic,
+        )
+    }
+
+    /// Checks e(σ, G2) == Π e(H(m_i), pk_i)
+    ///
+    /// Under [`AggregationScheme::Basic`] duplicate messages are rejected, since they would let an
+    /// attacker cancel out honest keys. Pairs sharing a message have their keys summed first, so
+    /// the check costs one Miller loop per distinct message plus one.
+    pub fn verify_distinct_with_scheme(
+        &self,
+        entries: &[(G2Pubkey, &[u8])],
+        hash_scheme: &HashToCurveScheme,
+        aggregation_scheme: AggregationScheme,
+    ) -> Result<(), AggregateVerificationError> {
+        if entries.is_empty() {
+            return Err(AggregateVerificationError::EmptyAggregate);
+        }
+
+        // Duplicates are found on the hashed points, distinct byte strings may hash to the same
+        // point, e.g. m and m + p under try-and-increment
+        let mut points: Vec<(G1Affine, G2Projective)> = Vec::with_capacity(entries.len());
+        let mut seen: HashMap<G1Affine, usize> = HashMap::with_capacity(entries.len());
+
+        for (index, (pubkey, message)) in entries.iter().enumerate() {
+            let point = hash_scheme.hash_to_g1(message)?;
+            match seen.entry(point) {
+                Entry::Occupied(slot) => {
+                    if aggregation_scheme == AggregationScheme::Basic {
+                        return Err(AggregateVerificationError::DuplicateMessage(index));
+                    }
+                    points[*slot.get()].1 += pubkey.0;
+                }
+                Entry::Vacant(slot) => {
+                    slot.insert(points.len());
+                    points.push((point, pubkey.0.into()));
+                }
+            }
+        }
+
+        let mut g1_points = Vec::with_capacity(points.len() + 1);
+        let mut g2_points = Vec::with_capacity(points.len() + 1);
+
+        for (point, pubkey) in points {
+            g1_points.push(point);
+            g2_points.push(pubkey.into_affine());
+        }
+
+        let neg_sig: G1Affine = -self.0 .0;
+        g1_points.push(neg_sig);
+        g2_points.push(G2Affine::generator());
+
+        // Π e(H(m_i), pk_i) * e(-σ, G2) =? 1
+        let multi_pairing = Bn254::multi_pairing(g1_points, g2_points);
+
+        if !multi_pairing.0.is_one() {
+            return Err(AggregateVerificationError::InvalidSignature);
+        }
+
+        Ok(())
+    }
+}
+
+impl From<Signature> for AggregateSignature {
+    fn from(signature: Signature) -> Self {
+        AggregateSignature(signature)
+    }
+}
+
+impl From<AggregateSignature> for Signature {
+    fn from(aggregate: AggregateSignature) -> Self {
+        aggregate.0
+    }
+}
+
+impl Display for AggregateSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use signature::{Keypair as _, Signer};
+
+    use crate::keypair::{bn254::Keypair as Bn254Keypair, traits::Keypair};
+
+    use super::{super::keypair_signer::WithHashScheme, *};
+
+    fn keypairs(count: usize) -> Vec<Bn254Keypair> {
+        (0..count).map(|_| Bn254Keypair::generate()).collect()
+    }
+
+    #[test]
+    fn test_verify_distinct_messages() {
+        let keypairs = keypairs(3);
+        let messages: Vec<_> = (0..3).map(|i| [i as u8; 32]).collect();
+
+        let signatures: Vec<Signature> = keypairs
+            .iter()
+            .zip(messages.iter())
+            .map(|(keypair, message)| keypair.sign(message))
+            .collect();
+        let aggregate = AggregateSignature::from(signatures.iter().sum::<Signature>());
+        assert_eq!(aggregate, AggregateSignature::aggregate(&signatures));
+
+        let entries: Vec<_> = keypairs
+            .iter()
+            .zip(messages.iter())
+            .map(|(keypair, message)| (keypair.verifying_key(), message.as_slice()))
+            .collect();
+
+        assert!(aggregate.verify_distinct(&entries).is_ok());
+    }
+
+    #[test]
+    fn test_verify_distinct_wrong_message() {
+        let keypairs = keypairs(3);
+        let messages: Vec<_> = (0..3).map(|i| [i as u8; 32]).collect();
+
+        let aggregate = AggregateSignature::aggregate(
+            &keypairs
+                .iter()
+                .zip(messages.iter())
+                .map(|(keypair, message)| keypair.sign(message))
+                .collect::<Vec<_>>(),
+        );
+
+        let wrong_message = [0xffu8; 32];
+        let mut entries: Vec<_> = keypairs
+            .iter()
+            .zip(messages.iter())
+            .map(|(keypair, message)| (keypair.verifying_key(), message.as_slice()))
+            .collect();
+        entries[1].1 = wrong_message.as_slice();
+
+        assert!(matches!(
+            aggregate.verify_distinct(&entries),
+            Err(AggregateVerificationError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn test_verify_distinct_rejects_colliding_messages() {
+        let keypairs = keypairs(2);
+        // Both are the integer 1 to try-and-increment, so they hash to the same point
+        let messages: [&[u8]; 2] = [&[0x01], &[0x00, 0x01]];
+
+        let aggregate = AggregateSignature::aggregate(
+            &keypairs
+                .iter()
+                .zip(messages)
+                .map(|(keypair, message)| keypair.sign(message))
+                .collect::<Vec<_>>(),
+        );
+        let entries: Vec<_> = keypairs
+            .iter()
+            .zip(messages)
+            .map(|(keypair, message)| (keypair.verifying_key(), message))
+            .collect();
+
+        assert!(matches!(
+            aggregate.verify_distinct(&entries),
+            Err(AggregateVerificationError::DuplicateMessage(1))
+        ));
+    }
+
+    #[test]
+    fn test_verify_distinct_rejects_duplicates_without_pop() {
+        let keypairs = keypairs(2);
+        let message = [42u8; 32];
+
+        let aggregate = AggregateSignature::aggregate(
+            &keypairs
+                .iter()
+                .map(|keypair| keypair.sign(&message))
+                .collect::<Vec<_>>(),
+        );
+        let entries: Vec<_> = keypairs
+            .iter()
+            .map(|keypair| (keypair.verifying_key(), message.as_slice()))
+            .collect();
+
+        assert!(matches!(
+            aggregate.verify_distinct(&entries),
+            Err(AggregateVerificationError::DuplicateMessage(1))
+        ));
+        assert!(aggregate
+            .verify_distinct_with_scheme(
+                &entries,
+                &HashToCurveScheme::TryAndIncrement,
+                AggregationScheme::ProofOfPossession,
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_verify_distinct_mixed_messages_with_pop() {
+        let keypairs = keypairs(4);
+        let messages = [[1u8; 32], [1u8; 32], [2u8; 32], [3u8; 32]];
+        let scheme = HashToCurveScheme::svdw(b"KARAK-DSS-A".to_vec());
+
+        let aggregate = AggregateSignature::aggregate(
+            &keypairs
+                .iter()
+                .zip(messages.iter())
+                .map(|(keypair, message)| {
+                    WithHashScheme::new(keypair, scheme.clone()).sign(message)
+                })
+                .collect::<Vec<_>>(),
+        );
+        let entries: Vec<_> = keypairs
+            .iter()
+            .zip(messages.iter())
+            .map(|(keypair, message)| (keypair.public_key().g2, message.as_slice()))
+            .collect();
+
+        assert!(aggregate
+            .verify_distinct_with_scheme(&entries, &scheme, AggregationScheme::ProofOfPossession)
+            .is_ok());
+        assert!(aggregate
+            .verify_distinct_with_scheme(
+                &entries,
+                &HashToCurveScheme::TryAndIncrement,
+                AggregationScheme::ProofOfPossession,
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_distinct_empty() {
+        let aggregate = AggregateSignature::aggregate(&[]);
+        assert!(matches!(
+            aggregate.verify_distinct(&[]),
+            Err(AggregateVerificationError::EmptyAggregate)
+        ));
+    }
+}
@@ -0,0 +1,204 @@
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::One;
+use rand::{thread_rng, Rng};
+use thiserror::Error;
+
+use crate::keypair::bn254::G2Pubkey;
+
+use super::{
+    hash_to_curve::{HashToCurveError, HashToCurveScheme},
+    signature::Signature,
+};
+
+/// A single `(pubkey, message, signature)` triple to be checked by [`batch_verify`]
+pub type BatchEntry<'a> = (G2Pubkey, &'a [u8], Signature);
+
+#[derive(Debug, Error)]
+pub enum BatchVerificationError {
+    #[error("Invalid signatures at indices {0:?}")]
+    InvalidSignatures(Vec<usize>),
+    #[error("Hash to curve error: {0}")]
+    HashToCurveError(#[from] HashToCurveError),
+}
+
+// A batch entry with its message already hashed to G1 and its random blinding factor drawn
+struct PreparedEntry {
+    pubkey: G2Affine,
+    hashed_message: G1Affine,
+    signature: G1Affine,
+    blinding: Fr,
+}
+
+/// Verifies many BLS signatures at once, hashing messages with the legacy try-and-increment method
+///
+/// See [`batch_verify_with_scheme`]
+pub fn batch_verify(entries: &[BatchEntry]) -> Result<(), BatchVerificationError> {
+    batch_verify_with_scheme(entries, &HashToCurveScheme::TryAndIncrement)
+}
+
+/// Verifies many BLS signatures at once using a random linear combination
+///
+/// Each entry `i` is weighted by a random 128-bit scalar `r_i` and the whole batch is accepted iff
+/// e(Σ r_i * σ_i, -G2) * Π e(r_i * H(m_i), pk_i) == 1
+/// which costs N+1 Miller loops and a single final exponentiation.
+///
+/// If the combined check fails, the batch is bisected to find every invalid entry, whose indices
+/// are returned in [`BatchVerificationError::InvalidSignatures`].
+pub fn batch_verify_with_scheme(
+    entries: &[BatchEntry],
+    scheme: &HashToCurveScheme,
+) -> Result<(), BatchVerificationError> {
+    let mut rng = thread_rng();
+
+    let prepared = entries
+        .iter()
+        .map(|(pubkey, message, signature)| {
+            Ok(PreparedEntry {
+                pubkey: pubkey.0,
+                hashed_message: scheme.hash_to_g1(message)?,
+                signature: signature.0,
+                blinding: Fr::from(rng.gen::<u128>()),
+            })
+        })
+        .collect::<Result<Vec<_>, HashToCurveError>>()?;
+
+    let indices: Vec<usize> = (0..prepared.len()).collect();
+    let mut invalid = Vec::new();
+    find_invalid(&prepared, &indices, &mut invalid);
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        invalid.sort_unstable();
+        Err(BatchVerificationError::InvalidSignatures(invalid))
+    }
+}
+
+fn find_invalid(prepared: &[PreparedEntry], indices: &[usize], invalid: &mut Vec<usize>) {
+    if indices.is_empty() || verify_subset(prepared, indices) {
+        return;
+    }
+
+    if indices.len() == 1 {
+        invalid.push(indices[0]);
+        return;
+    }
+
+    let (left, right) = indices.split_at(indices.len() / 2);
+    find_invalid(prepared, left, invalid);
+    find_invalid(prepared, right, invalid);
+}
+
+fn verify_subset(prepared: &[PreparedEntry], indices: &[usize]) -> bool {
+    let mut g1_points = Vec::with_capacity(indices.len() + 1);
+    let mut g2_points = Vec::with_capacity(indices.len() + 1);
+    let mut combined_signature = G1Projective::default();
+
+    for &i in indices {
+        let entry = &prepared[i];
+        combined_signature += entry.signature * entry.blinding;
+        g1_points.push((entry.hashed_message * entry.blinding).into_affine());
+        g2_points.push(entry.pubkey);
+    }
+
+    g1_points.push(-combined_signature.into_affine());
+    g2_points.push(G2Affine::generator());
+
+    let miller_loop = Bn254::multi_miller_loop(g1_points, g2_points);
+    match Bn254::final_exponentiation(miller_loop) {
+        Some(result) => result.0.is_one(),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use signature::{Keypair as _, Signer};
+
+    use crate::keypair::{bn254::Keypair as Bn254Keypair, traits::Keypair};
+
+    use super::{super::keypair_signer::WithHashScheme, *};
+
+    fn signed_batch(size: usize) -> (Vec<Bn254Keypair>, Vec<[u8; 32]>, Vec<Signature>) {
+        let keypairs: Vec<_> = (0..size).map(|_| Bn254Keypair::generate()).collect();
+        let messages: Vec<_> = (0..size).map(|i| [i as u8; 32]).collect();
+        let signatures = keypairs
+            .iter()
+            .zip(messages.iter())
+            .map(|(keypair, message)| keypair.sign(message))
+            .collect();
+        (keypairs, messages, signatures)
+    }
+
+    fn entries<'a>(
+        keypairs: &[Bn254Keypair],
+        messages: &'a [[u8; 32]],
+        signatures: &[Signature],
+    ) -> Vec<BatchEntry<'a>> {
+        keypairs
+            .iter()
+            .zip(messages.iter())
+            .zip(signatures.iter())
+            .map(|((keypair, message), signature)| {
+                (keypair.verifying_key(), message.as_slice(), *signature)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_batch_verify_valid() {
+        let (keypairs, messages, signatures) = signed_batch(8);
+        assert!(batch_verify(&entries(&keypairs, &messages, &signatures)).is_ok());
+    }
+
+    #[test]
+    fn test_batch_verify_empty() {
+        assert!(batch_verify(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_batch_verify_reports_invalid_indices() {
+        let (keypairs, messages, mut signatures) = signed_batch(8);
+        signatures[2] = keypairs[2].sign(&[0xffu8; 32]);
+        signatures[7] = signatures[6];
+
+        match batch_verify(&entries(&keypairs, &messages, &signatures)) {
+            Err(BatchVerificationError::InvalidSignatures(invalid)) => {
+                assert_eq!(invalid, vec![2, 7])
+            }
+            other => panic!("Expected invalid signatures, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_batch_verify_rejects_swapped_signatures() {
+        // The sum of signatures is unchanged by swapping, which the random weights must catch
+        let (keypairs, messages, mut signatures) = signed_batch(4);
+        signatures.swap(0, 1);
+
+        match batch_verify(&entries(&keypairs, &messages, &signatures)) {
+            Err(BatchVerificationError::InvalidSignatures(invalid)) => {
+                assert_eq!(invalid, vec![0, 1])
+            }
+            other => panic!("Expected invalid signatures, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_batch_verify_with_scheme() {
+        let scheme = HashToCurveScheme::svdw(b"KARAK-DSS-A".to_vec());
+        let keypairs: Vec<_> = (0..4).map(|_| Bn254Keypair::generate()).collect();
+        let message = [42u8; 32];
+        let batch: Vec<_> = keypairs
+            .iter()
+            .map(|keypair| {
+                let signature = WithHashScheme::new(keypair, scheme.clone()).sign(&message);
+                (keypair.public_key().g2, message.as_slice(), signature)
+            })
+            .collect();
+
+        assert!(batch_verify_with_scheme(&batch, &scheme).is_ok());
+        assert!(batch_verify(&batch).is_err());
+    }
+}
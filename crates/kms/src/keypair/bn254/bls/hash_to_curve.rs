@@ -0,0 +1,290 @@
+use ark_bn254::{Fq, G1Affine, G1Projective};
+use ark_ec::CurveGroup;
+use ark_ff::{BigInteger, Field, MontFp, One, PrimeField, Zero};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use super::keypair_signer::hash_to_g1_point;
+
+// SHA-256 output and block sizes, in bytes
+const B_IN_BYTES: usize = 32;
+const S_IN_BYTES: usize = 64;
+
+// L = ceil((ceil(log2(p)) + k) / 8) with k = 128 bits of security
+const FIELD_ELEMENT_LEN: usize = 48;
+
+const MAX_DST_LEN: usize = 255;
+const OVERSIZE_DST_PREFIX: &[u8] = b"H2C-OVERSIZE-DST-";
+
+// Shallue-van de Woestijne constants for y^2 = x^3 + 3 with Z = 1
+// see https://www.rfc-editor.org/rfc/rfc9380.html#section-6.6.1
+const Z: Fq = MontFp!("1");
+const B: Fq = MontFp!("3");
+// g(Z)
+const C1: Fq = MontFp!("4");
+// -Z / 2
+const C2: Fq =
+    MontFp!("10944121435919637611123202872628637544348155578648911831344518947322613104291");
+// sqrt(-g(Z) * 3Z^2), with sgn0(C3) == 0
+const C3: Fq = MontFp!("8815841940592487685674414971303048083897117035520822607866");
+// -4g(Z) / 3Z^2
+const C4: Fq =
+    MontFp!("7296080957279758407415468581752425029565437052432607887563012631548408736189");
+
+#[derive(Debug, Error)]
+pub enum HashToCurveError {
+    #[error("Domain separation tag must not be empty")]
+    EmptyDst,
+    #[error("Invalid expand_message_xmd output length: {0}")]
+    InvalidOutputLength(usize),
+}
+
+/// Selects how a message is mapped onto G1 before it is signed or verified
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum HashToCurveScheme {
+    /// Legacy try-and-increment mapping, as implemented by the on-chain `BlsSdk` contracts
+    #[default]
+    TryAndIncrement,
+    /// RFC 9380 `BN254G1_XMD:SHA-256_SVDW_RO_` with an explicit domain separation tag
+    Svdw { dst: Vec<u8> },
+}
+
+impl HashToCurveScheme {
+    pub fn svdw<D: Into<Vec<u8>>>(dst: D) -> Self {
+        HashToCurveScheme::Svdw { dst: dst.into() }
+    }
+
+    pub fn hash_to_g1(&self, message: &[u8]) -> Result<G1Affine, HashToCurveError> {
+        match self {
+            HashToCurveScheme::TryAndIncrement => Ok(hash_to_g1_point(message)),
+            HashToCurveScheme::Svdw { dst } => hash_to_curve(message, dst),
+        }
+    }
+}
+
+/// Hashes `message` to a point on BN254 G1 as per RFC 9380 (random oracle encoding)
+///
+/// BN254 G1 has a cofactor of 1, so no cofactor clearing is needed
+pub fn hash_to_curve(message: &[u8], dst: &[u8]) -> Result<G1Affine, HashToCurveError> {
+    let [u0, u1] = hash_to_field(message, dst)?;
+    let q0 = map_to_curve_svdw(u0);
+    let q1 = map_to_curve_svdw(u1);
+
+    Ok((G1Projective::from(q0) + q1).into_affine())
+}
+
+/// Hashes `message` to two uniformly random elements of Fq
+pub fn hash_to_field(message: &[u8], dst: &[u8]) -> Result<[Fq; 2], HashToCurveError> {
+    let uniform_bytes = expand_message_xmd(message, dst, 2 * FIELD_ELEMENT_LEN)?;
+    let (first, second) = uniform_bytes.split_at(FIELD_ELEMENT_LEN);
+
+    Ok([
+        Fq::from_be_bytes_mod_order(first),
+        Fq::from_be_bytes_mod_order(second),
+    ])
+}
+
+/// `expand_message_xmd` instantiated with SHA-256
+/// see https://www.rfc-editor.org/rfc/rfc9380.html#section-5.3.1
+pub fn expand_message_xmd(
+    message: &[u8],
+    dst: &[u8],
+    len_in_bytes: usize,
+) -> Result<Vec<u8>, HashToCurveError> {
+    if dst.is_empty() {
+        return Err(HashToCurveError::EmptyDst);
+    }
+
+    let ell = len_in_bytes.div_ceil(B_IN_BYTES);
+    if len_in_bytes == 0 || ell > 255 || len_in_bytes > u16::MAX as usize {
+        return Err(HashToCurveError::InvalidOutputLength(len_in_bytes));
+    }
+
+    let dst_prime = dst_prime(dst);
+
+    let b_0 = Sha256::new()
+        .chain_update([0u8; S_IN_BYTES])
+        .chain_update(message)
+        .chain_update((len_in_bytes as u16).to_be_bytes())
+        .chain_update([0u8])
+        .chain_update(&dst_prime)
+        .finalize();
+
+    let mut b_i = Sha256::new()
+        .chain_update(b_0)
+        .chain_update([1u8])
+        .chain_update(&dst_prime)
+        .finalize();
+
+    let mut uniform_bytes = Vec::with_capacity(ell * B_IN_BYTES);
+    uniform_bytes.extend_from_slice(&b_i);
+
+    for i in 2..=ell {
+        let xored: Vec<u8> = b_0.iter().zip(b_i.iter()).map(|(a, b)| a ^ b).collect();
+        b_i = Sha256::new()
+            .chain_update(xored)
+            .chain_update([i as u8])
+            .chain_update(&dst_prime)
+            .finalize();
+        uniform_bytes.extend_from_slice(&b_i);
+    }
+
+    uniform_bytes.truncate(len_in_bytes);
+    Ok(uniform_bytes)
+}
+
+// DST_prime = DST || I2OSP(len(DST), 1), hashing DSTs that are too long
+// see https://www.rfc-editor.org/rfc/rfc9380.html#section-5.3.3
+fn dst_prime(dst: &[u8]) -> Vec<u8> {
+    let mut dst_prime = if dst.len() > MAX_DST_LEN {
+        Sha256::new()
+            .chain_update(OVERSIZE_DST_PREFIX)
+            .chain_update(dst)
+            .finalize()
+            .to_vec()
+    } else {
+        dst.to_vec()
+    };
+    dst_prime.push(dst_prime.len() as u8);
+    dst_prime
+}
+
+/// Shallue-van de Woestijne map to BN254 G1, following the straight-line
+/// implementation in RFC 9380 Appendix F.1
+pub fn map_to_curve_svdw(u: Fq) -> G1Affine {
+    let tv1 = u.square() * C1;
+    let tv2 = Fq::one() + tv1;
+    let tv1 = Fq::one() - tv1;
+    let tv3 = (tv1 * tv2).inverse().unwrap_or(Fq::zero());
+    let tv4 = u * tv1 * tv3 * C3;
+
+    let x1 = C2 - tv4;
+    let e1 = is_square(curve_rhs(x1));
+
+    let x2 = C2 + tv4;
+    let e2 = is_square(curve_rhs(x2)) && !e1;
+
+    let x3 = (tv2.square() * tv3).square() * C4 + Z;
+
+    let x = if e1 {
+        x1
+    } else if e2 {
+        x2
+    } else {
+        x3
+    };
+
+    // One of x1, x2, x3 is always on the curve
+    let mut y = curve_rhs(x).sqrt().expect("SvdW always yields a square");
+    if sgn0(u) != sgn0(y) {
+        y = -y;
+    }
+
+    G1Affine::new_unchecked(x, y)
+}
+
+// y^2 = x^3 + 3
+fn curve_rhs(x: Fq) -> Fq {
+    x.square() * x + B
+}
+
+fn is_square(x: Fq) -> bool {
+    !x.legendre().is_qnr()
+}
+
+fn sgn0(x: Fq) -> bool {
+    x.into_bigint().is_odd()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXPANDER_DST: &[u8] = b"QUUX-V01-CS02-with-expander-SHA256-128";
+    const SUITE_DST: &[u8] = b"QUUX-V01-CS02-with-BN254G1_XMD:SHA-256_SVDW_RO_";
+
+    fn fq(hex_str: &str) -> Fq {
+        let bytes = hex::decode(hex_str).unwrap();
+        Fq::from_be_bytes_mod_order(&bytes)
+    }
+
+    #[test]
+    fn test_expand_message_xmd_vectors() {
+        // RFC 9380 Appendix K.1
+        let vectors = [
+            (
+                "",
+                "68a985b87eb6b46952128911f2a4412bbc302a9d759667f87f7a21d803f07235",
+            ),
+            (
+                "abc",
+                "d8ccab23b5985ccea865c6c97b6e5b8350e794e603b4b97902f53a8a0d605615",
+            ),
+        ];
+
+        for (msg, expected) in vectors {
+            let uniform_bytes = expand_message_xmd(msg.as_bytes(), EXPANDER_DST, 32).unwrap();
+            assert_eq!(hex::encode(uniform_bytes), expected);
+        }
+    }
+
+    #[test]
+    fn test_hash_to_curve_vectors() {
+        let vectors = [
+            (
+                "",
+                "0a976ab906170db1f9638d376514dbf8c42aef256a54bbd48521f20749e59e86",
+                "02925ead66b9e68bfc309b014398640ab55f6619ab59bc1fab2210ad4c4d53d5",
+            ),
+            (
+                "abc",
+                "23f717bee89b1003957139f193e6be7da1df5f1374b26a4643b0378b5baf53d1",
+                "04142f826b71ee574452dbc47e05bc3e1a647478403a7ba38b7b93948f4e151d",
+            ),
+        ];
+
+        for (msg, x, y) in vectors {
+            let point = hash_to_curve(msg.as_bytes(), SUITE_DST).unwrap();
+            assert_eq!(point, G1Affine::new(fq(x), fq(y)));
+        }
+    }
+
+    #[test]
+    fn test_svdw_output_is_on_curve() {
+        for i in 0..32u64 {
+            let point = map_to_curve_svdw(Fq::from(i));
+            assert!(point.is_on_curve());
+            assert!(point.is_in_correct_subgroup_assuming_on_curve());
+        }
+    }
+
+    #[test]
+    fn test_dst_separates_domains() {
+        let message = [42u8; 32];
+        let first = hash_to_curve(&message, b"KARAK-DSS-A").unwrap();
+        let second = hash_to_curve(&message, b"KARAK-DSS-B").unwrap();
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_empty_dst_rejected() {
+        assert!(matches!(
+            hash_to_curve(b"message", b""),
+            Err(HashToCurveError::EmptyDst)
+        ));
+    }
+
+    #[test]
+    fn test_oversize_dst_is_hashed() {
+        let long_dst = [b'a'; 300];
+        let hashed_dst = Sha256::new()
+            .chain_update(OVERSIZE_DST_PREFIX)
+            .chain_update(long_dst)
+            .finalize();
+
+        assert_eq!(
+            hash_to_curve(b"message", &long_dst).unwrap(),
+            hash_to_curve(b"message", &hashed_dst).unwrap()
+        );
+    }
+}
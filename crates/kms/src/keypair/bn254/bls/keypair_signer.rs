@@ -11,7 +11,7 @@ use super::super::{
     super::traits::Keypair, algebra::g1::G1Point, G2Pubkey, Keypair as Bn254Keypair,
 };
 
-use super::signature::Signature;
+use super::{hash_to_curve::HashToCurveScheme, signature::Signature};
 
 impl signature::Keypair for Bn254Keypair {
     type VerifyingKey = G2Pubkey;
@@ -31,69 +31,135 @@ pub type SignatureResult<T> = Result<T, SignatureError>;
 impl Signer<Signature> for Bn254Keypair {
     /// Caller is responsible for ensuring `hash` is a 32-byte hash of some arbitrary sized message
     fn try_sign(&self, bytes: &[u8]) -> SignatureResult<Signature> {
-        let sk = self.secret_key();
-        let hm = hash_to_g1_point(bytes);
-        // TODO: Check whether its better/worse to use the projective version of the point
-        let sig = (hm * sk).into_affine();
-
-        Ok(Signature::from(sig))
+        Ok(sign_hashed_message(self, hash_to_g1_point(bytes)))
     }
 }
 
 impl Verifier<Signature> for G2Pubkey {
     fn verify(&self, message: &[u8], sig: &Signature) -> SignatureResult<()> {
-        let gen_g2 = G2Affine::generator();
-        let msg_point_g1 = hash_to_g1_point(message);
+        verify_hashed_message_g2(self, hash_to_g1_point(message), sig)
+    }
+}
 
-        let neg_sig = sig.0.neg();
+impl Verifier<Signature> for PublicKey {
+    fn verify(&self, message: &[u8], sig: &Signature) -> SignatureResult<()> {
+        verify_hashed_message(self, hash_to_g1_point(message), sig)
+    }
+}
 
-        let p = [msg_point_g1, neg_sig];
-        let q = [self.0, gen_g2];
+/// Binds a keypair or public key to a [`HashToCurveScheme`], so that signing and verification
+/// hash messages onto G1 with that scheme instead of the legacy try-and-increment method
+#[derive(Clone, Debug)]
+pub struct WithHashScheme<'a, K> {
+    key: &'a K,
+    scheme: HashToCurveScheme,
+}
 
-        // e(H(m), sk * G2) * e(-(sk * H(m)), G2) =? 1
-        let multi_pairing = Bn254::multi_pairing(p, q);
+impl<'a, K> WithHashScheme<'a, K> {
+    pub fn new(key: &'a K, scheme: HashToCurveScheme) -> Self {
+        Self { key, scheme }
+    }
 
-        if !multi_pairing.0.is_one() {
-            return Err(SignatureError::from_source(
-                Bn254SignatureError::InvalidSignature,
-            ));
-        }
+    pub fn key(&self) -> &K {
+        self.key
+    }
+
+    pub fn scheme(&self) -> &HashToCurveScheme {
+        &self.scheme
+    }
 
-        Ok(())
+    fn hash_to_g1(&self, message: &[u8]) -> SignatureResult<G1Affine> {
+        self.scheme
+            .hash_to_g1(message)
+            .map_err(SignatureError::from_source)
     }
 }
 
-impl Verifier<Signature> for PublicKey {
+impl Signer<Signature> for WithHashScheme<'_, Bn254Keypair> {
+    fn try_sign(&self, message: &[u8]) -> SignatureResult<Signature> {
+        Ok(sign_hashed_message(self.key, self.hash_to_g1(message)?))
+    }
+}
+
+impl Verifier<Signature> for WithHashScheme<'_, G2Pubkey> {
     fn verify(&self, message: &[u8], sig: &Signature) -> SignatureResult<()> {
-        let signature_plus_pubkey_g1 = sig + &self.g1;
-        let hash_plus_generator_g1 =
-            G1Point::from(hash_to_g1_point(message)) + G1Point::generator();
+        verify_hashed_message_g2(self.key, self.hash_to_g1(message)?, sig)
+    }
+}
 
-        let gen_g2 = G2Affine::generator();
+impl Verifier<Signature> for WithHashScheme<'_, PublicKey> {
+    fn verify(&self, message: &[u8], sig: &Signature) -> SignatureResult<()> {
+        verify_hashed_message(self.key, self.hash_to_g1(message)?, sig)
+    }
+}
 
-        let neg_sig = signature_plus_pubkey_g1.0.neg();
+fn sign_hashed_message(keypair: &Bn254Keypair, hm: G1Affine) -> Signature {
+    let sk = keypair.secret_key().expose_secret();
+    // TODO: Check whether its better/worse to use the projective version of the point
+    let sig = (hm * sk).into_affine();
 
-        let p = [hash_plus_generator_g1.0, neg_sig];
-        let q = [self.g2.0, gen_g2];
+    Signature::from(sig)
+}
 
-        // e((H(m)+G1), sk * G2) * e(-(sk * (H(m) + G1)), G2) =? 1
-        let multi_pairing = Bn254::multi_pairing(p, q);
+fn verify_hashed_message_g2(
+    pubkey: &G2Pubkey,
+    msg_point_g1: G1Affine,
+    sig: &Signature,
+) -> SignatureResult<()> {
+    let gen_g2 = G2Affine::generator();
 
-        if !multi_pairing.0.is_one() {
-            return Err(SignatureError::from_source(
-                Bn254SignatureError::InvalidSignature,
-            ));
-        }
+    let neg_sig = sig.0.neg();
+
+    let p = [msg_point_g1, neg_sig];
+    let q = [pubkey.0, gen_g2];
+
+    // e(H(m), sk * G2) * e(-(sk * H(m)), G2) =? 1
+    let multi_pairing = Bn254::multi_pairing(p, q);
+
+    if !multi_pairing.0.is_one() {
+        return Err(SignatureError::from_source(
+            Bn254SignatureError::InvalidSignature,
+        ));
+    }
+
+    Ok(())
+}
+
+fn verify_hashed_message(
+    pubkey: &PublicKey,
+    msg_point_g1: G1Affine,
+    sig: &Signature,
+) -> SignatureResult<()> {
+    let signature_plus_pubkey_g1 = sig + &pubkey.g1;
+    let hash_plus_generator_g1 = G1Point::from(msg_point_g1) + G1Point::generator();
+
+    let gen_g2 = G2Affine::generator();
+
+    let neg_sig = signature_plus_pubkey_g1.0.neg();
+
+    let p = [hash_plus_generator_g1.0, neg_sig];
+    let q = [pubkey.g2.0, gen_g2];
 
-        Ok(())
+    // e((H(m)+G1), sk * G2) * e(-(sk * (H(m) + G1)), G2) =? 1
+    let multi_pairing = Bn254::multi_pairing(p, q);
+
+    if !multi_pairing.0.is_one() {
+        return Err(SignatureError::from_source(
+            Bn254SignatureError::InvalidSignature,
+        ));
     }
+
+    Ok(())
 }
 
 // Implements the hash-and-check algorithm
 // see https://hackmd.io/@benjaminion/bls12-381#Hash-and-check
 // Curve: y^2 = x^3 + 3
+//
+// The message is interpreted as a big-endian integer to match `BN254.hashToG1` in the on-chain
+// `BlsSdk` contracts. This mapping is neither constant-time nor domain separated, prefer
+// `HashToCurveScheme::Svdw` for new protocols.
 pub fn hash_to_g1_point(message: &[u8]) -> G1Affine {
-    // TODO: big-endian or litte-endian here?
     let mut x = Fq::from_be_bytes_mod_order(message);
 
     loop {
@@ -277,4 +343,65 @@ mod tests {
             .is_err());
         assert!(keypair.public_key().verify(&message1, &signature2).is_err());
     }
+
+    #[test]
+    fn test_sign_and_verify_with_svdw_scheme() {
+        let keypair = generate_keypair();
+        let message = [42u8; 32];
+        let scheme = HashToCurveScheme::svdw(b"KARAK-DSS-A".to_vec());
+
+        let signature = WithHashScheme::new(&keypair, scheme.clone()).sign(&message);
+
+        assert!(
+            WithHashScheme::new(&keypair.verifying_key(), scheme.clone())
+                .verify(&message, &signature)
+                .is_ok()
+        );
+        assert!(WithHashScheme::new(keypair.public_key(), scheme)
+            .verify(&message, &signature)
+            .is_ok());
+
+        // Not verifiable with the legacy hashing method
+        assert!(keypair
+            .verifying_key()
+            .verify(&message, &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn test_signature_not_replayable_across_dsts() {
+        let keypair = generate_keypair();
+        let message = [42u8; 32];
+
+        let signature =
+            WithHashScheme::new(&keypair, HashToCurveScheme::svdw(b"KARAK-DSS-A".to_vec()))
+                .sign(&message);
+
+        let other_scheme = HashToCurveScheme::svdw(b"KARAK-DSS-B".to_vec());
+        assert!(
+            WithHashScheme::new(&keypair.verifying_key(), other_scheme.clone())
+                .verify(&message, &signature)
+                .is_err()
+        );
+        assert!(WithHashScheme::new(keypair.public_key(), other_scheme)
+            .verify(&message, &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn test_try_and_increment_scheme_matches_legacy() {
+        let keypair = precomputed_keypair();
+        let message = [42u8; 32];
+
+        let signature =
+            WithHashScheme::new(keypair, HashToCurveScheme::TryAndIncrement).sign(&message);
+        assert_eq!(signature, *precomputed_signature_for_keypair());
+    }
+
+    #[test]
+    fn test_empty_dst_fails_to_sign() {
+        let keypair = generate_keypair();
+        let signer = WithHashScheme::new(&keypair, HashToCurveScheme::svdw(Vec::new()));
+        assert!(signer.try_sign(&[42u8; 32]).is_err());
+    }
 }
@@ -1,3 +1,15 @@
+pub mod aggregate;
+pub mod batch;
+pub mod hash_to_curve;
 pub mod keypair_signer;
+pub mod proof_of_possession;
+pub mod quorum;
 pub mod registration;
 pub mod signature;
+
+pub use aggregate::{AggregateSignature, AggregateVerificationError, AggregationScheme};
+pub use batch::{batch_verify, batch_verify_with_scheme, BatchEntry, BatchVerificationError};
+pub use proof_of_possession::{
+    aggregate_with_proofs, ProofOfPossession, ProofOfPossessionError, POP_DST,
+};
+pub use quorum::{QuorumCertificate, QuorumCertificateData, QuorumCertificateError};
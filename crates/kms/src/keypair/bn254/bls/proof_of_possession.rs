@@ -0,0 +1,186 @@
+use ark_serialize::{CanonicalSerialize, Valid};
+use signature::{Signer, Verifier};
+use thiserror::Error;
+
+use crate::keypair::{
+    bn254::{Bn254Error, Keypair as Bn254Keypair, PublicKey},
+    traits::Keypair,
+};
+
+use super::{
+    hash_to_curve::HashToCurveScheme, keypair_signer::WithHashScheme, signature::Signature,
+};
+
+/// Domain separation tag for proofs of possession, distinct from any signing DST so that a proof
+/// can never be replayed as a signature (or vice versa)
+pub const POP_DST: &[u8] = b"KARAK_BLS_POP_BN254G1_XMD:SHA-256_SVDW_RO_POP_";
+
+/// A BLS signature over the signer's own public key, proving knowledge of its secret key
+pub type ProofOfPossession = Signature;
+
+#[derive(Debug, Error)]
+pub enum ProofOfPossessionError {
+    #[error("Invalid public key")]
+    InvalidPublicKey,
+    #[error("Invalid proof of possession")]
+    InvalidProof,
+    #[error("Invalid proof of possession for public key at index {0}")]
+    InvalidProofAt(usize),
+    #[error("No public keys to aggregate")]
+    EmptyAggregate,
+    #[error("Keypair error: {0}")]
+    KeypairError(#[from] Bn254Error),
+}
+
+fn pop_scheme() -> HashToCurveScheme {
+    HashToCurveScheme::svdw(POP_DST)
+}
+
+// The proof binds both the G1 and G2 halves of the public key
+fn pop_message(public_key: &PublicKey) -> Result<Vec<u8>, Bn254Error> {
+    let mut message = Vec::new();
+    public_key.serialize_compressed(&mut message)?;
+    Ok(message)
+}
+
+impl Bn254Keypair {
+    pub fn proof_of_possession(&self) -> Result<ProofOfPossession, ProofOfPossessionError> {
+        let message = pop_message(self.public_key())?;
+        WithHashScheme::new(self, pop_scheme())
+            .try_sign(&message)
+            .map_err(|_| ProofOfPossessionError::InvalidProof)
+    }
+}
+
+impl PublicKey {
+    /// Checks that the G1 and G2 keys match and that `proof` was produced by their secret key
+    pub fn verify_proof_of_possession(
+        &self,
+        proof: &ProofOfPossession,
+    ) -> Result<(), ProofOfPossessionError> {
+        self.check()
+            .map_err(|_| ProofOfPossessionError::InvalidPublicKey)?;
+
+        let message = pop_message(self)?;
+        WithHashScheme::new(&self.g2, pop_scheme())
+            .verify(&message, proof)
+            .map_err(|_| ProofOfPossessionError::InvalidProof)
+    }
+}
+
+/// Aggregates public keys, requiring a valid proof of possession for every one of them
+///
+/// Keys aggregated this way are safe to use with
+/// [`AggregationScheme::ProofOfPossession`](super::aggregate::AggregationScheme::ProofOfPossession)
+pub fn aggregate_with_proofs(
+    keys: &[(PublicKey, ProofOfPossession)],
+) -> Result<PublicKey, ProofOfPossessionError> {
+    if keys.is_empty() {
+        return Err(ProofOfPossessionError::EmptyAggregate);
+    }
+
+    for (index, (public_key, proof)) in keys.iter().enumerate() {
+        public_key
+            .verify_proof_of_possession(proof)
+            .map_err(|_| ProofOfPossessionError::InvalidProofAt(index))?;
+    }
+
+    Ok(PublicKey {
+        g1: keys.iter().map(|(public_key, _)| &public_key.g1).sum(),
+        g2: keys.iter().map(|(public_key, _)| &public_key.g2).sum(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proof_of_possession() {
+        let keypair = Bn254Keypair::generate();
+        let proof = keypair.proof_of_possession().unwrap();
+
+        assert!(keypair
+            .public_key()
+            .verify_proof_of_possession(&proof)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_proof_of_possession_wrong_key() {
+        let keypair = Bn254Keypair::generate();
+        let other_keypair = Bn254Keypair::generate();
+        let proof = keypair.proof_of_possession().unwrap();
+
+        assert!(matches!(
+            other_keypair
+                .public_key()
+                .verify_proof_of_possession(&proof),
+            Err(ProofOfPossessionError::InvalidProof)
+        ));
+    }
+
+    #[test]
+    fn test_proof_of_possession_is_not_a_signature() {
+        let keypair = Bn254Keypair::generate();
+        let message = pop_message(keypair.public_key()).unwrap();
+
+        // A plain signature over the same bytes must not pass as a proof
+        let signature: Signature = keypair.sign(&message);
+        assert!(keypair
+            .public_key()
+            .verify_proof_of_possession(&signature)
+            .is_err());
+    }
+
+    #[test]
+    fn test_rogue_key_rejected() {
+        let honest = Bn254Keypair::generate();
+        let attacker = Bn254Keypair::generate();
+
+        // pk_rogue = pk_attacker - pk_honest, so the aggregate is controlled by the attacker
+        let rogue_key = PublicKey {
+            g1: attacker.public_key().g1 - honest.public_key().g1,
+            g2: attacker.public_key().g2 - honest.public_key().g2,
+        };
+        let forged_proof = attacker.proof_of_possession().unwrap();
+
+        let keys = [
+            (
+                honest.public_key().clone(),
+                honest.proof_of_possession().unwrap(),
+            ),
+            (rogue_key, forged_proof),
+        ];
+
+        assert!(matches!(
+            aggregate_with_proofs(&keys),
+            Err(ProofOfPossessionError::InvalidProofAt(1))
+        ));
+    }
+
+    #[test]
+    fn test_aggregate_with_proofs() {
+        let keypairs: Vec<_> = (0..3).map(|_| Bn254Keypair::generate()).collect();
+        let keys: Vec<_> = keypairs
+            .iter()
+            .map(|keypair| {
+                (
+                    keypair.public_key().clone(),
+                    keypair.proof_of_possession().unwrap(),
+                )
+            })
+            .collect();
+
+        let aggregate = aggregate_with_proofs(&keys).unwrap();
+        assert_eq!(
+            aggregate.g2,
+            keypairs.iter().map(|kp| &kp.public_key().g2).sum()
+        );
+        assert!(aggregate.check().is_ok());
+        assert!(matches!(
+            aggregate_with_proofs(&[]),
+            Err(ProofOfPossessionError::EmptyAggregate)
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+use alloy::{primitives::U256, sol, sol_types::SolValue};
+use signature::Verifier;
+use thiserror::Error;
+
+use crate::keypair::bn254::{bls::signature::Signature, G1Pubkey, G2Pubkey, PublicKey};
+
+// A U256 bitmap can index at most this many operators
+const MAX_OPERATORS: usize = 256;
+
+sol!(
+    #[derive(Debug, PartialEq, Eq)]
+    struct QuorumCertificateData {
+        uint256 signer_bitmap;
+        G1Pubkey[] non_signer_g1_pubkeys;
+        G2Pubkey signer_g2_apk;
+        Signature signature;
+    }
+);
+
+#[derive(Debug, Error)]
+pub enum QuorumCertificateError {
+    #[error("Operator set of size {0} exceeds the maximum of 256")]
+    TooManyOperators(usize),
+    #[error("Signer bitmap has bits set beyond the operator set")]
+    InvalidBitmap,
+    #[error("No signers in quorum certificate")]
+    NoSigners,
+    #[error("Invalid aggregate signature")]
+    InvalidSignature,
+}
+
+/// An aggregate signature from a subset of an ordered operator set
+///
+/// Bit `i` of the signer bitmap (least significant first) is set iff `operators[i]` signed.
+/// The signers' aggregate key is derived as the total aggregate key minus the non-signers' keys,
+/// the same way DSS contracts compute it from the ABI-encoded [`QuorumCertificateData`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuorumCertificate {
+    operators: Vec<PublicKey>,
+    signer_bitmap: U256,
+    signature: Signature,
+}
+
+impl QuorumCertificate {
+    pub fn new(
+        operators: Vec<PublicKey>,
+        signer_bitmap: U256,
+        signature: Signature,
+    ) -> Result<Self, QuorumCertificateError> {
+        if operators.len() > MAX_OPERATORS {
+            return Err(QuorumCertificateError::TooManyOperators(operators.len()));
+        }
+        if operators.len() < MAX_OPERATORS && signer_bitmap >> operators.len() != U256::ZERO {
+            return Err(QuorumCertificateError::InvalidBitmap);
+        }
+        if signer_bitmap == U256::ZERO {
+            return Err(QuorumCertificateError::NoSigners);
+        }
+
+        Ok(Self {
+            operators,
+            signer_bitmap,
+            signature,
+        })
+    }
+
+    /// Builds the bitmap from the indices of the signing operators
+    pub fn from_signer_indices(
+        operators: Vec<PublicKey>,
+        signer_indices: &[usize],
+        signature: Signature,
+    ) -> Result<Self, QuorumCertificateError> {
+        if operators.len() > MAX_OPERATORS {
+            return Err(QuorumCertificateError::TooManyOperators(operators.len()));
+        }
+
+        let mut signer_bitmap = U256::ZERO;
+        for &index in signer_indices {
+            if index >= operators.len() {
+                return Err(QuorumCertificateError::InvalidBitmap);
+            }
+            signer_bitmap.set_bit(index, true);
+        }
+
+        Self::new(operators, signer_bitmap, signature)
+    }
+
+    pub fn operators(&self) -> &[PublicKey] {
+        &self.operators
+    }
+
+    pub fn signer_bitmap(&self) -> U256 {
+        self.signer_bitmap
+    }
+
+    pub fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    pub fn is_signer(&self, index: usize) -> bool {
+        index < self.operators.len() && self.signer_bitmap.bit(index)
+    }
+
+    pub fn signers(&self) -> impl Iterator<Item = &PublicKey> {
+        self.operators
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| self.is_signer(*index))
+            .map(|(_, operator)| operator)
+    }
+
+    pub fn non_signers(&self) -> impl Iterator<Item = &PublicKey> {
+        self.operators
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !self.is_signer(*index))
+            .map(|(_, operator)| operator)
+    }
+
+    /// The signers' aggregate public key, in both G1 and G2
+    pub fn signer_apk(&self) -> PublicKey {
+        let total_g1: G1Pubkey = self.operators.iter().map(|operator| &operator.g1).sum();
+        let total_g2: G2Pubkey = self.operators.iter().map(|operator| &operator.g2).sum();
+        let non_signer_g1: G1Pubkey = self.non_signers().map(|operator| &operator.g1).sum();
+        let non_signer_g2: G2Pubkey = self.non_signers().map(|operator| &operator.g2).sum();
+
+        PublicKey {
+            g1: total_g1 - non_signer_g1,
+            g2: total_g2 - non_signer_g2,
+        }
+    }
+
+    pub fn verify(&self, message: &[u8]) -> Result<(), QuorumCertificateError> {
+        self.signer_apk()
+            .verify(message, &self.signature)
+            .map_err(|_| QuorumCertificateError::InvalidSignature)
+    }
+
+    pub fn to_data(&self) -> QuorumCertificateData {
+        QuorumCertificateData {
+            signer_bitmap: self.signer_bitmap,
+            non_signer_g1_pubkeys: self.non_signers().map(|operator| operator.g1).collect(),
+            signer_g2_apk: self.signer_apk().g2,
+            signature: self.signature,
+        }
+    }
+
+    pub fn abi_encode(&self) -> Vec<u8> {
+        self.to_data().abi_encode()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use signature::Signer;
+
+    use crate::keypair::{bn254::Keypair, traits::Keypair as _};
+
+    use super::*;
+
+    fn operator_set(size: usize) -> Vec<Keypair> {
+        (0..size).map(|_| Keypair::generate()).collect()
+    }
+
+    fn public_keys(keypairs: &[Keypair]) -> Vec<PublicKey> {
+        keypairs
+            .iter()
+            .map(|keypair| keypair.public_key().clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_quorum_certificate() {
+        let keypairs = operator_set(4);
+        let message = [42u8; 32];
+        let signers = [0, 2, 3];
+
+        let signature: Signature = signers
+            .iter()
+            .map(|&index| keypairs[index].sign(&message))
+            .sum();
+
+        let certificate =
+            QuorumCertificate::from_signer_indices(public_keys(&keypairs), &signers, signature)
+                .unwrap();
+
+        assert_eq!(certificate.signer_bitmap(), U256::from(0b1101));
+        assert_eq!(certificate.non_signers().count(), 1);
+        assert_eq!(
+            certificate.signer_apk().g2,
+            signers
+                .iter()
+                .map(|&index| &keypairs[index].public_key().g2)
+                .sum()
+        );
+        assert!(certificate.verify(&message).is_ok());
+    }
+
+    #[test]
+    fn test_quorum_certificate_wrong_bitmap() {
+        let keypairs = operator_set(4);
+        let message = [42u8; 32];
+
+        let signature: Signature = [0, 1]
+            .iter()
+            .map(|&index: &usize| keypairs[index].sign(&message))
+            .sum();
+
+        let certificate =
+            QuorumCertificate::new(public_keys(&keypairs), U256::from(0b0111), signature).unwrap();
+        assert!(matches!(
+            certificate.verify(&message),
+            Err(QuorumCertificateError::InvalidSignature)
+        ));
+    }
+
+    #[test]
+    fn test_quorum_certificate_invalid_bitmap() {
+        let keypairs = operator_set(2);
+        let signature = keypairs[0].sign(&[42u8; 32]);
+
+        assert!(matches!(
+            QuorumCertificate::new(public_keys(&keypairs), U256::from(0b100), signature),
+            Err(QuorumCertificateError::InvalidBitmap)
+        ));
+        assert!(matches!(
+            QuorumCertificate::new(public_keys(&keypairs), U256::ZERO, signature),
+            Err(QuorumCertificateError::NoSigners)
+        ));
+    }
+
+    #[test]
+    fn test_quorum_certificate_abi_encode() -> eyre::Result<()> {
+        let keypairs = operator_set(3);
+        let message = [42u8; 32];
+        let signature = keypairs[1].sign(&message);
+
+        let certificate =
+            QuorumCertificate::from_signer_indices(public_keys(&keypairs), &[1], signature)?;
+
+        let encoded = certificate.abi_encode();
+        let expected = (
+            U256::from(0b010),
+            vec![keypairs[0].public_key().g1, keypairs[2].public_key().g1],
+            keypairs[1].public_key().g2,
+            signature,
+        )
+            .abi_encode();
+        assert_eq!(encoded, expected);
+
+        let decoded = QuorumCertificateData::abi_decode(&encoded, true)?;
+        assert_eq!(decoded, certificate.to_data());
+        Ok(())
+    }
+}
@@ -1,4 +1,10 @@
-use crate::keypair::bn254::{bls::signature::Signature, G1Pubkey, G2Pubkey};
+use crate::keypair::bn254::{
+    bls::{
+        proof_of_possession::{ProofOfPossession, ProofOfPossessionError},
+        signature::Signature,
+    },
+    G1Pubkey, G2Pubkey, PublicKey,
+};
 use alloy::{
     primitives::{Address, Bytes, TxHash},
     providers::Provider,
@@ -15,8 +21,42 @@ sol!(
         G2Pubkey g2_pubkey;
         Signature signature;
     }
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct BlsRegistrationWithPop {
+        BlsRegistration registration;
+        ProofOfPossession proof_of_possession;
+    }
 );
 
+impl BlsRegistration {
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey {
+            g1: self.g1_pubkey,
+            g2: self.g2_pubkey,
+        }
+    }
+
+    pub fn with_proof_of_possession(
+        self,
+        proof_of_possession: ProofOfPossession,
+    ) -> BlsRegistrationWithPop {
+        BlsRegistrationWithPop {
+            registration: self,
+            proof_of_possession,
+        }
+    }
+}
+
+impl BlsRegistrationWithPop {
+    /// Checks that the registered public key carries a valid proof of possession
+    pub fn validate(&self) -> Result<(), ProofOfPossessionError> {
+        self.registration
+            .public_key()
+            .verify_proof_of_possession(&self.proof_of_possession)
+    }
+}
+
 #[trait_variant::make(Send)]
 pub trait OperatorRegistration {
     async fn register_operator_to_dss_with_data<B: Into<Bytes> + Send + Sync>(
@@ -32,6 +72,15 @@ pub trait OperatorRegistration {
     ) -> eyre::Result<TxHash> {
         self.register_operator_to_dss_with_data(dss, registration.abi_encode())
     }
+
+    /// The proof of possession is not checked here, see [`BlsRegistrationWithPop::validate`]
+    async fn register_operator_to_dss_with_bls_pop(
+        &self,
+        dss: Address,
+        registration: &BlsRegistrationWithPop,
+    ) -> eyre::Result<TxHash> {
+        self.register_operator_to_dss_with_data(dss, registration.abi_encode())
+    }
 }
 
 impl<T: Transport + Clone, P: Provider<T>> OperatorRegistration for CoreInstance<T, P> {
@@ -55,7 +104,9 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
+    use crate::keypair::{bn254::Keypair, traits::Keypair as _};
     use alloy::primitives::U256;
+    use signature::Signer;
 
     #[test]
     fn test_registration_abi_encode() -> eyre::Result<()> {
@@ -100,4 +151,30 @@ mod tests {
         assert_eq!(decoded, registration);
         Ok(())
     }
+
+    #[test]
+    fn test_registration_with_pop() -> eyre::Result<()> {
+        let keypair = Keypair::generate();
+        let other_keypair = Keypair::generate();
+        let message = [42u8; 32];
+
+        let registration = BlsRegistration {
+            g1_pubkey: keypair.public_key().g1,
+            g2_pubkey: keypair.public_key().g2,
+            signature: keypair.sign(&message),
+        }
+        .with_proof_of_possession(keypair.proof_of_possession()?);
+        assert!(registration.validate().is_ok());
+
+        let encoded = registration.abi_encode();
+        let decoded = BlsRegistrationWithPop::abi_decode(&encoded, true)?;
+        assert_eq!(decoded, registration);
+
+        let forged = BlsRegistrationWithPop {
+            proof_of_possession: other_keypair.proof_of_possession()?,
+            ..registration
+        };
+        assert!(forged.validate().is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,287 @@
+use std::{fmt::Display, str::FromStr};
+
+use ark_bn254::Fr;
+use ark_ff::{BigInteger, PrimeField};
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use super::Keypair;
+
+/// Purpose level of Karak key paths, after the EIP this scheme follows
+pub const KARAK_PURPOSE: u32 = 2333;
+/// Second level of Karak key paths, "KRK" in ASCII
+pub const KARAK_COIN_TYPE: u32 = 0x004b_524b;
+
+const MIN_SEED_LENGTH: usize = 32;
+const KEYGEN_SALT: &[u8] = b"BLS-SIG-KEYGEN-SALT-";
+// ceil((3 * ceil(log2(r))) / 16), 48 for both BN254 and BLS12-381
+const OKM_LENGTH: usize = 48;
+const LAMPORT_CHUNKS: usize = 255;
+
+#[derive(Debug, Error)]
+pub enum DerivationError {
+    #[error("Seed must be at least 32 bytes, got {0}")]
+    SeedTooShort(usize),
+    #[error("Invalid derivation path: {0}")]
+    InvalidPath(String),
+}
+
+/// A path of child indices below the master key, written as `m/2333'/...'`
+///
+/// Every EIP-2333 derivation is hardened, so the trailing `'` is optional when parsing.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    pub fn new(indices: Vec<u32>) -> Self {
+        Self(indices)
+    }
+
+    /// `m/2333'/karak'/dss'/index'`, the key an operator uses for the `index`-th key of a DSS
+    pub fn dss(dss: u32, index: u32) -> Self {
+        Self(vec![KARAK_PURPOSE, KARAK_COIN_TYPE, dss, index])
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.0
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = DerivationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut components = s.split('/');
+        if components.next() != Some("m") {
+            return Err(DerivationError::InvalidPath(s.to_string()));
+        }
+
+        components
+            .map(|component| {
+                component
+                    .strip_suffix('\'')
+                    .unwrap_or(component)
+                    .parse::<u32>()
+                    .map_err(|_| DerivationError::InvalidPath(s.to_string()))
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl Display for DerivationPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "m")?;
+        for index in &self.0 {
+            write!(f, "/{index}'")?;
+        }
+        Ok(())
+    }
+}
+
+/// Derives the master secret key from `seed` as in EIP-2333, reducing modulo the scalar field `F`
+pub fn derive_master_secret_key<F: PrimeField>(seed: &[u8]) -> Result<F, DerivationError> {
+    if seed.len() < MIN_SEED_LENGTH {
+        return Err(DerivationError::SeedTooShort(seed.len()));
+    }
+    Ok(hkdf_mod_r(seed, b""))
+}
+
+pub fn derive_child_secret_key<F: PrimeField>(parent: &F, index: u32) -> F {
+    hkdf_mod_r(&parent_secret_key_to_lamport_public_key(parent, index), b"")
+}
+
+pub fn derive_secret_key<F: PrimeField>(
+    seed: &[u8],
+    path: &DerivationPath,
+) -> Result<F, DerivationError> {
+    Ok(path
+        .indices()
+        .iter()
+        .fold(derive_master_secret_key(seed)?, |parent, &index| {
+            derive_child_secret_key(&parent, index)
+        }))
+}
+
+impl Keypair {
+    /// Deterministically derives the keypair at `path` below the master key of `seed`
+    pub fn from_seed_path(seed: &[u8], path: &DerivationPath) -> Result<Self, DerivationError> {
+        Ok(Keypair::from_secret(derive_secret_key::<Fr>(seed, path)?))
+    }
+}
+
+fn hkdf_mod_r<F: PrimeField>(ikm: &[u8], key_info: &[u8]) -> F {
+    let mut salt = Sha256::digest(KEYGEN_SALT);
+    let ikm = [ikm, &[0u8]].concat();
+    let info = [key_info, &(OKM_LENGTH as u16).to_be_bytes()].concat();
+
+    loop {
+        let mut okm = [0u8; OKM_LENGTH];
+        Hkdf::<Sha256>::new(Some(&salt), &ikm)
+            .expand(&info, &mut okm)
+            .expect("48 bytes is a valid HKDF-SHA256 output length");
+
+        let secret_key = F::from_be_bytes_mod_order(&okm);
+        if !secret_key.is_zero() {
+            return secret_key;
+        }
+        salt = Sha256::digest(salt);
+    }
+}
+
+fn ikm_to_lamport_secret_key(ikm: &[u8], salt: &[u8]) -> Vec<[u8; 32]> {
+    let mut okm = vec![0u8; LAMPORT_CHUNKS * 32];
+    Hkdf::<Sha256>::new(Some(salt), ikm)
+        .expand(b"", &mut okm)
+        .expect("255 * 32 bytes is a valid HKDF-SHA256 output length");
+
+    okm.chunks_exact(32)
+        .map(|chunk| chunk.try_into().expect("chunks are 32 bytes"))
+        .collect()
+}
+
+fn parent_secret_key_to_lamport_public_key<F: PrimeField>(parent: &F, index: u32) -> [u8; 32] {
+    let salt = index.to_be_bytes();
+    let mut ikm = parent.into_bigint().to_bytes_be();
+    // I2OSP(parent_SK, 32)
+    ikm.splice(
+        0..0,
+        std::iter::repeat(0).take(32usize.saturating_sub(ikm.len())),
+    );
+    let not_ikm: Vec<u8> = ikm.iter().map(|byte| !byte).collect();
+
+    let mut hasher = Sha256::new();
+    for chunk in ikm_to_lamport_secret_key(&ikm, &salt)
+        .iter()
+        .chain(ikm_to_lamport_secret_key(&not_ikm, &salt).iter())
+    {
+        hasher.update(Sha256::digest(chunk));
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use hex::FromHex;
+
+    use crate::keypair::traits::Keypair as _;
+
+    use super::*;
+
+    fn bls12_381_fr(decimal: &str) -> ark_bls12_381::Fr {
+        ark_bls12_381::Fr::from_str(decimal).unwrap()
+    }
+
+    // https://eips.ethereum.org/EIPS/eip-2333#test-cases
+    #[test]
+    fn test_eip_2333_vectors() {
+        let vectors = [
+            (
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+                "6083874454709270928345386274498605044986640685124978867557563392430687146096",
+                0,
+                "20397789859736650942317412262472558107875392172444076792671091975210932703118",
+            ),
+            (
+                "3141592653589793238462643383279502884197169399375105820974944592",
+                "29757020647961307431480504535336562678282505419141012933316116377660817309383",
+                3141592653,
+                "25457201688850691947727629385191704516744796114925897962676248250929345014287",
+            ),
+            (
+                "0099FF991111002299DD7744EE3355BBDD8844115566CC55663355668888CC00",
+                "27580842291869792442942448775674722299803720648445448686099262467207037398656",
+                4294967295,
+                "29358610794459428860402234341874281240803786294062035874021252734817515685787",
+            ),
+            (
+                "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3",
+                "19022158461524446591288038168518313374041767046816487870552872741050760015818",
+                42,
+                "31372231650479070279774297061823572166496564838472787488249775572789064611981",
+            ),
+        ];
+
+        for (seed, master, index, child) in vectors {
+            let seed = Vec::from_hex(seed).unwrap();
+            let master_secret_key: ark_bls12_381::Fr = derive_master_secret_key(&seed).unwrap();
+            assert_eq!(master_secret_key, bls12_381_fr(master));
+            assert_eq!(
+                derive_child_secret_key(&master_secret_key, index),
+                bls12_381_fr(child)
+            );
+        }
+    }
+
+    // Same derivation as above, reduced modulo the BN254 scalar field instead
+    #[test]
+    fn test_bn254_known_answers() {
+        let seed = Vec::from_hex(
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+        )
+        .unwrap();
+
+        let master: Fr = derive_master_secret_key(&seed).unwrap();
+        assert_eq!(
+            master,
+            Fr::from_str(
+                "16876385784863514523309488032647671531381760176997820269052892961094459323096"
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            derive_child_secret_key(&master, 0),
+            Fr::from_str(
+                "6261163673700163178650738809658100478163222593983987165305930523660281595207"
+            )
+            .unwrap()
+        );
+
+        let keypair = Keypair::from_seed_path(&seed, &DerivationPath::dss(1, 0)).unwrap();
+        assert_eq!(
+            keypair.secret_key().expose_secret(),
+            &Fr::from_str(
+                "19133303594933047342422024083986642611206038621273188655870164481470118522964"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_from_seed_path_is_deterministic() {
+        let seed = [7u8; 32];
+        let path = DerivationPath::dss(1, 0);
+
+        let keypair = Keypair::from_seed_path(&seed, &path).unwrap();
+        assert_eq!(
+            keypair.secret_key(),
+            Keypair::from_seed_path(&seed, &path).unwrap().secret_key()
+        );
+        assert_ne!(
+            keypair.secret_key(),
+            Keypair::from_seed_path(&seed, &DerivationPath::dss(1, 1))
+                .unwrap()
+                .secret_key()
+        );
+        assert!(matches!(
+            Keypair::from_seed_path(&seed[..31], &path),
+            Err(DerivationError::SeedTooShort(31))
+        ));
+    }
+
+    #[test]
+    fn test_derivation_path() {
+        let path: DerivationPath = "m/2333'/4936267'/7'/3".parse().unwrap();
+        assert_eq!(path, DerivationPath::dss(7, 3));
+        assert_eq!(path.to_string(), "m/2333'/4936267'/7'/3'");
+        assert_eq!(
+            "m".parse::<DerivationPath>().unwrap().indices(),
+            &[] as &[u32]
+        );
+
+        for invalid in ["", "2333/1", "m/", "m/karak'", "m/4294967296"] {
+            assert!(invalid.parse::<DerivationPath>().is_err(), "{invalid}");
+        }
+    }
+}
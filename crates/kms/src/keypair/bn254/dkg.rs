@@ -0,0 +1,784 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use ark_bn254::{Bn254, Fr, G1Affine, G2Affine};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{One, UniformRand, Zero};
+use rand::thread_rng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use zeroize::Zeroize;
+
+use super::{
+    threshold::{evaluate_polynomial, FeldmanCommitments, SecretShare, ThresholdError},
+    G1Pubkey, G2Pubkey, Keypair, PublicKey,
+};
+
+#[derive(Debug, Error)]
+pub enum DkgError {
+    #[error("Invalid threshold {threshold} for {participants} participants")]
+    InvalidConfig {
+        threshold: usize,
+        participants: usize,
+    },
+    #[error("Participant index {0} is out of range")]
+    UnknownParticipant(u32),
+    #[error("Expected phase {expected:?}, currently in {actual:?}")]
+    UnexpectedPhase {
+        expected: DkgPhase,
+        actual: DkgPhase,
+    },
+    #[error("Message addressed to participant {0}")]
+    WrongRecipient(u32),
+    #[error("Participant {from} sent a message on behalf of participant {claimed}")]
+    ForgedSender { from: u32, claimed: u32 },
+    #[error("No qualified dealers")]
+    NoQualifiedDealers,
+    #[error("Missing share from qualified dealer {0}")]
+    MissingShare(u32),
+    #[error("Threshold error: {0}")]
+    ThresholdError(#[from] ThresholdError),
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DkgPhase {
+    Dealing,
+    Complaining,
+    Responding,
+    Finalizing,
+    Done,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DkgMessage {
+    /// Broadcast: commitments to the dealer's polynomial, plus its constant term in G1
+    Dealing {
+        dealer: u32,
+        commitments: Vec<G2Pubkey>,
+        g1_commitment: G1Pubkey,
+    },
+    /// Private: the dealer's polynomial evaluated at the recipient's index
+    Share {
+        dealer: u32,
+        recipient: u32,
+        #[serde(with = "fr_hex")]
+        share: Fr,
+    },
+    /// Broadcast: the complainer received an invalid or no share from the dealer
+    Complaint { complainer: u32, dealer: u32 },
+    /// Broadcast: the dealer reveals the share it owes the complainer
+    Response {
+        dealer: u32,
+        recipient: u32,
+        #[serde(with = "fr_hex")]
+        share: Fr,
+    },
+}
+
+impl DkgMessage {
+    /// The participant the message claims to be from, which must match the authenticated sender
+    pub fn sender(&self) -> u32 {
+        match self {
+            DkgMessage::Dealing { dealer, .. }
+            | DkgMessage::Share { dealer, .. }
+            | DkgMessage::Response { dealer, .. } => *dealer,
+            DkgMessage::Complaint { complainer, .. } => *complainer,
+        }
+    }
+
+    /// The participant a private message must be delivered to, `None` for broadcasts
+    pub fn recipient(&self) -> Option<u32> {
+        match self {
+            DkgMessage::Share { recipient, .. } => Some(*recipient),
+            _ => None,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, DkgError> {
+        Ok(serde_json::to_vec(self)?)
+    }
+
+    pub fn from_bytes<B: AsRef<[u8]>>(bytes: B) -> Result<Self, DkgError> {
+        Ok(serde_json::from_slice(bytes.as_ref())?)
+    }
+}
+
+/// The result of a successful DKG for one participant
+#[derive(Clone, Debug)]
+pub struct DkgOutput {
+    pub share: SecretShare,
+    pub public_key: PublicKey,
+    pub commitments: FeldmanCommitments,
+    pub qualified: Vec<u32>,
+}
+
+#[derive(Debug)]
+struct Dealing {
+    commitments: FeldmanCommitments,
+    g1_commitment: G1Pubkey,
+}
+
+/// One participant in a Joint-Feldman distributed key generation
+///
+/// Each round is closed by the caller once its messages have been exchanged: [`Self::deal`],
+/// [`Self::complain`], [`Self::respond`] and finally [`Self::finalize`]. Messages are plain
+/// serializable values so they can be carried over any transport, e.g. `karak_p2p` gossip.
+/// Every message needs an authenticated channel, and messages with a recipient carry a secret
+/// share so theirs must be confidential too.
+pub struct DkgParticipant {
+    index: u32,
+    threshold: usize,
+    participants: usize,
+    phase: DkgPhase,
+    polynomial: Vec<Fr>,
+    dealings: BTreeMap<u32, Dealing>,
+    shares: BTreeMap<u32, Fr>,
+    // (dealer, complainer) pairs that have not been answered with a valid share yet
+    complaints: BTreeSet<(u32, u32)>,
+    disqualified: BTreeSet<u32>,
+}
+
+impl DkgParticipant {
+    /// `index` is this participant's 1-based position among `participants`
+    pub fn new(index: u32, threshold: usize, participants: usize) -> Result<Self, DkgError> {
+        if threshold == 0 || threshold > participants || participants > u32::MAX as usize {
+            return Err(DkgError::InvalidConfig {
+                threshold,
+                participants,
+            });
+        }
+        if index == 0 || index as usize > participants {
+            return Err(DkgError::UnknownParticipant(index));
+        }
+
+        let mut rng = thread_rng();
+        let polynomial = (0..threshold).map(|_| Fr::rand(&mut rng)).collect();
+
+        Ok(Self {
+            index,
+            threshold,
+            participants,
+            phase: DkgPhase::Dealing,
+            polynomial,
+            dealings: BTreeMap::new(),
+            shares: BTreeMap::new(),
+            complaints: BTreeSet::new(),
+            disqualified: BTreeSet::new(),
+        })
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn phase(&self) -> DkgPhase {
+        self.phase
+    }
+
+    /// Round 1: the dealing broadcast followed by one private share for every other participant
+    pub fn deal(&mut self) -> Result<Vec<DkgMessage>, DkgError> {
+        self.expect_phase(DkgPhase::Dealing)?;
+
+        let commitments: Vec<G2Pubkey> = self
+            .polynomial
+            .iter()
+            .map(|coefficient| G2Pubkey::from((G2Affine::generator() * coefficient).into_affine()))
+            .collect();
+        let g1_commitment =
+            G1Pubkey::from((G1Affine::generator() * self.polynomial[0]).into_affine());
+
+        self.dealings.insert(
+            self.index,
+            Dealing {
+                commitments: FeldmanCommitments::new(commitments.clone()),
+                g1_commitment,
+            },
+        );
+        self.shares.insert(
+            self.index,
+            evaluate_polynomial(&self.polynomial, self.index),
+        );
+
+        let mut messages = vec![DkgMessage::Dealing {
+            dealer: self.index,
+            commitments,
+            g1_commitment,
+        }];
+        messages.extend(
+            self.other_participants()
+                .map(|recipient| DkgMessage::Share {
+                    dealer: self.index,
+                    recipient,
+                    share: evaluate_polynomial(&self.polynomial, recipient),
+                }),
+        );
+
+        self.phase = DkgPhase::Complaining;
+        Ok(messages)
+    }
+
+    /// Records a message from another participant, in any phase before finalization. Complaints
+    /// are only accepted until [`Self::respond`] is called
+    ///
+    /// `from` is the sender as authenticated by the transport. Messages claiming to come from
+    /// anyone else are rejected, otherwise a participant could get honest dealers disqualified by
+    /// forging their dealings or responses.
+    pub fn handle_message(&mut self, from: u32, message: DkgMessage) -> Result<(), DkgError> {
+        if self.phase == DkgPhase::Done {
+            return Err(DkgError::UnexpectedPhase {
+                expected: DkgPhase::Finalizing,
+                actual: self.phase,
+            });
+        }
+
+        self.check_participant(from)?;
+        let claimed = message.sender();
+        if claimed != from {
+            return Err(DkgError::ForgedSender { from, claimed });
+        }
+        if from == self.index {
+            return Ok(());
+        }
+
+        match message {
+            DkgMessage::Dealing {
+                dealer,
+                commitments,
+                g1_commitment,
+            } => self.handle_dealing(dealer, commitments, g1_commitment),
+            DkgMessage::Share {
+                dealer,
+                recipient,
+                share,
+            } => {
+                if recipient != self.index {
+                    return Err(DkgError::WrongRecipient(recipient));
+                }
+                self.shares.insert(dealer, share);
+            }
+            DkgMessage::Complaint { complainer, dealer } => {
+                // A complaint that arrives after the response round could no longer be answered,
+                // so the dealer would be disqualified only by the participants that accepted it
+                if !matches!(self.phase, DkgPhase::Complaining | DkgPhase::Responding) {
+                    return Err(DkgError::UnexpectedPhase {
+                        expected: DkgPhase::Responding,
+                        actual: self.phase,
+                    });
+                }
+                self.check_participant(dealer)?;
+                self.complaints.insert((dealer, complainer));
+            }
+            DkgMessage::Response {
+                dealer,
+                recipient,
+                share,
+            } => {
+                self.check_participant(recipient)?;
+                self.handle_response(dealer, recipient, share);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Round 2: complaints against every dealer whose share is missing or inconsistent
+    pub fn complain(&mut self) -> Result<Vec<DkgMessage>, DkgError> {
+        self.expect_phase(DkgPhase::Complaining)?;
+
+        let mut messages = Vec::new();
+        for dealer in self.other_participants() {
+            if self.disqualified.contains(&dealer) || !self.dealings.contains_key(&dealer) {
+                continue;
+            }
+            if !self.has_valid_share_from(dealer) {
+                self.complaints.insert((dealer, self.index));
+                messages.push(DkgMessage::Complaint {
+                    complainer: self.index,
+                    dealer,
+                });
+            }
+        }
+
+        self.phase = DkgPhase::Responding;
+        Ok(messages)
+    }
+
+    /// Round 3: reveal the shares this participant was complained about
+    pub fn respond(&mut self) -> Result<Vec<DkgMessage>, DkgError> {
+        self.expect_phase(DkgPhase::Responding)?;
+
+        let recipients: Vec<u32> = self
+            .complaints
+            .iter()
+            .filter(|(dealer, _)| *dealer == self.index)
+            .map(|(_, recipient)| *recipient)
+            .collect();
+
+        let mut messages = Vec::with_capacity(recipients.len());
+        for recipient in recipients {
+            let share = evaluate_polynomial(&self.polynomial, recipient);
+            self.handle_response(self.index, recipient, share);
+            messages.push(DkgMessage::Response {
+                dealer: self.index,
+                recipient,
+                share,
+            });
+        }
+
+        self.phase = DkgPhase::Finalizing;
+        Ok(messages)
+    }
+
+    /// Disqualifies dealers that were silent or left complaints unanswered, and derives this
+    /// participant's share and the group public key from the remaining ones
+    pub fn finalize(&mut self) -> Result<DkgOutput, DkgError> {
+        self.expect_phase(DkgPhase::Finalizing)?;
+
+        let unanswered: Vec<u32> = self.complaints.iter().map(|(dealer, _)| *dealer).collect();
+        self.disqualified.extend(unanswered);
+
+        let qualified: Vec<u32> = self
+            .dealings
+            .keys()
+            .copied()
+            .filter(|dealer| !self.disqualified.contains(dealer))
+            .collect();
+        if qualified.is_empty() {
+            return Err(DkgError::NoQualifiedDealers);
+        }
+
+        let mut secret = Fr::zero();
+        let mut commitments = vec![G2Affine::zero().into_group(); self.threshold];
+        let mut g1_public_key = G1Affine::zero().into_group();
+
+        for dealer in &qualified {
+            if !self.has_valid_share_from(*dealer) {
+                return Err(DkgError::MissingShare(*dealer));
+            }
+            secret += self.shares[dealer];
+
+            let dealing = &self.dealings[dealer];
+            for (sum, commitment) in commitments
+                .iter_mut()
+                .zip(dealing.commitments.commitments())
+            {
+                *sum += commitment.0;
+            }
+            g1_public_key += dealing.g1_commitment.0;
+        }
+
+        let commitments = FeldmanCommitments::new(
+            commitments
+                .into_iter()
+                .map(|commitment| G2Pubkey::from(commitment.into_affine()))
+                .collect(),
+        );
+        let public_key = PublicKey {
+            g1: G1Pubkey::from(g1_public_key.into_affine()),
+            g2: commitments.group_public_key(),
+        };
+
+        let share = SecretShare::new(self.index, Keypair::from_secret(secret))?;
+        commitments.verify_share(&share)?;
+
+        self.phase = DkgPhase::Done;
+        Ok(DkgOutput {
+            share,
+            public_key,
+            commitments,
+            qualified,
+        })
+    }
+
+    fn handle_dealing(&mut self, dealer: u32, commitments: Vec<G2Pubkey>, g1: G1Pubkey) {
+        // A dealing that arrives after the complaint round could no longer be complained about
+        if !self.dealings.contains_key(&dealer)
+            && matches!(self.phase, DkgPhase::Responding | DkgPhase::Finalizing)
+        {
+            self.disqualified.insert(dealer);
+            return;
+        }
+
+        let well_formed = commitments.len() == self.threshold
+            && Bn254::multi_pairing(
+                [g1.0, -G1Affine::generator()],
+                [G2Affine::generator(), commitments[0].0],
+            )
+            .0
+            .is_one();
+
+        let dealing = Dealing {
+            commitments: FeldmanCommitments::new(commitments),
+            g1_commitment: g1,
+        };
+
+        match self.dealings.get(&dealer) {
+            // Equivocating dealers are disqualified
+            Some(existing)
+                if existing.commitments != dealing.commitments
+                    || existing.g1_commitment != dealing.g1_commitment =>
+            {
+                self.disqualified.insert(dealer);
+            }
+            Some(_) => {}
+            None => {
+                if !well_formed {
+                    self.disqualified.insert(dealer);
+                }
+                self.dealings.insert(dealer, dealing);
+            }
+        }
+    }
+
+    fn handle_response(&mut self, dealer: u32, recipient: u32, share: Fr) {
+        let valid = self.dealings.get(&dealer).is_some_and(|dealing| {
+            dealing.commitments.share_public_key(recipient).0
+                == (G2Affine::generator() * share).into_affine()
+        });
+
+        if !valid {
+            self.disqualified.insert(dealer);
+            return;
+        }
+
+        self.complaints.remove(&(dealer, recipient));
+        if recipient == self.index {
+            self.shares.insert(dealer, share);
+        }
+    }
+
+    fn has_valid_share_from(&self, dealer: u32) -> bool {
+        match (self.dealings.get(&dealer), self.shares.get(&dealer)) {
+            (Some(dealing), Some(share)) => {
+                dealing.commitments.share_public_key(self.index).0
+                    == (G2Affine::generator() * share).into_affine()
+            }
+            _ => false,
+        }
+    }
+
+    fn other_participants(&self) -> impl Iterator<Item = u32> {
+        let index = self.index;
+        (1..=self.participants as u32).filter(move |&i| i != index)
+    }
+
+    fn check_participant(&self, index: u32) -> Result<(), DkgError> {
+        if index == 0 || index as usize > self.participants {
+            return Err(DkgError::UnknownParticipant(index));
+        }
+        Ok(())
+    }
+
+    fn expect_phase(&self, expected: DkgPhase) -> Result<(), DkgError> {
+        if self.phase != expected {
+            return Err(DkgError::UnexpectedPhase {
+                expected,
+                actual: self.phase,
+            });
+        }
+        Ok(())
+    }
+}
+
+// The polynomial and received shares are secret key material
+impl Drop for DkgParticipant {
+    fn drop(&mut self) {
+        self.polynomial.zeroize();
+        self.shares.values_mut().for_each(Zeroize::zeroize);
+    }
+}
+
+mod fr_hex {
+    use ark_bn254::Fr;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &Fr, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut bytes = Vec::new();
+        value
+            .serialize_compressed(&mut bytes)
+            .map_err(serde::ser::Error::custom)?;
+        serializer.serialize_str(&hex::encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Fr, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        let bytes = hex::decode(s).map_err(serde::de::Error::custom)?;
+        Fr::deserialize_compressed(bytes.as_slice()).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_serialize::Valid;
+    use signature::{Signer, Verifier};
+
+    use super::{super::threshold::combine_partial_signatures, *};
+
+    // Delivers every message to its recipient(s), round-tripping it through bytes on the way
+    fn deliver(participants: &mut [DkgParticipant], messages: Vec<DkgMessage>) {
+        for message in messages {
+            let bytes = message.to_bytes().unwrap();
+            for participant in participants.iter_mut() {
+                let is_recipient = match message.recipient() {
+                    Some(recipient) => recipient == participant.index(),
+                    None => true,
+                };
+                if is_recipient {
+                    participant
+                        .handle_message(message.sender(), DkgMessage::from_bytes(&bytes).unwrap())
+                        .unwrap();
+                }
+            }
+        }
+    }
+
+    fn run_round<F>(participants: &mut [DkgParticipant], mut round: F)
+    where
+        F: FnMut(&mut DkgParticipant) -> Vec<DkgMessage>,
+    {
+        let messages: Vec<_> = participants.iter_mut().flat_map(&mut round).collect();
+        deliver(participants, messages);
+    }
+
+    fn participants(threshold: usize, count: usize) -> Vec<DkgParticipant> {
+        (1..=count as u32)
+            .map(|index| DkgParticipant::new(index, threshold, count).unwrap())
+            .collect()
+    }
+
+    fn assert_threshold_signing(outputs: &[DkgOutput], threshold: usize) {
+        let message = [42u8; 32];
+        let partials: Vec<_> = outputs
+            .iter()
+            .rev()
+            .take(threshold)
+            .map(|output| output.share.sign(&message))
+            .collect();
+        let signature = combine_partial_signatures(&partials, threshold).unwrap();
+
+        let public_key = &outputs[0].public_key;
+        assert!(public_key.g2.verify(&message, &signature).is_ok());
+        assert!(public_key.verify(&message, &signature).is_ok());
+    }
+
+    #[test]
+    fn test_dkg_honest_participants() {
+        let mut participants = participants(3, 5);
+
+        run_round(&mut participants, |p| p.deal().unwrap());
+        run_round(&mut participants, |p| p.complain().unwrap());
+        run_round(&mut participants, |p| p.respond().unwrap());
+
+        let outputs: Vec<_> = participants
+            .iter_mut()
+            .map(|p| p.finalize().unwrap())
+            .collect();
+
+        for output in &outputs {
+            assert_eq!(output.public_key, outputs[0].public_key);
+            assert_eq!(output.qualified, vec![1, 2, 3, 4, 5]);
+            assert!(output.public_key.check().is_ok());
+        }
+        assert_threshold_signing(&outputs, 3);
+    }
+
+    #[test]
+    fn test_dkg_complaints() {
+        let mut participants = participants(2, 4);
+
+        // Dealer 1 sends a bad share to participant 2 but answers the complaint honestly, while
+        // dealer 3 sends a bad share to participant 4 and never answers
+        let mut dealing_messages = Vec::new();
+        let mut withheld = None;
+        for participant in participants.iter_mut() {
+            for message in participant.deal().unwrap() {
+                match message {
+                    DkgMessage::Share {
+                        dealer: 1,
+                        recipient: 2,
+                        share,
+                    } => dealing_messages.push(DkgMessage::Share {
+                        dealer: 1,
+                        recipient: 2,
+                        share: share + Fr::one(),
+                    }),
+                    DkgMessage::Share {
+                        dealer: 3,
+                        recipient: 4,
+                        share,
+                    } => withheld = Some(share),
+                    message => dealing_messages.push(message),
+                }
+            }
+        }
+        assert!(withheld.is_some());
+        deliver(&mut participants, dealing_messages);
+
+        let complaints: Vec<_> = participants
+            .iter_mut()
+            .flat_map(|p| p.complain().unwrap())
+            .collect();
+        assert_eq!(
+            complaints,
+            vec![
+                DkgMessage::Complaint {
+                    complainer: 2,
+                    dealer: 1
+                },
+                DkgMessage::Complaint {
+                    complainer: 4,
+                    dealer: 3
+                },
+            ]
+        );
+        deliver(&mut participants, complaints);
+
+        let responses: Vec<_> = participants
+            .iter_mut()
+            .flat_map(|p| p.respond().unwrap())
+            .filter(|message| message.sender() != 3)
+            .collect();
+        deliver(&mut participants, responses);
+
+        let outputs: Vec<_> = participants
+            .iter_mut()
+            .map(|p| p.finalize().unwrap())
+            .collect();
+
+        // Dealer 3 is excluded by every honest participant
+        let honest_outputs: Vec<_> = outputs
+            .into_iter()
+            .filter(|output| output.share.index() != 3)
+            .collect();
+        for output in &honest_outputs {
+            assert_eq!(output.qualified, vec![1, 2, 4]);
+            assert_eq!(output.public_key, honest_outputs[0].public_key);
+        }
+        assert_threshold_signing(&honest_outputs, 2);
+    }
+
+    #[test]
+    fn test_dkg_forged_messages() {
+        let mut participants = participants(2, 3);
+        let dealings: Vec<_> = participants.iter_mut().map(|p| p.deal().unwrap()).collect();
+
+        // Participant 3 replays dealer 1's dealing with different commitments
+        let DkgMessage::Dealing {
+            mut commitments,
+            g1_commitment,
+            ..
+        } = dealings[0][0].clone()
+        else {
+            panic!("expected a dealing");
+        };
+        commitments.reverse();
+        let forged_dealing = DkgMessage::Dealing {
+            dealer: 1,
+            commitments,
+            g1_commitment,
+        };
+        assert!(matches!(
+            participants[1].handle_message(3, forged_dealing),
+            Err(DkgError::ForgedSender {
+                from: 3,
+                claimed: 1
+            })
+        ));
+
+        for messages in dealings {
+            deliver(&mut participants, messages);
+        }
+        run_round(&mut participants, |p| p.complain().unwrap());
+        run_round(&mut participants, |p| p.respond().unwrap());
+
+        // Participant 3 answers a complaint on behalf of dealer 1 with a garbage share
+        let forged_response = DkgMessage::Response {
+            dealer: 1,
+            recipient: 2,
+            share: Fr::one(),
+        };
+        assert!(matches!(
+            participants[1].handle_message(3, forged_response),
+            Err(DkgError::ForgedSender { .. })
+        ));
+
+        for participant in participants.iter_mut() {
+            assert_eq!(participant.finalize().unwrap().qualified, vec![1, 2, 3]);
+        }
+    }
+
+    #[test]
+    fn test_dkg_late_dealing() {
+        let mut participants = participants(2, 3);
+        let mut dealings: Vec<_> = participants.iter_mut().map(|p| p.deal().unwrap()).collect();
+
+        // Dealer 3's messages only arrive once the complaint round is over
+        let late = dealings.pop().unwrap();
+        for messages in dealings {
+            deliver(&mut participants, messages);
+        }
+        run_round(&mut participants, |p| p.complain().unwrap());
+        deliver(&mut participants, late);
+        run_round(&mut participants, |p| p.respond().unwrap());
+
+        for participant in participants.iter_mut().take(2) {
+            assert_eq!(participant.finalize().unwrap().qualified, vec![1, 2]);
+        }
+    }
+
+    #[test]
+    fn test_dkg_late_complaint() {
+        let mut participants = participants(2, 3);
+        run_round(&mut participants, |p| p.deal().unwrap());
+        run_round(&mut participants, |p| p.complain().unwrap());
+        run_round(&mut participants, |p| p.respond().unwrap());
+
+        // Participant 2 complains about the honest dealer 1 once it can no longer respond
+        let complaint = DkgMessage::Complaint {
+            complainer: 2,
+            dealer: 1,
+        };
+        for participant in participants.iter_mut().filter(|p| p.index() != 2) {
+            assert!(matches!(
+                participant.handle_message(2, complaint.clone()),
+                Err(DkgError::UnexpectedPhase {
+                    actual: DkgPhase::Finalizing,
+                    ..
+                })
+            ));
+        }
+
+        let outputs: Vec<_> = participants
+            .iter_mut()
+            .map(|p| p.finalize().unwrap())
+            .collect();
+        for output in &outputs {
+            assert_eq!(output.qualified, vec![1, 2, 3]);
+            assert_eq!(output.public_key, outputs[0].public_key);
+        }
+    }
+
+    #[test]
+    fn test_dkg_phase_order() {
+        let mut participant = DkgParticipant::new(1, 2, 3).unwrap();
+        assert!(matches!(
+            participant.complain(),
+            Err(DkgError::UnexpectedPhase {
+                expected: DkgPhase::Complaining,
+                actual: DkgPhase::Dealing
+            })
+        ));
+        assert!(participant.deal().is_ok());
+        assert!(participant.deal().is_err());
+    }
+
+    #[test]
+    fn test_dkg_invalid_config() {
+        assert!(DkgParticipant::new(1, 0, 3).is_err());
+        assert!(DkgParticipant::new(1, 4, 3).is_err());
+        assert!(DkgParticipant::new(0, 2, 3).is_err());
+        assert!(DkgParticipant::new(4, 2, 3).is_err());
+    }
+}
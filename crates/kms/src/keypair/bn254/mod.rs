@@ -2,26 +2,35 @@ use std::{fmt::Display, str::FromStr};
 
 use ark_bn254::{Fr, G1Affine, G2Affine};
 use ark_ec::{AffineRepr, CurveGroup};
-use ark_ff::UniformRand;
+use ark_ff::{BigInt, BigInteger, PrimeField, UniformRand, Zero};
 use ark_serialize::{
     CanonicalDeserialize, CanonicalSerialize, Compress, SerializationError, Valid, Validate,
 };
 use rand::thread_rng;
 use serde::Deserialize;
 use thiserror::Error;
+use zeroize::Zeroizing;
 
 use super::traits::Keypair as KeypairTrait;
 
 pub mod algebra;
 pub mod bls;
+pub mod derivation;
+pub mod dkg;
 mod encryption;
 mod pubkey;
+mod secret_key;
+pub mod threshold;
 pub use encryption::*;
 pub use pubkey::*;
+pub use secret_key::*;
+
+/// Length of the big-endian secret key encoding used by [`KeypairTrait::to_secret_bytes`]
+pub const SECRET_KEY_LENGTH: usize = 32;
 
 #[derive(Clone, Debug)]
 pub struct Keypair {
-    secret_key: Fr,
+    secret_key: SecretKey,
     public_key: PublicKey,
 }
 
@@ -33,12 +42,20 @@ pub enum Bn254Error {
     DecodingError(#[from] bs58::decode::Error),
     #[error("Invalid public key")]
     InvalidPublicKey,
+    #[error("Secret key must be {SECRET_KEY_LENGTH} bytes, got {0}")]
+    InvalidSecretKeyLength(usize),
+    #[error("Secret key is not a canonical scalar")]
+    NonCanonicalSecretKey,
+    #[error("Secret key must be non-zero")]
+    ZeroSecretKey,
+    #[error("Hex decoding error: {0}")]
+    HexDecodingError(#[from] hex::FromHexError),
 }
 
 impl Keypair {
     pub fn new(secret_key: Fr, public_key: PublicKey) -> Result<Self, Bn254Error> {
         let keypair = Self {
-            secret_key,
+            secret_key: SecretKey::new(secret_key),
             public_key,
         };
 
@@ -46,6 +63,20 @@ impl Keypair {
 
         Ok(keypair)
     }
+
+    // Derives both public keys from the secret key
+    fn from_secret(secret_key: Fr) -> Self {
+        let g1_public_key = (G1Affine::generator() * secret_key).into_affine();
+        let g2_public_key = (G2Affine::generator() * secret_key).into_affine();
+
+        Self {
+            secret_key: SecretKey::new(secret_key),
+            public_key: PublicKey {
+                g1: g1_public_key.into(),
+                g2: g2_public_key.into(),
+            },
+        }
+    }
 }
 
 impl From<SerializationError> for Bn254Error {
@@ -60,11 +91,13 @@ impl CanonicalSerialize for Keypair {
         writer: W,
         compress: Compress,
     ) -> Result<(), SerializationError> {
-        self.secret_key.serialize_with_mode(writer, compress)
+        self.secret_key
+            .expose_secret()
+            .serialize_with_mode(writer, compress)
     }
 
     fn serialized_size(&self, compress: Compress) -> usize {
-        self.secret_key.serialized_size(compress)
+        self.secret_key.expose_secret().serialized_size(compress)
     }
 }
 
@@ -74,20 +107,8 @@ impl CanonicalDeserialize for Keypair {
         compress: Compress,
         validate: Validate,
     ) -> Result<Self, SerializationError> {
-        let secret_key = Fr::deserialize_with_mode(reader, compress, validate)?;
-
-        let g1_public_key = (G1Affine::generator() * secret_key).into_affine();
-        let g2_public_key = (G2Affine::generator() * secret_key).into_affine();
-
-        let public_key = PublicKey {
-            g1: g1_public_key.into(),
-            g2: g2_public_key.into(),
-        };
-
-        let keypair = Keypair {
-            secret_key,
-            public_key,
-        };
+        let secret_key = SecretKey::new(Fr::deserialize_with_mode(reader, compress, validate)?);
+        let keypair = Keypair::from_secret(*secret_key.expose_secret());
 
         if let Validate::Yes = validate {
             keypair.check()?;
@@ -99,7 +120,7 @@ impl CanonicalDeserialize for Keypair {
 
 impl Valid for Keypair {
     fn check(&self) -> Result<(), SerializationError> {
-        self.secret_key.check()?;
+        self.secret_key.expose_secret().check()?;
         self.public_key.check()?;
 
         Ok(())
@@ -107,9 +128,10 @@ impl Valid for Keypair {
 }
 
 impl Keypair {
-    pub fn to_bytes(&self) -> Result<Vec<u8>, Bn254Error> {
-        let mut bytes = Vec::new();
-        self.serialize_uncompressed(&mut bytes)?;
+    /// The serialized secret key, wiped when dropped
+    pub fn to_bytes(&self) -> Result<Zeroizing<Vec<u8>>, Bn254Error> {
+        let mut bytes = Zeroizing::new(Vec::new());
+        self.serialize_uncompressed(&mut *bytes)?;
         Ok(bytes)
     }
 
@@ -127,22 +149,42 @@ impl TryFrom<&[u8]> for Keypair {
 }
 
 impl KeypairTrait for Keypair {
-    type SecretKey = Fr;
+    type SecretKey = SecretKey;
     type PublicKey = PublicKey;
+    type KeypairError = Bn254Error;
 
     fn generate() -> Self {
         let mut rng = thread_rng();
-        let secret_key = Fr::rand(&mut rng);
-        let g1_public_key = (G1Affine::generator() * secret_key).into_affine();
-        let g2_public_key = (G2Affine::generator() * secret_key).into_affine();
+        Keypair::from_secret(Fr::rand(&mut rng))
+    }
 
-        Self {
-            secret_key,
-            public_key: PublicKey {
-                g1: g1_public_key.into(),
-                g2: g2_public_key.into(),
-            },
+    fn from_secret_key(secret_key: SecretKey) -> Result<Self, Bn254Error> {
+        if secret_key.expose_secret().is_zero() {
+            return Err(Bn254Error::ZeroSecretKey);
         }
+        Ok(Keypair::from_secret(*secret_key.expose_secret()))
+    }
+
+    /// Expects the 32-byte big-endian encoding of a non-zero scalar below the group order
+    fn from_secret_bytes(bytes: &[u8]) -> Result<Self, Bn254Error> {
+        let bytes: Zeroizing<[u8; SECRET_KEY_LENGTH]> = Zeroizing::new(
+            bytes
+                .try_into()
+                .map_err(|_| Bn254Error::InvalidSecretKeyLength(bytes.len()))?,
+        );
+
+        let mut limbs = Zeroizing::new([0u64; 4]);
+        for (limb, chunk) in limbs.iter_mut().zip(bytes.rchunks_exact(8)) {
+            *limb = u64::from_be_bytes(chunk.try_into().expect("chunks are 8 bytes"));
+        }
+
+        // from_bigint rejects values that are not reduced modulo the group order
+        let scalar = Fr::from_bigint(BigInt(*limbs)).ok_or(Bn254Error::NonCanonicalSecretKey)?;
+        Self::from_secret_key(SecretKey::new(scalar))
+    }
+
+    fn to_secret_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.secret_key.expose_secret().into_bigint().to_bytes_be())
     }
 
     fn secret_key(&self) -> &Self::SecretKey {
@@ -181,9 +223,6 @@ impl<'de> Deserialize<'de> for Keypair {
 
 #[cfg(test)]
 mod tests {
-    // TODO: More exhaustive tests with pre-loaded keypairs
-    //       The above will need methods to load keypairs from bytes
-
     use super::*;
 
     #[test]
@@ -194,7 +233,7 @@ mod tests {
 
         keypair
             .secret_key()
-            .0
+            .expose_secret()
             .serialize_with_mode(&mut secret_key_bytes, Compress::Yes)
             .unwrap();
 
@@ -207,4 +246,74 @@ mod tests {
         assert_eq!(secret_key_bytes.len(), 32);
         assert_eq!(public_key_bytes.len(), 64);
     }
+
+    #[test]
+    fn test_keypair_debug_redacts_secret_key() {
+        let keypair = Keypair::generate();
+        let debug = format!("{keypair:?}");
+
+        assert!(debug.contains("SecretKey(<redacted>)"));
+        assert!(!debug.contains(&keypair.secret_key().expose_secret().to_string()));
+    }
+
+    #[test]
+    fn test_secret_bytes_roundtrip() {
+        let keypair = Keypair::generate();
+        let bytes = keypair.to_secret_bytes();
+        assert_eq!(bytes.len(), SECRET_KEY_LENGTH);
+
+        let restored = Keypair::from_secret_bytes(&bytes).unwrap();
+        assert_eq!(restored.secret_key(), keypair.secret_key());
+        assert_eq!(restored.public_key(), keypair.public_key());
+
+        let hex = format!("0x{}", hex::encode(bytes.as_slice()));
+        let restored = Keypair::from_hex(&hex).unwrap();
+        assert_eq!(restored.public_key(), keypair.public_key());
+        assert_eq!(
+            Keypair::from_hex(&hex[2..]).unwrap().public_key(),
+            keypair.public_key()
+        );
+    }
+
+    #[test]
+    fn test_from_secret_bytes_known_answer() {
+        let keypair =
+            Keypair::from_hex("0x0000000000000000000000000000000000000000000000000000000000000001")
+                .unwrap();
+        assert_eq!(keypair.secret_key().expose_secret(), &Fr::from(1u64));
+        assert_eq!(keypair.public_key().g1.0, G1Affine::generator());
+        assert_eq!(keypair.public_key().g2.0, G2Affine::generator());
+    }
+
+    #[test]
+    fn test_from_secret_bytes_rejects_invalid_scalars() {
+        // The group order r and r + 1 are not reduced
+        let order = "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001";
+        let order_plus_one = "30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000002";
+
+        assert!(matches!(
+            Keypair::from_hex(order),
+            Err(Bn254Error::NonCanonicalSecretKey)
+        ));
+        assert!(matches!(
+            Keypair::from_hex(order_plus_one),
+            Err(Bn254Error::NonCanonicalSecretKey)
+        ));
+        assert!(matches!(
+            Keypair::from_secret_bytes(&[0u8; 32]),
+            Err(Bn254Error::ZeroSecretKey)
+        ));
+        assert!(matches!(
+            Keypair::from_secret_bytes(&[1u8; 31]),
+            Err(Bn254Error::InvalidSecretKeyLength(31))
+        ));
+        assert!(matches!(
+            Keypair::from_hex("0xzz"),
+            Err(Bn254Error::HexDecodingError(_))
+        ));
+        assert!(matches!(
+            Keypair::from_secret_key(SecretKey::new(Fr::zero())),
+            Err(Bn254Error::ZeroSecretKey)
+        ));
+    }
 }
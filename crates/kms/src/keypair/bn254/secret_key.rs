@@ -0,0 +1,76 @@
+use std::fmt::Debug;
+
+use ark_bn254::Fr;
+use subtle::{Choice, ConstantTimeEq};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// A BN254 secret scalar that is wiped from memory on drop and never printed
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct SecretKey(Fr);
+
+impl SecretKey {
+    pub fn new(scalar: Fr) -> Self {
+        Self(scalar)
+    }
+
+    /// The underlying scalar, any copies of which are not wiped on drop
+    pub fn expose_secret(&self) -> &Fr {
+        &self.0
+    }
+}
+
+impl From<Fr> for SecretKey {
+    fn from(scalar: Fr) -> Self {
+        Self(scalar)
+    }
+}
+
+impl ConstantTimeEq for SecretKey {
+    fn ct_eq(&self, other: &Self) -> Choice {
+        // Montgomery form is unique for reduced scalars, so the limbs can be compared directly
+        self.0 .0 .0.ct_eq(&other.0 .0 .0)
+    }
+}
+
+impl PartialEq for SecretKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.ct_eq(other).into()
+    }
+}
+
+impl Eq for SecretKey {}
+
+impl Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretKey(<redacted>)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ff::{UniformRand, Zero};
+    use rand::thread_rng;
+
+    use super::*;
+
+    #[test]
+    fn test_secret_key_redacted() {
+        let scalar = Fr::from(42u64);
+        let secret_key = SecretKey::new(scalar);
+
+        assert_eq!(format!("{secret_key:?}"), "SecretKey(<redacted>)");
+        assert!(!format!("{secret_key:?}").contains("42"));
+    }
+
+    #[test]
+    fn test_secret_key_eq_and_zeroize() {
+        let scalar = Fr::rand(&mut thread_rng());
+        let mut secret_key = SecretKey::new(scalar);
+
+        assert_eq!(secret_key, SecretKey::from(scalar));
+        assert_ne!(secret_key, SecretKey::from(scalar + Fr::from(1u64)));
+
+        secret_key.zeroize();
+        assert!(secret_key.expose_secret().is_zero());
+    }
+}
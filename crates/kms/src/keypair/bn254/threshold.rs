@@ -0,0 +1,396 @@
+use std::collections::HashSet;
+
+use ark_bn254::{Fr, G1Projective, G2Affine, G2Projective};
+use ark_ec::{AffineRepr, CurveGroup};
+use ark_ff::{Field, One, UniformRand, Zero};
+use rand::thread_rng;
+use signature::{Error as SignatureError, Signer, Verifier};
+use thiserror::Error;
+
+use super::{
+    bls::{hash_to_curve::HashToCurveScheme, keypair_signer::WithHashScheme, signature::Signature},
+    G2Pubkey, Keypair,
+};
+use crate::keypair::traits::Keypair as KeypairTrait;
+
+#[derive(Debug, Error)]
+pub enum ThresholdError {
+    #[error("Invalid threshold {threshold} for {participants} participants")]
+    InvalidThreshold {
+        threshold: usize,
+        participants: usize,
+    },
+    #[error("Not enough shares: {provided} provided, {required} required")]
+    NotEnoughShares { required: usize, provided: usize },
+    #[error("Share index must be non-zero")]
+    ZeroShareIndex,
+    #[error("Duplicate share index {0}")]
+    DuplicateShareIndex(u32),
+    #[error("Share {0} does not match the commitments")]
+    InvalidShare(u32),
+    #[error("Invalid partial signature from share {0}")]
+    InvalidPartialSignature(u32),
+}
+
+/// One participant's share of a Shamir-split secret key, i.e. f(index) for the dealer's
+/// polynomial f with f(0) equal to the original secret key
+#[derive(Clone, Debug)]
+pub struct SecretShare {
+    index: u32,
+    keypair: Keypair,
+}
+
+impl SecretShare {
+    pub fn new(index: u32, keypair: Keypair) -> Result<Self, ThresholdError> {
+        if index == 0 {
+            return Err(ThresholdError::ZeroShareIndex);
+        }
+        Ok(Self { index, keypair })
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    /// The share as a regular keypair, e.g. for storage in a keystore
+    pub fn keypair(&self) -> &Keypair {
+        &self.keypair
+    }
+
+    pub fn public_key(&self) -> G2Pubkey {
+        self.keypair.public_key().g2
+    }
+
+    pub fn sign_with_scheme(
+        &self,
+        message: &[u8],
+        scheme: HashToCurveScheme,
+    ) -> Result<PartialSignature, SignatureError> {
+        Ok(PartialSignature {
+            index: self.index,
+            signature: WithHashScheme::new(&self.keypair, scheme).try_sign(message)?,
+        })
+    }
+}
+
+impl Signer<PartialSignature> for SecretShare {
+    fn try_sign(&self, message: &[u8]) -> Result<PartialSignature, SignatureError> {
+        Ok(PartialSignature {
+            index: self.index,
+            signature: self.keypair.try_sign(message)?,
+        })
+    }
+}
+
+/// A signature produced with a [`SecretShare`], tagged with the share's index
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PartialSignature {
+    pub index: u32,
+    pub signature: Signature,
+}
+
+/// Feldman commitments C_j = a_j * G2 to the coefficients a_j of a sharing polynomial
+///
+/// They are public and let anyone check a share, or a partial signature, against the group key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FeldmanCommitments(Vec<G2Pubkey>);
+
+impl FeldmanCommitments {
+    pub fn new(commitments: Vec<G2Pubkey>) -> Self {
+        Self(commitments)
+    }
+
+    pub fn commitments(&self) -> &[G2Pubkey] {
+        &self.0
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The public key of the shared secret, C_0
+    pub fn group_public_key(&self) -> G2Pubkey {
+        self.0
+            .first()
+            .copied()
+            .unwrap_or_else(|| G2Pubkey::from(G2Affine::zero()))
+    }
+
+    /// Evaluates Σ C_j * index^j, i.e. f(index) * G2
+    pub fn share_public_key(&self, index: u32) -> G2Pubkey {
+        let x = Fr::from(index);
+        // Horner's method, highest degree coefficient first
+        let point = self
+            .0
+            .iter()
+            .rev()
+            .fold(G2Projective::zero(), |acc, commitment| {
+                acc * x + commitment.0
+            });
+        G2Pubkey::from(point.into_affine())
+    }
+
+    pub fn verify_share(&self, share: &SecretShare) -> Result<(), ThresholdError> {
+        if self.share_public_key(share.index) != share.public_key() {
+            return Err(ThresholdError::InvalidShare(share.index));
+        }
+        Ok(())
+    }
+
+    pub fn verify_partial_signature(
+        &self,
+        message: &[u8],
+        partial: &PartialSignature,
+    ) -> Result<(), ThresholdError> {
+        self.verify_partial_signature_with_scheme(
+            message,
+            partial,
+            HashToCurveScheme::TryAndIncrement,
+        )
+    }
+
+    pub fn verify_partial_signature_with_scheme(
+        &self,
+        message: &[u8],
+        partial: &PartialSignature,
+        scheme: HashToCurveScheme,
+    ) -> Result<(), ThresholdError> {
+        let share_public_key = self.share_public_key(partial.index);
+        WithHashScheme::new(&share_public_key, scheme)
+            .verify(message, &partial.signature)
+            .map_err(|_| ThresholdError::InvalidPartialSignature(partial.index))
+    }
+}
+
+/// Splits `keypair`'s secret key into `participants` shares, any `threshold` of which can sign
+/// on behalf of the original key
+pub fn split_keypair(
+    keypair: &Keypair,
+    threshold: usize,
+    participants: usize,
+) -> Result<(Vec<SecretShare>, FeldmanCommitments), ThresholdError> {
+    if threshold == 0 || threshold > participants || participants > u32::MAX as usize {
+        return Err(ThresholdError::InvalidThreshold {
+            threshold,
+            participants,
+        });
+    }
+
+    let mut rng = thread_rng();
+    let coefficients: Vec<Fr> = std::iter::once(*keypair.secret_key().expose_secret())
+        .chain((1..threshold).map(|_| Fr::rand(&mut rng)))
+        .collect();
+
+    Ok(deal(&coefficients, participants))
+}
+
+// Evaluates the polynomial with the given coefficients at 1..=participants
+pub(crate) fn deal(
+    coefficients: &[Fr],
+    participants: usize,
+) -> (Vec<SecretShare>, FeldmanCommitments) {
+    let shares = (1..=participants as u32)
+        .map(|index| SecretShare {
+            index,
+            keypair: Keypair::from_secret(evaluate_polynomial(coefficients, index)),
+        })
+        .collect();
+
+    let commitments = coefficients
+        .iter()
+        .map(|coefficient| G2Pubkey::from((G2Affine::generator() * coefficient).into_affine()))
+        .collect();
+
+    (shares, FeldmanCommitments(commitments))
+}
+
+pub(crate) fn evaluate_polynomial(coefficients: &[Fr], index: u32) -> Fr {
+    let x = Fr::from(index);
+    coefficients
+        .iter()
+        .rev()
+        .fold(Fr::zero(), |acc, coefficient| acc * x + coefficient)
+}
+
+/// Lagrange coefficients at x = 0 for the given distinct, non-zero indices
+pub(crate) fn lagrange_coefficients(indices: &[u32]) -> Result<Vec<Fr>, ThresholdError> {
+    let mut seen = HashSet::with_capacity(indices.len());
+    for &index in indices {
+        if index == 0 {
+            return Err(ThresholdError::ZeroShareIndex);
+        }
+        if !seen.insert(index) {
+            return Err(ThresholdError::DuplicateShareIndex(index));
+        }
+    }
+
+    Ok(indices
+        .iter()
+        .map(|&i| {
+            let x_i = Fr::from(i);
+            let (numerator, denominator) = indices.iter().filter(|&&j| j != i).fold(
+                (Fr::one(), Fr::one()),
+                |(numerator, denominator), &j| {
+                    let x_j = Fr::from(j);
+                    (numerator * x_j, denominator * (x_j - x_i))
+                },
+            );
+            // Indices are distinct, so the denominator is non-zero
+            numerator * denominator.inverse().unwrap()
+        })
+        .collect())
+}
+
+/// Combines at least `threshold` partial signatures into a signature under the group key
+///
+/// Partial signatures are not checked here, invalid ones will yield an invalid signature. Use
+/// [`FeldmanCommitments::verify_partial_signature`] to filter them out first.
+pub fn combine_partial_signatures(
+    partials: &[PartialSignature],
+    threshold: usize,
+) -> Result<Signature, ThresholdError> {
+    if partials.len() < threshold || partials.is_empty() {
+        return Err(ThresholdError::NotEnoughShares {
+            required: threshold.max(1),
+            provided: partials.len(),
+        });
+    }
+
+    // Any `threshold` shares determine the polynomial, so only use that many
+    let partials = &partials[..threshold.max(1)];
+    let indices: Vec<u32> = partials.iter().map(|partial| partial.index).collect();
+    let coefficients = lagrange_coefficients(&indices)?;
+
+    let signature = partials
+        .iter()
+        .zip(coefficients)
+        .fold(G1Projective::zero(), |acc, (partial, coefficient)| {
+            acc + partial.signature.0 * coefficient
+        });
+
+    Ok(Signature::from(signature.into_affine()))
+}
+
+#[cfg(test)]
+mod tests {
+    use signature::Keypair as _;
+
+    use super::*;
+
+    #[test]
+    fn test_threshold_signature() {
+        let keypair = Keypair::generate();
+        let message = [42u8; 32];
+        let (shares, commitments) = split_keypair(&keypair, 3, 5).unwrap();
+
+        assert_eq!(commitments.group_public_key(), keypair.verifying_key());
+        for share in &shares {
+            assert!(commitments.verify_share(share).is_ok());
+        }
+
+        let partials: Vec<PartialSignature> = shares
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .chain(shares.iter().take(1))
+            .map(|share| share.sign(&message))
+            .collect();
+        for partial in &partials {
+            assert!(commitments
+                .verify_partial_signature(&message, partial)
+                .is_ok());
+        }
+
+        let signature = combine_partial_signatures(&partials, 3).unwrap();
+        assert_eq!(signature, keypair.sign(&message));
+        assert!(keypair.verifying_key().verify(&message, &signature).is_ok());
+    }
+
+    #[test]
+    fn test_threshold_signature_with_scheme() {
+        let keypair = Keypair::generate();
+        let message = [42u8; 32];
+        let scheme = HashToCurveScheme::svdw(b"KARAK-DSS-A".to_vec());
+        let (shares, commitments) = split_keypair(&keypair, 2, 3).unwrap();
+
+        let partials: Vec<_> = shares[1..]
+            .iter()
+            .map(|share| share.sign_with_scheme(&message, scheme.clone()).unwrap())
+            .collect();
+        for partial in &partials {
+            assert!(commitments
+                .verify_partial_signature_with_scheme(&message, partial, scheme.clone())
+                .is_ok());
+        }
+
+        let signature = combine_partial_signatures(&partials, 2).unwrap();
+        assert!(WithHashScheme::new(&keypair.verifying_key(), scheme)
+            .verify(&message, &signature)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_not_enough_shares() {
+        let keypair = Keypair::generate();
+        let message = [42u8; 32];
+        let (shares, _) = split_keypair(&keypair, 3, 5).unwrap();
+
+        let partials: Vec<PartialSignature> = shares
+            .iter()
+            .take(2)
+            .map(|share| share.sign(&message))
+            .collect();
+
+        assert!(matches!(
+            combine_partial_signatures(&partials, 3),
+            Err(ThresholdError::NotEnoughShares {
+                required: 3,
+                provided: 2
+            })
+        ));
+
+        // Too few shares interpolate to an unrelated key
+        let signature = combine_partial_signatures(&partials, 2).unwrap();
+        assert!(keypair
+            .verifying_key()
+            .verify(&message, &signature)
+            .is_err());
+    }
+
+    #[test]
+    fn test_duplicate_partial_signatures() {
+        let keypair = Keypair::generate();
+        let message = [42u8; 32];
+        let (shares, _) = split_keypair(&keypair, 2, 3).unwrap();
+
+        let partial = shares[0].sign(&message);
+        assert!(matches!(
+            combine_partial_signatures(&[partial, partial], 2),
+            Err(ThresholdError::DuplicateShareIndex(1))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_share_and_partial_signature() {
+        let keypair = Keypair::generate();
+        let message = [42u8; 32];
+        let (shares, commitments) = split_keypair(&keypair, 2, 3).unwrap();
+
+        let tampered = SecretShare::new(shares[0].index(), Keypair::generate()).unwrap();
+        assert!(matches!(
+            commitments.verify_share(&tampered),
+            Err(ThresholdError::InvalidShare(1))
+        ));
+        assert!(matches!(
+            commitments.verify_partial_signature(&message, &tampered.sign(&message)),
+            Err(ThresholdError::InvalidPartialSignature(1))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_threshold() {
+        let keypair = Keypair::generate();
+        assert!(split_keypair(&keypair, 0, 3).is_err());
+        assert!(split_keypair(&keypair, 4, 3).is_err());
+    }
+}
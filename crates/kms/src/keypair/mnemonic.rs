@@ -0,0 +1,194 @@
+use std::{fmt::Debug, str::FromStr};
+
+use alloy::signers::local::{
+    coins_bip39::{English, Mnemonic as Bip39Mnemonic, MnemonicError as Bip39Error},
+    LocalSignerError, MnemonicBuilder, PrivateKeySigner,
+};
+use rand::thread_rng;
+use thiserror::Error;
+
+use super::bn254::{
+    self,
+    derivation::{DerivationError, DerivationPath},
+};
+
+pub const DEFAULT_WORD_COUNT: usize = 24;
+/// The first account of the standard Ethereum path, as used by most wallets
+pub const DEFAULT_SECP256K1_PATH: &str = "m/44'/60'/0'/0/0";
+
+#[derive(Debug, Error)]
+pub enum MnemonicError {
+    #[error("Invalid mnemonic: {0}")]
+    InvalidMnemonic(#[from] Bip39Error),
+    #[error("Derivation error: {0}")]
+    DerivationError(#[from] DerivationError),
+    #[error("Signer error: {0}")]
+    SignerError(#[from] LocalSignerError),
+}
+
+/// A BIP-39 mnemonic over the English wordlist, from which both BN254 and secp256k1 keys can be
+/// recovered
+///
+/// BN254 keys are derived from the BIP-39 seed along an EIP-2333 path, secp256k1 keys along a
+/// BIP-32 path so that they match the accounts wallets derive from the same phrase.
+#[derive(Clone)]
+pub struct Mnemonic(Bip39Mnemonic<English>);
+
+impl Mnemonic {
+    /// `word_count` must be one of 12, 15, 18, 21 or 24
+    pub fn generate(word_count: usize) -> Result<Self, MnemonicError> {
+        Ok(Self(Bip39Mnemonic::new_with_count(
+            &mut thread_rng(),
+            word_count,
+        )?))
+    }
+
+    /// Parses a phrase, checking every word against the wordlist and the checksum
+    pub fn from_phrase(phrase: &str) -> Result<Self, MnemonicError> {
+        let normalized = phrase
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(Self(Bip39Mnemonic::new_from_phrase(&normalized)?))
+    }
+
+    pub fn phrase(&self) -> String {
+        self.0.to_phrase()
+    }
+
+    /// The 64 byte BIP-39 seed, `passphrase` being the optional extension word
+    pub fn to_seed(&self, passphrase: Option<&str>) -> Result<[u8; 64], MnemonicError> {
+        Ok(self.0.to_seed(passphrase)?)
+    }
+
+    pub fn bn254_keypair(
+        &self,
+        passphrase: Option<&str>,
+        path: &DerivationPath,
+    ) -> Result<bn254::Keypair, MnemonicError> {
+        Ok(bn254::Keypair::from_seed_path(
+            &self.to_seed(passphrase)?,
+            path,
+        )?)
+    }
+
+    pub fn secp256k1_signer(
+        &self,
+        passphrase: Option<&str>,
+        path: &str,
+    ) -> Result<PrivateKeySigner, MnemonicError> {
+        let builder = MnemonicBuilder::<English>::default()
+            .phrase(self.phrase())
+            .derivation_path(path)?;
+
+        Ok(match passphrase {
+            Some(passphrase) => builder.password(passphrase).build()?,
+            None => builder.build()?,
+        })
+    }
+}
+
+impl FromStr for Mnemonic {
+    type Err = MnemonicError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_phrase(s)
+    }
+}
+
+// Never print the phrase by accident
+impl Debug for Mnemonic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Mnemonic(..)")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_bn254::Fr;
+
+    use crate::keypair::traits::Keypair;
+
+    use super::*;
+
+    const ABANDON: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_generate_and_recover() {
+        let mnemonic = Mnemonic::generate(DEFAULT_WORD_COUNT).unwrap();
+        assert_eq!(mnemonic.phrase().split(' ').count(), 24);
+
+        let recovered = Mnemonic::from_phrase(&mnemonic.phrase()).unwrap();
+        let path = DerivationPath::dss(1, 0);
+        assert_eq!(
+            mnemonic.bn254_keypair(None, &path).unwrap().secret_key(),
+            recovered.bn254_keypair(None, &path).unwrap().secret_key()
+        );
+        assert_eq!(
+            mnemonic
+                .secp256k1_signer(None, DEFAULT_SECP256K1_PATH)
+                .unwrap()
+                .address(),
+            recovered
+                .secp256k1_signer(None, DEFAULT_SECP256K1_PATH)
+                .unwrap()
+                .address()
+        );
+
+        assert!(matches!(
+            Mnemonic::generate(13),
+            Err(MnemonicError::InvalidMnemonic(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_phrase() {
+        // Valid words, wrong checksum
+        assert!(Mnemonic::from_phrase(&ABANDON.replace("about", "abandon")).is_err());
+        assert!(Mnemonic::from_phrase(&ABANDON.replace("about", "karak")).is_err());
+        assert!(Mnemonic::from_phrase(&format!("  {}\n", ABANDON.to_uppercase())).is_ok());
+    }
+
+    // https://github.com/trezor/python-mnemonic/blob/master/vectors.json
+    #[test]
+    fn test_bn254_from_mnemonic() {
+        let mnemonic = Mnemonic::from_phrase(ABANDON).unwrap();
+        let seed = mnemonic.to_seed(Some("TREZOR")).unwrap();
+        assert_eq!(
+            hex::encode(seed),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+
+        let keypair = mnemonic
+            .bn254_keypair(Some("TREZOR"), &DerivationPath::dss(1, 0))
+            .unwrap();
+        assert_eq!(
+            keypair.secret_key().expose_secret(),
+            &Fr::from_str(
+                "19133303594933047342422024083986642611206038621273188655870164481470118522964"
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_secp256k1_from_mnemonic() {
+        let mnemonic =
+            Mnemonic::from_phrase("test test test test test test test test test test test junk")
+                .unwrap();
+
+        let signer = mnemonic
+            .secp256k1_signer(None, DEFAULT_SECP256K1_PATH)
+            .unwrap();
+        assert_eq!(
+            signer.address().to_string(),
+            "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+        );
+        let signer = mnemonic.secp256k1_signer(None, "m/44'/60'/0'/0/1").unwrap();
+        assert_eq!(
+            signer.address().to_string(),
+            "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+        );
+    }
+}
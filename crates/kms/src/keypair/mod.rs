@@ -1,4 +1,6 @@
 pub mod bn254;
+pub mod mnemonic;
+pub mod secp256k1;
 pub mod traits;
 
 pub use signature::{Signer, Verifier};
@@ -0,0 +1,66 @@
+use super::{Keypair, Secp256k1Error};
+use crate::{
+    encryption::{EncryptedPayload, EncryptionError, EncryptionParams},
+    keypair::traits::{Encryptable, Keypair as KeypairTrait},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum KeypairEncryptionError {
+    #[error("Keypair error: {0}")]
+    KeypairError(#[from] Secp256k1Error),
+
+    #[error("Encryption error: {0}")]
+    EncryptionError(#[from] EncryptionError),
+}
+
+// Same payload as BN254 keypairs, so either can sit in the same keystores
+impl Encryptable for Keypair {
+    type EncryptionError = KeypairEncryptionError;
+
+    fn encrypt_with_params(
+        &self,
+        passphrase: &str,
+        params: &EncryptionParams,
+    ) -> Result<Vec<u8>, KeypairEncryptionError> {
+        let secret_bytes = self.to_secret_bytes();
+        let encrypted_keypair =
+            EncryptedPayload::encrypt(&secret_bytes, passphrase.as_bytes(), params)?;
+
+        Ok(encrypted_keypair.to_bytes()?)
+    }
+
+    fn decrypt(encrypted_keypair: &[u8], passphrase: &str) -> Result<Self, KeypairEncryptionError> {
+        let secret_bytes =
+            EncryptedPayload::from_bytes(encrypted_keypair)?.decrypt(passphrase.as_bytes())?;
+
+        Ok(Keypair::from_secret_bytes(&secret_bytes)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::encryption::KdfParams;
+
+    use super::*;
+
+    #[test]
+    fn test_encryption_roundtrip() {
+        let keypair = Keypair::generate();
+        let encrypted = keypair
+            .encrypt_with_params(
+                "passphrase",
+                &KdfParams::Pbkdf2 { iterations: 1_000 }.into(),
+            )
+            .unwrap();
+
+        let decrypted = Keypair::decrypt(&encrypted, "passphrase").unwrap();
+        assert_eq!(decrypted.address(), keypair.address());
+        assert!(matches!(
+            Keypair::decrypt(&encrypted, "wrong passphrase"),
+            Err(KeypairEncryptionError::EncryptionError(
+                EncryptionError::MacVerificationFailed
+            ))
+        ));
+    }
+}
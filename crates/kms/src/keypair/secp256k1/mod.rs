@@ -0,0 +1,202 @@
+use std::{fmt::Display, str::FromStr};
+
+use alloy::{
+    primitives::{keccak256, Address, B256},
+    signers::{
+        k256::ecdsa::{SigningKey, VerifyingKey},
+        local::PrivateKeySigner,
+        SignerSync,
+    },
+};
+use signature::{Error as SignatureError, Signer, Verifier};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+use super::traits::Keypair as KeypairTrait;
+
+mod encryption;
+pub use encryption::*;
+
+pub type Signature = alloy::primitives::Signature;
+
+/// Length of the big-endian secret key encoding used by [`KeypairTrait::to_secret_bytes`]
+pub const SECRET_KEY_LENGTH: usize = 32;
+
+/// An ECDSA keypair on secp256k1, e.g. an operator's Ethereum key
+///
+/// Converts to and from alloy's [`PrivateKeySigner`] to sign transactions.
+#[derive(Clone, Debug)]
+pub struct Keypair {
+    signer: PrivateKeySigner,
+}
+
+#[derive(Debug, Error)]
+pub enum Secp256k1Error {
+    #[error("Secret key must be {SECRET_KEY_LENGTH} bytes, got {0}")]
+    InvalidSecretKeyLength(usize),
+    #[error("Secret key is zero or not below the group order")]
+    InvalidSecretKey,
+    #[error("Hex decoding error: {0}")]
+    HexDecodingError(#[from] hex::FromHexError),
+}
+
+impl Keypair {
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    pub fn signer(&self) -> &PrivateKeySigner {
+        &self.signer
+    }
+}
+
+impl KeypairTrait for Keypair {
+    type SecretKey = SigningKey;
+    type PublicKey = VerifyingKey;
+    type KeypairError = Secp256k1Error;
+
+    fn generate() -> Self {
+        Self {
+            signer: PrivateKeySigner::random(),
+        }
+    }
+
+    // A SigningKey is always a valid non-zero scalar
+    fn from_secret_key(secret_key: SigningKey) -> Result<Self, Secp256k1Error> {
+        Ok(Self {
+            signer: PrivateKeySigner::from_signing_key(secret_key),
+        })
+    }
+
+    /// Expects the 32-byte big-endian encoding of a non-zero scalar below the group order
+    fn from_secret_bytes(bytes: &[u8]) -> Result<Self, Secp256k1Error> {
+        // SigningKey::from_slice would also accept and left-pad shorter slices
+        if bytes.len() != SECRET_KEY_LENGTH {
+            return Err(Secp256k1Error::InvalidSecretKeyLength(bytes.len()));
+        }
+        let secret_key =
+            SigningKey::from_slice(bytes).map_err(|_| Secp256k1Error::InvalidSecretKey)?;
+        Self::from_secret_key(secret_key)
+    }
+
+    fn to_secret_bytes(&self) -> Zeroizing<Vec<u8>> {
+        Zeroizing::new(self.signer.credential().to_bytes().to_vec())
+    }
+
+    fn secret_key(&self) -> &Self::SecretKey {
+        self.signer.credential()
+    }
+
+    fn public_key(&self) -> &Self::PublicKey {
+        self.signer.credential().verifying_key()
+    }
+}
+
+impl From<PrivateKeySigner> for Keypair {
+    fn from(signer: PrivateKeySigner) -> Self {
+        Self { signer }
+    }
+}
+
+impl From<Keypair> for PrivateKeySigner {
+    fn from(keypair: Keypair) -> Self {
+        keypair.signer
+    }
+}
+
+/// Signs the Keccak-256 hash of the message, i.e. what `ecrecover` checks against
+impl Signer<Signature> for Keypair {
+    fn try_sign(&self, message: &[u8]) -> Result<Signature, SignatureError> {
+        self.signer
+            .sign_hash_sync(&keccak256(message))
+            .map_err(SignatureError::from_source)
+    }
+}
+
+impl Verifier<Signature> for Keypair {
+    fn verify(&self, message: &[u8], signature: &Signature) -> Result<(), SignatureError> {
+        let hash: B256 = keccak256(message);
+        let recovered = signature
+            .recover_address_from_prehash(&hash)
+            .map_err(SignatureError::from_source)?;
+
+        if recovered != self.address() {
+            return Err(SignatureError::new());
+        }
+        Ok(())
+    }
+}
+
+/// Displays the checksummed address
+impl Display for Keypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.address().fmt(f)
+    }
+}
+
+impl FromStr for Keypair {
+    type Err = Secp256k1Error;
+
+    /// Parses a hex encoded secret key
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Keypair::from_hex(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The first anvil account
+    const SECRET_KEY: &str = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+    const ADDRESS: &str = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266";
+
+    #[test]
+    fn test_from_hex() {
+        let keypair = Keypair::from_hex(SECRET_KEY).unwrap();
+        assert_eq!(keypair.to_string(), ADDRESS);
+        assert_eq!(
+            format!("0x{}", hex::encode(keypair.to_secret_bytes().as_slice())),
+            SECRET_KEY
+        );
+        assert!(!format!("{keypair:?}").contains(&SECRET_KEY[2..]));
+    }
+
+    #[test]
+    fn test_from_secret_bytes_rejects_invalid_scalars() {
+        let order = "fffffffffffffffffffffffffffffffebaaedce6af48a03bbfd25e8cd0364141";
+
+        assert!(matches!(
+            Keypair::from_hex(order),
+            Err(Secp256k1Error::InvalidSecretKey)
+        ));
+        assert!(matches!(
+            Keypair::from_secret_bytes(&[0u8; 32]),
+            Err(Secp256k1Error::InvalidSecretKey)
+        ));
+        assert!(matches!(
+            Keypair::from_secret_bytes(&[1u8; 31]),
+            Err(Secp256k1Error::InvalidSecretKeyLength(31))
+        ));
+    }
+
+    #[test]
+    fn test_private_key_signer_conversion() {
+        let signer = PrivateKeySigner::random();
+        let keypair = Keypair::from(signer.clone());
+        assert_eq!(keypair.address(), signer.address());
+        assert_eq!(PrivateKeySigner::from(keypair).address(), signer.address());
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let keypair = Keypair::generate();
+        let other_keypair = Keypair::generate();
+        let message = b"karak";
+
+        let signature = keypair.sign(message);
+        assert!(keypair.verify(message, &signature).is_ok());
+        assert!(keypair.verify(b"other message", &signature).is_err());
+        assert!(other_keypair.verify(message, &signature).is_err());
+    }
+}
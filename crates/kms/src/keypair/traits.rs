@@ -1,18 +1,45 @@
 use std::{error::Error, fmt::Display};
 
-pub trait Keypair: Display {
+use zeroize::Zeroizing;
+
+use crate::encryption::EncryptionParams;
+
+pub trait Keypair: Display + Sized {
     type SecretKey;
     type PublicKey;
+    type KeypairError: Error + Send + Sync + From<hex::FromHexError>;
 
     fn generate() -> Self;
-    // TODO: Add from methods to "load" keys
+    /// Fails if the secret key is not a valid key for the curve, e.g. zero
+    fn from_secret_key(secret_key: Self::SecretKey) -> Result<Self, Self::KeypairError>;
+    /// Loads a keypair from the curve's canonical secret key encoding, see [`Self::to_secret_bytes`]
+    fn from_secret_bytes(bytes: &[u8]) -> Result<Self, Self::KeypairError>;
+    /// Encodes the secret key canonically, the result is wiped when dropped
+    fn to_secret_bytes(&self) -> Zeroizing<Vec<u8>>;
     fn secret_key(&self) -> &Self::SecretKey;
     fn public_key(&self) -> &Self::PublicKey;
+
+    /// Loads a keypair from the hex encoded secret key, with or without a `0x` prefix
+    fn from_hex(hex: &str) -> Result<Self, Self::KeypairError> {
+        let hex = hex.trim();
+        let hex = hex.strip_prefix("0x").unwrap_or(hex);
+        let bytes = Zeroizing::new(hex::decode(hex)?);
+        Self::from_secret_bytes(&bytes)
+    }
 }
 
 pub trait Encryptable: Sized {
     type EncryptionError: Error + Send + Sync;
 
-    fn encrypt(&self, passphrase: &str) -> Result<Vec<u8>, Self::EncryptionError>;
+    /// The parameters are recorded in the payload, so `decrypt` does not need to be told about them
+    fn encrypt_with_params(
+        &self,
+        passphrase: &str,
+        params: &EncryptionParams,
+    ) -> Result<Vec<u8>, Self::EncryptionError>;
     fn decrypt(encrypted_keypair: &[u8], passphrase: &str) -> Result<Self, Self::EncryptionError>;
+
+    fn encrypt(&self, passphrase: &str) -> Result<Vec<u8>, Self::EncryptionError> {
+        self.encrypt_with_params(passphrase, &EncryptionParams::default())
+    }
 }
@@ -1,60 +1,214 @@
-use aws_sdk_secretsmanager::primitives::Blob;
+use aws_sdk_secretsmanager::{
+    error::{DisplayErrorContext, ProvideErrorMetadata, SdkError},
+    primitives::Blob,
+    types::Tag,
+};
 use thiserror::Error;
 
-use crate::keypair::traits::Encryptable;
+use crate::{encryption::EncryptionParams, keypair::traits::Encryptable};
 
 use super::traits::AsyncEncryptedKeystore;
 
 pub use aws_config::SdkConfig as AwsConfig;
 
+#[derive(Debug, Error)]
+pub enum AwsSecretsManagerError {
+    #[error("AWS secret {0} not found")]
+    SecretNotFound(String),
+    #[error("Access to AWS secret {secret} denied: {message}")]
+    AccessDenied { secret: String, message: String },
+    /// The KMS key of the secret could not decrypt it, or the caller cannot use that key
+    #[error("AWS could not decrypt secret {secret}: {message}")]
+    DecryptionFailure { secret: String, message: String },
+    #[error("AWS secret {0} has no binary value")]
+    SecretBinaryEmpty(String),
+    #[error("AWS Secrets Manager error: {message}")]
+    ServiceError {
+        code: Option<String>,
+        message: String,
+    },
+}
+
+impl AwsSecretsManagerError {
+    fn from_sdk<E, R>(err: SdkError<E, R>, secret: &str) -> Self
+    where
+        E: ProvideErrorMetadata + std::error::Error + 'static,
+        R: std::fmt::Debug,
+    {
+        let message = err.message().unwrap_or_default().to_string();
+        match err.code() {
+            Some("ResourceNotFoundException") => Self::SecretNotFound(secret.to_string()),
+            Some("AccessDeniedException") => Self::AccessDenied {
+                secret: secret.to_string(),
+                message,
+            },
+            Some("DecryptionFailure") => Self::DecryptionFailure {
+                secret: secret.to_string(),
+                message,
+            },
+            code => Self::ServiceError {
+                code: code.map(String::from),
+                message: DisplayErrorContext(&err).to_string(),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum AwsKeystoreError<E: std::error::Error + Send + Sync> {
     #[error("Encryption error: {0}")]
     EncryptionError(E),
-    #[error("AWS Secrets Manager Put error: {0}")]
-    AwsSecretsManagerPutError(
-        #[from]
-        aws_sdk_secretsmanager::error::SdkError<
-            aws_sdk_secretsmanager::operation::put_secret_value::PutSecretValueError,
-        >,
-    ),
-    #[error("AWS Secrets Manager Create error: {0}")]
-    AwsSecretsManagerCreateError(
-        #[from]
-        aws_sdk_secretsmanager::error::SdkError<
-            aws_sdk_secretsmanager::operation::create_secret::CreateSecretError,
-        >,
-    ),
-    #[error("AWS Secrets Manager Get error: {0}")]
-    AwsSecretsManagerGetError(
-        #[from]
-        aws_sdk_secretsmanager::error::SdkError<
-            aws_sdk_secretsmanager::operation::get_secret_value::GetSecretValueError,
-        >,
-    ),
-    #[error("AWS Secrets Manager Blob empty")]
-    AwsSecretBlobEmpty,
+    #[error(transparent)]
+    SecretsManagerError(#[from] AwsSecretsManagerError),
+    #[error("Cannot rekey pinned version {0}, only AWSCURRENT can be rekeyed")]
+    PinnedVersion(String),
 }
 
 pub struct AwsEncryptedKeystore {
     client: aws_sdk_secretsmanager::Client,
+    kms_key_id: Option<String>,
+    tags: Vec<(String, String)>,
+    encryption_params: EncryptionParams,
 }
 
 impl AwsEncryptedKeystore {
     pub fn new(config: &AwsConfig) -> Self {
+        Self::from_client(aws_sdk_secretsmanager::Client::new(config))
+    }
+
+    pub fn from_client(client: aws_sdk_secretsmanager::Client) -> Self {
         Self {
-            client: aws_sdk_secretsmanager::Client::new(config),
+            client,
+            kms_key_id: None,
+            tags: vec![],
+            encryption_params: EncryptionParams::default(),
         }
     }
+
+    /// Sets the KMS key secrets are created with, instead of the account's `aws/secretsmanager` key
+    pub fn with_kms_key_id(mut self, kms_key_id: impl Into<String>) -> Self {
+        self.kms_key_id = Some(kms_key_id.into());
+        self
+    }
+
+    /// Adds a tag to secrets when they are created, existing secrets keep their tags
+    pub fn with_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn with_encryption_params(mut self, encryption_params: EncryptionParams) -> Self {
+        self.encryption_params = encryption_params;
+        self
+    }
 }
 
 pub struct AwsKeystoreParams {
     pub secret_name: String,
+    /// The version to retrieve, takes precedence over `version_stage`
+    pub version_id: Option<String>,
+    /// The staging label of the version to retrieve, `None` for `AWSCURRENT`
+    pub version_stage: Option<String>,
+}
+
+impl AwsKeystoreParams {
+    pub fn new(secret_name: impl Into<String>) -> Self {
+        Self {
+            secret_name: secret_name.into(),
+            version_id: None,
+            version_stage: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AwsSecretVersion {
+    pub version_id: String,
+    /// Staging labels such as `AWSCURRENT` and `AWSPREVIOUS`
+    pub version_stages: Vec<String>,
+}
+
+impl AwsEncryptedKeystore {
+    /// Lists the versions of a secret that still have a staging label
+    pub async fn list_versions(
+        &self,
+        secret_name: &str,
+    ) -> Result<Vec<AwsSecretVersion>, AwsSecretsManagerError> {
+        let mut versions = vec![];
+        let mut next_token = None;
+        loop {
+            let resp = self
+                .client
+                .list_secret_version_ids()
+                .secret_id(secret_name)
+                .set_next_token(next_token)
+                .send()
+                .await
+                .map_err(|err| AwsSecretsManagerError::from_sdk(err, secret_name))?;
+
+            versions.extend(resp.versions().iter().map(|version| AwsSecretVersion {
+                version_id: version.version_id().unwrap_or_default().to_string(),
+                version_stages: version.version_stages().to_vec(),
+            }));
+
+            next_token = resp.next_token().map(String::from);
+            if next_token.is_none() {
+                return Ok(versions);
+            }
+        }
+    }
+
+    /// Puts a new version of the secret, creating it if it does not exist
+    async fn upsert(
+        &self,
+        secret_name: &str,
+        encrypted_keypair: Vec<u8>,
+    ) -> Result<(), AwsSecretsManagerError> {
+        let put = || {
+            self.client
+                .put_secret_value()
+                .secret_id(secret_name)
+                .secret_binary(Blob::new(encrypted_keypair.clone()))
+                .send()
+        };
+
+        match put().await {
+            Ok(_) => return Ok(()),
+            Err(err) => match AwsSecretsManagerError::from_sdk(err, secret_name) {
+                AwsSecretsManagerError::SecretNotFound(_) => {}
+                err => return Err(err),
+            },
+        }
+
+        let tags = self
+            .tags
+            .iter()
+            .map(|(key, value)| Tag::builder().key(key).value(value).build())
+            .collect();
+        let created = self
+            .client
+            .create_secret()
+            .name(secret_name)
+            .secret_binary(Blob::new(encrypted_keypair.clone()))
+            .set_kms_key_id(self.kms_key_id.clone())
+            .set_tags(Some(tags))
+            .send()
+            .await;
+
+        match created {
+            Ok(_) => Ok(()),
+            // Created by someone else in the meantime
+            Err(err) if err.code() == Some("ResourceExistsException") => put()
+                .await
+                .map(|_| ())
+                .map_err(|err| AwsSecretsManagerError::from_sdk(err, secret_name)),
+            Err(err) => Err(AwsSecretsManagerError::from_sdk(err, secret_name)),
+        }
+    }
 }
 
-impl<Keypair: Encryptable + Send + Sync> AsyncEncryptedKeystore<Keypair, AwsKeystoreParams>
-    for AwsEncryptedKeystore
-{
+impl<Keypair: Encryptable + Send + Sync> AsyncEncryptedKeystore<Keypair> for AwsEncryptedKeystore {
+    type Params = AwsKeystoreParams;
     type StorageError = AwsKeystoreError<Keypair::EncryptionError>;
 
     async fn store(
@@ -64,29 +218,10 @@ impl<Keypair: Encryptable + Send + Sync> AsyncEncryptedKeystore<Keypair, AwsKeys
         params: &AwsKeystoreParams,
     ) -> Result<(), Self::StorageError> {
         let encrypted_keypair = keypair
-            .encrypt(passphrase)
+            .encrypt_with_params(passphrase, &self.encryption_params)
             .map_err(AwsKeystoreError::EncryptionError)?;
 
-        // TODO: Maybe handle some of the possible error scenarios here?
-        // TODO: Also make sure this is idempotent and can work even if the secret does not exist yet
-        let resp = self
-            .client
-            .put_secret_value()
-            .secret_id(&params.secret_name)
-            .set_secret_binary(Some(Blob::new(encrypted_keypair.clone())))
-            .send()
-            .await;
-
-        if resp.is_err() {
-            self.client
-                .create_secret()
-                .name(&params.secret_name)
-                .set_secret_binary(Some(Blob::new(encrypted_keypair)))
-                .send()
-                .await?;
-        }
-
-        Ok(())
+        Ok(self.upsert(&params.secret_name, encrypted_keypair).await?)
     }
 
     async fn retrieve(
@@ -98,14 +233,392 @@ impl<Keypair: Encryptable + Send + Sync> AsyncEncryptedKeystore<Keypair, AwsKeys
             .client
             .get_secret_value()
             .secret_id(&params.secret_name)
+            .set_version_id(params.version_id.clone())
+            .set_version_stage(params.version_stage.clone())
             .send()
-            .await?;
+            .await
+            .map_err(|err| AwsSecretsManagerError::from_sdk(err, &params.secret_name))?;
 
         let encrypted_keypair = match resp.secret_binary() {
             Some(blob) => blob.as_ref(),
-            None => return Err(AwsKeystoreError::AwsSecretBlobEmpty),
+            None => {
+                return Err(AwsSecretsManagerError::SecretBinaryEmpty(
+                    params.secret_name.clone(),
+                ))?
+            }
         };
 
         Keypair::decrypt(encrypted_keypair, passphrase).map_err(AwsKeystoreError::EncryptionError)
     }
+
+    // Always rekeys AWSCURRENT, putting a version rekeyed from an older one would roll the key
+    // back. Puts a new version of the secret, so the previous one stays retrievable as AWSPREVIOUS
+    // under the old passphrase. Secrets Manager cannot destroy a single version, the secret has
+    // to be recreated for the old passphrase to stop mattering
+    async fn rekey(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+        params: &AwsKeystoreParams,
+    ) -> Result<(), Self::StorageError> {
+        if let Some(version) = params.version_id.as_ref().or(params.version_stage.as_ref()) {
+            if version != "AWSCURRENT" {
+                return Err(AwsKeystoreError::PinnedVersion(version.clone()));
+            }
+        }
+        let keypair: Keypair = self.retrieve(old_passphrase, params).await?;
+        let encrypted_keypair = keypair
+            .encrypt_with_params(new_passphrase, new_params)
+            .map_err(AwsKeystoreError::EncryptionError)?;
+
+        self.client
+            .put_secret_value()
+            .secret_id(&params.secret_name)
+            .secret_binary(Blob::new(encrypted_keypair))
+            .send()
+            .await
+            .map_err(|err| AwsSecretsManagerError::from_sdk(err, &params.secret_name))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use aws_sdk_secretsmanager::config::{BehaviorVersion, Credentials, Region};
+    use axum::{
+        body::Bytes,
+        extract::State,
+        http::{header, HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        routing::post,
+        Router,
+    };
+    use serde_json::{json, Value};
+
+    use crate::{
+        encryption::KdfParams,
+        keypair::{bn254, traits::Keypair as _},
+        test_utils::{spawn_mock, LIGHT_KDF},
+    };
+
+    use super::*;
+
+    struct Version {
+        id: String,
+        secret_binary: String,
+        stages: Vec<String>,
+    }
+
+    #[derive(Default)]
+    struct Secret {
+        kms_key_id: Option<String>,
+        tags: Vec<(String, String)>,
+        versions: Vec<Version>,
+    }
+
+    #[derive(Clone, Default)]
+    struct SecretsManager {
+        secrets: Arc<Mutex<HashMap<String, Secret>>>,
+    }
+
+    fn respond(status: StatusCode, body: Value) -> Response {
+        (
+            status,
+            [(header::CONTENT_TYPE, "application/x-amz-json-1.1")],
+            body.to_string(),
+        )
+            .into_response()
+    }
+
+    fn error(code: &str) -> Response {
+        respond(
+            StatusCode::BAD_REQUEST,
+            json!({ "__type": code, "message": format!("{code} from the mock") }),
+        )
+    }
+
+    fn add_version(secret: &mut Secret, secret_binary: &Value) -> String {
+        for version in secret.versions.iter_mut() {
+            version.stages.retain(|stage| stage != "AWSPREVIOUS");
+            for stage in version
+                .stages
+                .iter_mut()
+                .filter(|stage| *stage == "AWSCURRENT")
+            {
+                *stage = "AWSPREVIOUS".to_string();
+            }
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        secret.versions.push(Version {
+            id: id.clone(),
+            secret_binary: secret_binary.as_str().unwrap().to_string(),
+            stages: vec!["AWSCURRENT".to_string()],
+        });
+        id
+    }
+
+    // Speaks the awsJson1.1 protocol of the operations the keystore uses
+    async fn handle(
+        State(secrets_manager): State<SecretsManager>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Response {
+        let target = headers["x-amz-target"].to_str().unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let mut secrets = secrets_manager.secrets.lock().unwrap();
+
+        if target == "secretsmanager.CreateSecret" {
+            let name = body["Name"].as_str().unwrap();
+            if secrets.contains_key(name) {
+                return error("ResourceExistsException");
+            }
+            let mut secret = Secret {
+                kms_key_id: body["KmsKeyId"].as_str().map(String::from),
+                tags: body["Tags"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|tag| {
+                        let field = |key: &str| tag[key].as_str().unwrap().to_string();
+                        (field("Key"), field("Value"))
+                    })
+                    .collect(),
+                ..Default::default()
+            };
+            let id = add_version(&mut secret, &body["SecretBinary"]);
+            secrets.insert(name.to_string(), secret);
+            return respond(StatusCode::OK, json!({ "Name": name, "VersionId": id }));
+        }
+
+        let name = body["SecretId"].as_str().unwrap();
+        if name == "denied" {
+            return error("AccessDeniedException");
+        }
+        let Some(secret) = secrets.get_mut(name) else {
+            return error("ResourceNotFoundException");
+        };
+        match target {
+            "secretsmanager.PutSecretValue" => {
+                let id = add_version(secret, &body["SecretBinary"]);
+                respond(StatusCode::OK, json!({ "Name": name, "VersionId": id }))
+            }
+            "secretsmanager.GetSecretValue" if name == "undecryptable" => {
+                error("DecryptionFailure")
+            }
+            "secretsmanager.GetSecretValue" => {
+                let stage = body["VersionStage"].as_str().unwrap_or("AWSCURRENT");
+                let version = secret
+                    .versions
+                    .iter()
+                    .find(|version| match &body["VersionId"] {
+                        Value::String(id) => version.id == *id,
+                        _ => version.stages.iter().any(|s| s == stage),
+                    });
+                match version {
+                    Some(version) => respond(
+                        StatusCode::OK,
+                        json!({
+                            "Name": name,
+                            "VersionId": version.id,
+                            "SecretBinary": version.secret_binary,
+                            "VersionStages": version.stages,
+                        }),
+                    ),
+                    None => error("ResourceNotFoundException"),
+                }
+            }
+            "secretsmanager.ListSecretVersionIds" => {
+                let versions: Vec<Value> = secret
+                    .versions
+                    .iter()
+                    .filter(|version| !version.stages.is_empty())
+                    .map(|version| json!({ "VersionId": version.id, "VersionStages": version.stages }))
+                    .collect();
+                respond(
+                    StatusCode::OK,
+                    json!({ "Name": name, "Versions": versions }),
+                )
+            }
+            _ => error("InvalidRequestException"),
+        }
+    }
+
+    async fn secrets_manager() -> (aws_sdk_secretsmanager::Client, SecretsManager) {
+        let secrets_manager = SecretsManager::default();
+        let app = Router::new()
+            .route("/", post(handle))
+            .with_state(secrets_manager.clone());
+
+        let url = spawn_mock(app).await;
+
+        let config = aws_sdk_secretsmanager::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+            .endpoint_url(url.as_str().trim_end_matches('/'))
+            .build();
+        (
+            aws_sdk_secretsmanager::Client::from_conf(config),
+            secrets_manager,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_store_versions_and_rekey() {
+        let (client, secrets_manager) = secrets_manager().await;
+        let keystore = AwsEncryptedKeystore::from_client(client)
+            .with_kms_key_id("alias/karak")
+            .with_tag("team", "operators")
+            .with_encryption_params(LIGHT_KDF.into());
+        let params = AwsKeystoreParams::new("operator.bls");
+        let first = bn254::Keypair::generate();
+        let second = bn254::Keypair::generate();
+
+        keystore.store(&first, "passphrase", &params).await.unwrap();
+        keystore
+            .store(&second, "passphrase", &params)
+            .await
+            .unwrap();
+        {
+            let secrets = secrets_manager.secrets.lock().unwrap();
+            let secret = &secrets["operator.bls"];
+            assert_eq!(secret.kms_key_id.as_deref(), Some("alias/karak"));
+            assert_eq!(
+                secret.tags,
+                vec![("team".to_string(), "operators".to_string())]
+            );
+        }
+
+        let versions = keystore.list_versions("operator.bls").await.unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].version_stages, vec!["AWSPREVIOUS"]);
+        assert_eq!(versions[1].version_stages, vec!["AWSCURRENT"]);
+
+        let current: bn254::Keypair = keystore.retrieve("passphrase", &params).await.unwrap();
+        assert_eq!(current.public_key(), second.public_key());
+        let previous: bn254::Keypair = keystore
+            .retrieve(
+                "passphrase",
+                &AwsKeystoreParams {
+                    version_stage: Some("AWSPREVIOUS".to_string()),
+                    ..AwsKeystoreParams::new("operator.bls")
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(previous.public_key(), first.public_key());
+        let by_id: bn254::Keypair = keystore
+            .retrieve(
+                "passphrase",
+                &AwsKeystoreParams {
+                    version_id: Some(versions[0].version_id.clone()),
+                    ..AwsKeystoreParams::new("operator.bls")
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(by_id.public_key(), first.public_key());
+
+        AsyncEncryptedKeystore::<bn254::Keypair>::rekey(
+            &keystore,
+            "passphrase",
+            "new passphrase",
+            &KdfParams::Pbkdf2 { iterations: 2_000 }.into(),
+            &params,
+        )
+        .await
+        .unwrap();
+        let rekeyed: bn254::Keypair = keystore.retrieve("new passphrase", &params).await.unwrap();
+        assert_eq!(rekeyed.public_key(), second.public_key());
+        assert_eq!(
+            keystore.list_versions("operator.bls").await.unwrap().len(),
+            2
+        );
+
+        // Rekeying an older version would roll the key back
+        for pinned in [
+            AwsKeystoreParams {
+                version_id: Some(versions[0].version_id.clone()),
+                ..AwsKeystoreParams::new("operator.bls")
+            },
+            AwsKeystoreParams {
+                version_stage: Some("AWSPREVIOUS".to_string()),
+                ..AwsKeystoreParams::new("operator.bls")
+            },
+        ] {
+            let result = AsyncEncryptedKeystore::<bn254::Keypair>::rekey(
+                &keystore,
+                "passphrase",
+                "new passphrase",
+                &LIGHT_KDF.into(),
+                &pinned,
+            )
+            .await;
+            assert!(matches!(result, Err(AwsKeystoreError::PinnedVersion(_))));
+        }
+        let rekeyed: bn254::Keypair = keystore.retrieve("new passphrase", &params).await.unwrap();
+        assert_eq!(rekeyed.public_key(), second.public_key());
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let (client, secrets_manager) = secrets_manager().await;
+        let keystore =
+            AwsEncryptedKeystore::from_client(client).with_encryption_params(LIGHT_KDF.into());
+        let keypair = bn254::Keypair::generate();
+
+        let result: Result<bn254::Keypair, _> = keystore
+            .retrieve("passphrase", &AwsKeystoreParams::new("missing"))
+            .await;
+        assert!(matches!(
+            result,
+            Err(AwsKeystoreError::SecretsManagerError(
+                AwsSecretsManagerError::SecretNotFound(name)
+            )) if name == "missing"
+        ));
+
+        // Only a missing secret is created, other failures are returned as is
+        let result = keystore
+            .store(&keypair, "passphrase", &AwsKeystoreParams::new("denied"))
+            .await;
+        assert!(matches!(
+            result,
+            Err(AwsKeystoreError::SecretsManagerError(
+                AwsSecretsManagerError::AccessDenied { .. }
+            ))
+        ));
+        assert!(!secrets_manager
+            .secrets
+            .lock()
+            .unwrap()
+            .contains_key("denied"));
+
+        let params = AwsKeystoreParams::new("undecryptable");
+        keystore
+            .store(&keypair, "passphrase", &params)
+            .await
+            .unwrap();
+        let result: Result<bn254::Keypair, _> = keystore.retrieve("passphrase", &params).await;
+        assert!(matches!(
+            result,
+            Err(AwsKeystoreError::SecretsManagerError(
+                AwsSecretsManagerError::DecryptionFailure { .. }
+            ))
+        ));
+
+        let params = AwsKeystoreParams::new("operator.bls");
+        keystore
+            .store(&keypair, "passphrase", &params)
+            .await
+            .unwrap();
+        let result: Result<bn254::Keypair, _> = keystore.retrieve("wrong", &params).await;
+        assert!(matches!(result, Err(AwsKeystoreError::EncryptionError(_))));
+    }
 }
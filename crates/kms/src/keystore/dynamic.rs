@@ -0,0 +1,143 @@
+use std::error::Error;
+
+use async_trait::async_trait;
+use thiserror::Error;
+
+use crate::{encryption::EncryptionParams, keypair::traits::Encryptable};
+
+use super::traits::AsyncEncryptedKeystore;
+
+/// The error of whichever backend a [`DynKeystore`] wraps
+#[derive(Debug, Error)]
+#[error(transparent)]
+pub struct DynKeystoreError(Box<dyn Error + Send + Sync>);
+
+impl DynKeystoreError {
+    pub fn into_inner(self) -> Box<dyn Error + Send + Sync> {
+        self.0
+    }
+}
+
+#[async_trait]
+trait BoundKeystore<Keypair>: Send + Sync {
+    async fn store(&self, keypair: &Keypair, passphrase: &str) -> Result<(), DynKeystoreError>;
+
+    async fn retrieve(&self, passphrase: &str) -> Result<Keypair, DynKeystoreError>;
+
+    async fn rekey(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+    ) -> Result<(), DynKeystoreError>;
+}
+
+struct Bound<Keystore, Params> {
+    keystore: Keystore,
+    params: Params,
+}
+
+#[async_trait]
+impl<Keypair, Keystore, Params> BoundKeystore<Keypair> for Bound<Keystore, Params>
+where
+    Keypair: Encryptable + Send + Sync,
+    Keystore: AsyncEncryptedKeystore<Keypair, Params = Params> + Send + Sync,
+    Keystore::StorageError: 'static,
+    Params: Send + Sync,
+{
+    async fn store(&self, keypair: &Keypair, passphrase: &str) -> Result<(), DynKeystoreError> {
+        self.keystore
+            .store(keypair, passphrase, &self.params)
+            .await
+            .map_err(|err| DynKeystoreError(Box::new(err)))
+    }
+
+    async fn retrieve(&self, passphrase: &str) -> Result<Keypair, DynKeystoreError> {
+        self.keystore
+            .retrieve(passphrase, &self.params)
+            .await
+            .map_err(|err| DynKeystoreError(Box::new(err)))
+    }
+
+    async fn rekey(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+    ) -> Result<(), DynKeystoreError> {
+        self.keystore
+            .rekey(old_passphrase, new_passphrase, new_params, &self.params)
+            .await
+            .map_err(|err| DynKeystoreError(Box::new(err)))
+    }
+}
+
+/// A keystore of any backend together with the params of one keypair in it, so callers can
+/// store and retrieve it without knowing the backend. See [`super::uri::KeystoreUri::resolve`]
+pub struct DynKeystore<Keypair> {
+    inner: Box<dyn BoundKeystore<Keypair>>,
+}
+
+impl<Keypair: Encryptable + Send + Sync + 'static> DynKeystore<Keypair> {
+    pub fn new<Keystore>(keystore: Keystore, params: Keystore::Params) -> Self
+    where
+        Keystore: AsyncEncryptedKeystore<Keypair> + Send + Sync + 'static,
+        Keystore::Params: 'static,
+        Keystore::StorageError: 'static,
+    {
+        Self {
+            inner: Box::new(Bound { keystore, params }),
+        }
+    }
+}
+
+impl<Keypair: Encryptable + Send + Sync> AsyncEncryptedKeystore<Keypair> for DynKeystore<Keypair> {
+    type Params = ();
+    type StorageError = DynKeystoreError;
+
+    async fn store(
+        &self,
+        keypair: &Keypair,
+        passphrase: &str,
+        _: &(),
+    ) -> Result<(), Self::StorageError> {
+        self.inner.store(keypair, passphrase).await
+    }
+
+    async fn retrieve(&self, passphrase: &str, _: &()) -> Result<Keypair, Self::StorageError> {
+        self.inner.retrieve(passphrase).await
+    }
+
+    async fn rekey(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+        _: &(),
+    ) -> Result<(), Self::StorageError> {
+        self.inner
+            .rekey(old_passphrase, new_passphrase, new_params)
+            .await
+    }
+}
+
+impl<Keypair: Encryptable + Send + Sync> DynKeystore<Keypair> {
+    pub async fn store(&self, keypair: &Keypair, passphrase: &str) -> Result<(), DynKeystoreError> {
+        self.inner.store(keypair, passphrase).await
+    }
+
+    pub async fn retrieve(&self, passphrase: &str) -> Result<Keypair, DynKeystoreError> {
+        self.inner.retrieve(passphrase).await
+    }
+
+    pub async fn rekey(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+    ) -> Result<(), DynKeystoreError> {
+        self.inner
+            .rekey(old_passphrase, new_passphrase, new_params)
+            .await
+    }
+}
@@ -0,0 +1,519 @@
+use aes::{
+    cipher::{KeyIvInit, StreamCipher},
+    Aes128,
+};
+use ark_serialize::CanonicalSerialize;
+use ctr::Ctr128BE;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::{
+    encryption::{EncryptionError, KdfParams},
+    keypair::{bn254, secp256k1, traits::Keypair as KeypairTrait},
+};
+
+type Aes128Ctr128BE = Ctr128BE<Aes128>;
+
+pub const EIP2335_VERSION: u32 = 4;
+const DKLEN: usize = 32;
+
+/// Keypairs that can be stored in an EIP-2335 keystore
+pub trait Eip2335Keypair: KeypairTrait {
+    /// Hex encoded public key for the keystore's `pubkey` field
+    fn pubkey_hex(&self) -> String;
+}
+
+impl Eip2335Keypair for bn254::Keypair {
+    // The compressed G1 and G2 keys, the same bytes a proof of possession signs
+    fn pubkey_hex(&self) -> String {
+        let mut bytes = Vec::new();
+        self.public_key()
+            .serialize_compressed(&mut bytes)
+            .expect("serializing into a Vec cannot fail");
+        hex::encode(bytes)
+    }
+}
+
+impl Eip2335Keypair for secp256k1::Keypair {
+    // The SEC1 compressed point
+    fn pubkey_hex(&self) -> String {
+        hex::encode(self.public_key().to_encoded_point(true).as_bytes())
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Eip2335Error {
+    #[error("Unsupported keystore version {0}")]
+    UnsupportedVersion(u32),
+    #[error("Unsupported {module} function {function}")]
+    UnsupportedFunction {
+        module: &'static str,
+        function: String,
+    },
+    #[error("Invalid derived key length {0}")]
+    InvalidKeyLength(usize),
+    #[error("Invalid scrypt parameters")]
+    InvalidScryptParams(#[from] scrypt::errors::InvalidParams),
+    #[error("Invalid scrypt output length")]
+    InvalidScryptOutputLength(#[from] scrypt::errors::InvalidOutputLen),
+    #[error("Invalid cipher length")]
+    InvalidCipherLength,
+    #[error("Checksum verification failed")]
+    ChecksumMismatch,
+    #[error("Public key does not match the decrypted secret key")]
+    PubkeyMismatch,
+    #[error("Failed to decode hex string: {0}")]
+    HexDecodeError(#[from] hex::FromHexError),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Key derivation failed: {0}")]
+    KdfError(#[from] EncryptionError),
+    #[error("Keypair error: {0}")]
+    KeypairError(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl From<aes::cipher::InvalidLength> for Eip2335Error {
+    fn from(_: aes::cipher::InvalidLength) -> Self {
+        Eip2335Error::InvalidCipherLength
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "function", content = "params", rename_all = "lowercase")]
+pub enum Kdf {
+    Scrypt {
+        dklen: usize,
+        n: u64,
+        r: u32,
+        p: u32,
+        salt: String,
+    },
+    Pbkdf2 {
+        dklen: usize,
+        c: u32,
+        prf: String,
+        salt: String,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfModule {
+    #[serde(flatten)]
+    pub kdf: Kdf,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Module<Params> {
+    pub function: String,
+    pub params: Params,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct EmptyParams {}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CipherParams {
+    pub iv: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Crypto {
+    pub kdf: KdfModule,
+    pub checksum: Module<EmptyParams>,
+    pub cipher: Module<CipherParams>,
+}
+
+/// A JSON keystore as specified by EIP-2335, see https://eips.ethereum.org/EIPS/eip-2335
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Eip2335Keystore {
+    pub crypto: Crypto,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub pubkey: String,
+    pub path: String,
+    pub uuid: Uuid,
+    pub version: u32,
+}
+
+impl Crypto {
+    fn encrypt(
+        secret: &[u8],
+        passphrase: &str,
+        kdf_params: &KdfParams,
+    ) -> Result<Self, Eip2335Error> {
+        let mut rng = rand::thread_rng();
+        let mut salt = [0u8; 32];
+        rng.fill_bytes(&mut salt);
+        let mut iv = [0u8; 16];
+        rng.fill_bytes(&mut iv);
+
+        let kdf = match *kdf_params {
+            KdfParams::Scrypt { log_n, r, p } => Kdf::Scrypt {
+                dklen: DKLEN,
+                n: 1u64
+                    .checked_shl(log_n.into())
+                    .ok_or(scrypt::errors::InvalidParams)?,
+                r,
+                p,
+                salt: hex::encode(salt),
+            },
+            KdfParams::Pbkdf2 { iterations } => Kdf::Pbkdf2 {
+                dklen: DKLEN,
+                c: iterations,
+                prf: "hmac-sha256".to_string(),
+                salt: hex::encode(salt),
+            },
+            KdfParams::Argon2id { .. } => {
+                return Err(Eip2335Error::UnsupportedFunction {
+                    module: "kdf",
+                    function: kdf_params.name().to_string(),
+                })
+            }
+        };
+        let decryption_key = derive_key(&kdf, passphrase)?;
+
+        let mut cipher_message = secret.to_vec();
+        Aes128Ctr128BE::new_from_slices(&decryption_key[..16], &iv)?
+            .apply_keystream(&mut cipher_message);
+        let checksum = checksum(&decryption_key, &cipher_message);
+
+        Ok(Crypto {
+            kdf: KdfModule {
+                kdf,
+                message: String::new(),
+            },
+            checksum: Module {
+                function: "sha256".to_string(),
+                params: EmptyParams {},
+                message: hex::encode(checksum),
+            },
+            cipher: Module {
+                function: "aes-128-ctr".to_string(),
+                params: CipherParams {
+                    iv: hex::encode(iv),
+                },
+                message: hex::encode(cipher_message),
+            },
+        })
+    }
+}
+
+impl Eip2335Keystore {
+    /// Encrypts the keypair's secret key with AES-128-CTR, the EIP only allows scrypt and PBKDF2
+    ///
+    /// `path` is the derivation path of the key, or empty if it was not derived.
+    pub fn encrypt<Keypair: Eip2335Keypair>(
+        keypair: &Keypair,
+        passphrase: &str,
+        path: &str,
+        kdf_params: &KdfParams,
+    ) -> Result<Self, Eip2335Error> {
+        Ok(Self {
+            crypto: Crypto::encrypt(&keypair.to_secret_bytes(), passphrase, kdf_params)?,
+            description: None,
+            pubkey: keypair.pubkey_hex(),
+            path: path.to_string(),
+            uuid: Uuid::new_v4(),
+            version: EIP2335_VERSION,
+        })
+    }
+
+    /// Re-encrypts the keystore under a new passphrase, only its `crypto` section changes
+    pub fn rekey<Keypair: Eip2335Keypair>(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        kdf_params: &KdfParams,
+    ) -> Result<Self, Eip2335Error>
+    where
+        Keypair::KeypairError: 'static,
+    {
+        let keypair: Keypair = self.decrypt(old_passphrase)?;
+        Ok(Self {
+            crypto: Crypto::encrypt(&keypair.to_secret_bytes(), new_passphrase, kdf_params)?,
+            ..self.clone()
+        })
+    }
+
+    /// Decrypts the raw secret key, without interpreting it
+    pub fn decrypt_secret(&self, passphrase: &str) -> Result<Zeroizing<Vec<u8>>, Eip2335Error> {
+        if self.version != EIP2335_VERSION {
+            return Err(Eip2335Error::UnsupportedVersion(self.version));
+        }
+        if self.crypto.checksum.function != "sha256" {
+            return Err(Eip2335Error::UnsupportedFunction {
+                module: "checksum",
+                function: self.crypto.checksum.function.clone(),
+            });
+        }
+        if self.crypto.cipher.function != "aes-128-ctr" {
+            return Err(Eip2335Error::UnsupportedFunction {
+                module: "cipher",
+                function: self.crypto.cipher.function.clone(),
+            });
+        }
+
+        let decryption_key = derive_key(&self.crypto.kdf.kdf, passphrase)?;
+        let cipher_message = hex::decode(&self.crypto.cipher.message)?;
+
+        if checksum(&decryption_key, &cipher_message).as_slice()
+            != hex::decode(&self.crypto.checksum.message)?
+        {
+            return Err(Eip2335Error::ChecksumMismatch);
+        }
+
+        let iv = hex::decode(&self.crypto.cipher.params.iv)?;
+        let mut secret = Zeroizing::new(cipher_message);
+        Aes128Ctr128BE::new_from_slices(&decryption_key[..16], &iv)?.apply_keystream(&mut secret);
+
+        Ok(secret)
+    }
+
+    /// Decrypts the keypair, checking it against the keystore's public key if one is present
+    pub fn decrypt<Keypair: Eip2335Keypair>(
+        &self,
+        passphrase: &str,
+    ) -> Result<Keypair, Eip2335Error>
+    where
+        Keypair::KeypairError: 'static,
+    {
+        let secret = self.decrypt_secret(passphrase)?;
+        let keypair = Keypair::from_secret_bytes(&secret)
+            .map_err(|err| Eip2335Error::KeypairError(Box::new(err)))?;
+
+        if !self.pubkey.is_empty() && !self.pubkey.eq_ignore_ascii_case(&keypair.pubkey_hex()) {
+            return Err(Eip2335Error::PubkeyMismatch);
+        }
+        Ok(keypair)
+    }
+
+    pub fn to_json(&self) -> Result<String, Eip2335Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json<B: AsRef<[u8]>>(json: B) -> Result<Self, Eip2335Error> {
+        Ok(serde_json::from_slice(json.as_ref())?)
+    }
+}
+
+// NFKD normalization with control codes stripped, as required for passwords by the EIP
+fn process_passphrase(passphrase: &str) -> Zeroizing<String> {
+    Zeroizing::new(
+        passphrase
+            .nfkd()
+            .filter(|c| !matches!(*c as u32, 0x00..=0x1f | 0x7f..=0x9f))
+            .collect(),
+    )
+}
+
+// Derives through `KdfParams`, so keystore JSON is held to the same parameter limits as payloads
+fn derive_key(kdf: &Kdf, passphrase: &str) -> Result<Zeroizing<[u8; DKLEN]>, Eip2335Error> {
+    let (dklen, kdf_params, salt) = match kdf {
+        Kdf::Scrypt {
+            dklen,
+            n,
+            r,
+            p,
+            salt,
+        } => {
+            if !n.is_power_of_two() {
+                return Err(scrypt::errors::InvalidParams.into());
+            }
+            let log_n = n.trailing_zeros() as u8;
+            (
+                *dklen,
+                KdfParams::Scrypt {
+                    log_n,
+                    r: *r,
+                    p: *p,
+                },
+                salt,
+            )
+        }
+        Kdf::Pbkdf2 {
+            dklen,
+            c,
+            prf,
+            salt,
+        } => {
+            if prf != "hmac-sha256" {
+                return Err(Eip2335Error::UnsupportedFunction {
+                    module: "kdf prf",
+                    function: prf.clone(),
+                });
+            }
+            (*dklen, KdfParams::Pbkdf2 { iterations: *c }, salt)
+        }
+    };
+    if dklen != DKLEN {
+        return Err(Eip2335Error::InvalidKeyLength(dklen));
+    }
+
+    let passphrase = process_passphrase(passphrase);
+    let derived_key = kdf_params.derive_key(passphrase.as_bytes(), &hex::decode(salt)?, DKLEN)?;
+    let mut key = Zeroizing::new([0u8; DKLEN]);
+    key.copy_from_slice(&derived_key);
+    Ok(key)
+}
+
+fn checksum(decryption_key: &[u8; DKLEN], cipher_message: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(&decryption_key[16..]);
+    hasher.update(cipher_message);
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWORD: &str = "𝔱𝔢𝔰𝔱𝔭𝔞𝔰𝔰𝔴𝔬𝔯𝔡🔑";
+    const SECRET: &str = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+
+    // The test vectors of the EIP, whose pubkey is a BLS12-381 key
+    const SCRYPT_VECTOR: &str = r#"{
+        "crypto": {
+            "kdf": {
+                "function": "scrypt",
+                "params": {
+                    "dklen": 32,
+                    "n": 262144,
+                    "p": 1,
+                    "r": 8,
+                    "salt": "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+                },
+                "message": ""
+            },
+            "checksum": {
+                "function": "sha256",
+                "params": {},
+                "message": "d2217fe5f3e9a1e34581ef8a78f7c9928e436d36dacc5e846690a5581e8ea484"
+            },
+            "cipher": {
+                "function": "aes-128-ctr",
+                "params": {
+                    "iv": "264daa3f303d7259501c93d997d84fe6"
+                },
+                "message": "06ae90d55fe0a6e9c5c3bc5b170827b2e5cce3929ed3f116c2811e6366dfe20f"
+            }
+        },
+        "description": "This is a test keystore that uses scrypt to secure the secret.",
+        "pubkey": "9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07",
+        "path": "m/12381/60/3141592653/589793238",
+        "uuid": "1d85ae20-35c5-4611-98e8-aa14a633906f",
+        "version": 4
+    }"#;
+
+    const PBKDF2_VECTOR: &str = r#"{
+        "crypto": {
+            "kdf": {
+                "function": "pbkdf2",
+                "params": {
+                    "dklen": 32,
+                    "c": 262144,
+                    "prf": "hmac-sha256",
+                    "salt": "d4e56740f876aef8c010b86a40d5f56745a118d0906a34e69aec8c0db1cb8fa3"
+                },
+                "message": ""
+            },
+            "checksum": {
+                "function": "sha256",
+                "params": {},
+                "message": "8a9f5d9912ed7e75ea794bc5a89bca5f193721d30868ade6f73043c6ea6febf1"
+            },
+            "cipher": {
+                "function": "aes-128-ctr",
+                "params": {
+                    "iv": "264daa3f303d7259501c93d997d84fe6"
+                },
+                "message": "cee03fde2af33149775b7223e7845e4fb2c8ae1792e5f99fe9ecf474cc8c16ad"
+            }
+        },
+        "description": "This is a test keystore that uses PBKDF2 to secure the secret.",
+        "pubkey": "9612d7a727c9d0a22e185a1c768478dfe919cada9266988cb32359c11f2b7b27f4ae4040902382ae2910c15e2b420d07",
+        "path": "m/12381/60/0/0",
+        "uuid": "64625def-3331-4eea-ab6f-782f3ed16a83",
+        "version": 4
+    }"#;
+
+    #[test]
+    fn test_eip2335_vectors() {
+        for vector in [SCRYPT_VECTOR, PBKDF2_VECTOR] {
+            let keystore = Eip2335Keystore::from_json(vector).unwrap();
+            let secret = keystore.decrypt_secret(PASSWORD).unwrap();
+            assert_eq!(hex::encode(secret.as_slice()), SECRET);
+        }
+    }
+
+    #[test]
+    fn test_eip2335_roundtrip() {
+        let keypair = bn254::Keypair::generate();
+        let kdf_params = KdfParams::Scrypt {
+            log_n: 10,
+            r: 8,
+            p: 1,
+        };
+        let keystore =
+            Eip2335Keystore::encrypt(&keypair, "passphrase", "m/2333/0/0", &kdf_params).unwrap();
+        let json = keystore.to_json().unwrap();
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], 4);
+        assert_eq!(value["crypto"]["kdf"]["function"], "scrypt");
+        assert_eq!(value["crypto"]["kdf"]["params"]["n"], 1024);
+        assert_eq!(value["pubkey"], keypair.pubkey_hex());
+
+        let restored: bn254::Keypair = Eip2335Keystore::from_json(&json)
+            .unwrap()
+            .decrypt("passphrase")
+            .unwrap();
+        assert_eq!(restored.public_key(), keypair.public_key());
+
+        assert!(matches!(
+            keystore.decrypt::<bn254::Keypair>("wrong passphrase"),
+            Err(Eip2335Error::ChecksumMismatch)
+        ));
+
+        let mut other_pubkey = keystore.clone();
+        other_pubkey.pubkey = bn254::Keypair::generate().pubkey_hex();
+        assert!(matches!(
+            other_pubkey.decrypt::<bn254::Keypair>("passphrase"),
+            Err(Eip2335Error::PubkeyMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_eip2335_oversized_kdf_params() {
+        let mut scrypt = Eip2335Keystore::from_json(SCRYPT_VECTOR).unwrap();
+        if let Kdf::Scrypt { n, .. } = &mut scrypt.crypto.kdf.kdf {
+            *n = 1 << 40;
+        }
+        let mut pbkdf2 = Eip2335Keystore::from_json(PBKDF2_VECTOR).unwrap();
+        if let Kdf::Pbkdf2 { c, .. } = &mut pbkdf2.crypto.kdf.kdf {
+            *c = u32::MAX;
+        }
+
+        for keystore in [scrypt, pbkdf2] {
+            assert!(matches!(
+                keystore.decrypt_secret(PASSWORD),
+                Err(Eip2335Error::KdfError(EncryptionError::InvalidKdfParams(_)))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_process_passphrase() {
+        assert_eq!(process_passphrase(PASSWORD).as_str(), "testpassword🔑");
+        assert_eq!(
+            process_passphrase("pass\u{7f}\u{0}word\n").as_str(),
+            "password"
+        );
+    }
+}
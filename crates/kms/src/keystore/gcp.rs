@@ -1,35 +1,573 @@
+use std::sync::{Arc, RwLock};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
 use thiserror::Error;
+use url::Url;
+
+use crate::{encryption::EncryptionParams, keypair::traits::Encryptable};
 
-use crate::keypair::traits::Encryptable;
+use super::traits::AsyncEncryptedKeystore;
 
-use super::traits::EncryptedKeystore;
+pub const GCP_SECRET_MANAGER_URL: &str = "https://secretmanager.googleapis.com/v1/";
 
 #[derive(Debug, Error)]
-pub enum GcpKeystoreError {
+pub enum GcpKeystoreError<E: std::error::Error + Send + Sync> {
     #[error("Encryption error: {0}")]
-    EncryptionError(String),
+    EncryptionError(E),
+    #[error("GCP Secret Manager request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("GCP Secret Manager error ({status}): {message}")]
+    ApiError { status: StatusCode, message: String },
+    #[error("GCP secret {0} not found")]
+    SecretNotFound(String),
+    #[error("Invalid URL: {0}")]
+    UrlError(#[from] url::ParseError),
+    #[error("Failed to decode secret payload: {0}")]
+    DecodingError(#[from] base64::DecodeError),
+    #[error("Failed to refresh the GCP access token: {0}")]
+    AuthError(std::io::Error),
+    #[error("Cannot rekey pinned version {0}, only the latest version can be rekeyed")]
+    PinnedVersion(String),
+    #[error("Invalid GCP {field} {value:?}, only letters, digits, `-` and `_` are allowed")]
+    InvalidId { field: &'static str, value: String },
 }
 
+type TokenSource = Arc<dyn Fn() -> std::io::Result<String> + Send + Sync>;
+
+/// A keystore over the Secret Manager REST API, every store adds a new version of the secret
 pub struct GcpEncrypedKeystore {
-    // TODO
+    client: Client,
+    base_url: Url,
+    access_token: RwLock<String>,
+    token_source: Option<TokenSource>,
+    encryption_params: EncryptionParams,
+}
+
+impl GcpEncrypedKeystore {
+    /// `access_token` is an OAuth 2.0 token with the `cloud-platform` scope, for example the
+    /// output of `gcloud auth print-access-token`
+    pub fn new(access_token: impl Into<String>) -> Self {
+        Self {
+            client: Client::new(),
+            // The constant is a valid URL
+            base_url: Url::parse(GCP_SECRET_MANAGER_URL).unwrap(),
+            access_token: RwLock::new(access_token.into()),
+            token_source: None,
+            encryption_params: EncryptionParams::default(),
+        }
+    }
+
+    /// Points the keystore at another Secret Manager endpoint, such as a regional one
+    pub fn with_base_url(mut self, base_url: Url) -> Self {
+        self.base_url = base_url;
+        self
+    }
+
+    pub fn with_encryption_params(mut self, encryption_params: EncryptionParams) -> Self {
+        self.encryption_params = encryption_params;
+        self
+    }
+
+    /// Access tokens expire after an hour, when a request is rejected as unauthenticated a new
+    /// token is fetched from `token_source` and the request is retried once
+    pub fn with_token_source(
+        mut self,
+        token_source: impl Fn() -> std::io::Result<String> + Send + Sync + 'static,
+    ) -> Self {
+        self.token_source = Some(Arc::new(token_source));
+        self
+    }
+}
+
+/// An access token from `GOOGLE_OAUTH_ACCESS_TOKEN`, or else from the gcloud CLI's active account
+pub fn access_token_from_env() -> std::io::Result<String> {
+    if let Ok(token) = std::env::var("GOOGLE_OAUTH_ACCESS_TOKEN") {
+        return Ok(token);
+    }
+
+    let output = std::process::Command::new("gcloud")
+        .args(["auth", "print-access-token"])
+        .output()?;
+    if !output.status.success() {
+        return Err(std::io::Error::other(format!(
+            "gcloud auth print-access-token failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8(output.stdout)
+        .map(|token| token.trim().to_string())
+        .map_err(std::io::Error::other)
+}
+
+pub struct GcpKeystoreParams {
+    pub project_id: String,
+    pub secret_id: String,
+    /// The version to retrieve, `None` for the latest one
+    pub version: Option<String>,
+}
+
+impl GcpKeystoreParams {
+    pub fn new(project_id: impl Into<String>, secret_id: impl Into<String>) -> Self {
+        Self {
+            project_id: project_id.into(),
+            secret_id: secret_id.into(),
+            version: None,
+        }
+    }
+
+    fn secret_path(&self) -> String {
+        format!("projects/{}/secrets/{}", self.project_id, self.secret_id)
+    }
+
+    // The IDs are pasted into the request path, so they must not be able to change it
+    fn validate<E: std::error::Error + Send + Sync>(&self) -> Result<(), GcpKeystoreError<E>> {
+        let ids = [
+            ("project", Some(&self.project_id)),
+            ("secret", Some(&self.secret_id)),
+            ("version", self.version.as_ref()),
+        ];
+        for (field, value) in ids {
+            let Some(value) = value else { continue };
+            let valid = !value.is_empty()
+                && value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !valid {
+                return Err(GcpKeystoreError::InvalidId {
+                    field,
+                    value: value.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SecretPayload {
+    data: String,
+}
+
+#[derive(Deserialize)]
+struct AccessSecretVersionResponse {
+    payload: SecretPayload,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorResponse {
+    error: ApiErrorBody,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorBody {
+    message: String,
 }
 
 impl GcpEncrypedKeystore {
-    // TODO: Implement and remove this lint
-    #[allow(clippy::new_without_default)]
-    pub fn new() -> Self {
-        Self {}
+    fn access_token(&self) -> String {
+        self.access_token.read().unwrap().clone()
+    }
+
+    async fn send<E: std::error::Error + Send + Sync>(
+        &self,
+        request: impl Fn(String) -> RequestBuilder,
+    ) -> Result<Response, GcpKeystoreError<E>> {
+        let response = request(self.access_token()).send().await?;
+        let Some(token_source) = &self.token_source else {
+            return Ok(response);
+        };
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+
+        // The gcloud CLI blocks for a while
+        let token_source = token_source.clone();
+        let access_token = tokio::task::spawn_blocking(move || token_source())
+            .await
+            .map_err(|err| GcpKeystoreError::AuthError(std::io::Error::other(err)))?
+            .map_err(GcpKeystoreError::AuthError)?;
+        *self.access_token.write().unwrap() = access_token.clone();
+        Ok(request(access_token).send().await?)
+    }
+
+    async fn check<E: std::error::Error + Send + Sync>(
+        response: Response,
+        params: &GcpKeystoreParams,
+    ) -> Result<Response, GcpKeystoreError<E>> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        if status == StatusCode::NOT_FOUND {
+            return Err(GcpKeystoreError::SecretNotFound(params.secret_path()));
+        }
+
+        let body = response.text().await?;
+        let message = serde_json::from_str::<ApiErrorResponse>(&body)
+            .map(|response| response.error.message)
+            .unwrap_or(body);
+        Err(GcpKeystoreError::ApiError { status, message })
+    }
+
+    async fn add_version<E: std::error::Error + Send + Sync>(
+        &self,
+        encrypted_keypair: &[u8],
+        params: &GcpKeystoreParams,
+    ) -> Result<(), GcpKeystoreError<E>> {
+        let url = self
+            .base_url
+            .join(&format!("{}:addVersion", params.secret_path()))?;
+        let body = json!({ "payload": SecretPayload { data: STANDARD.encode(encrypted_keypair) } });
+        let response = self
+            .send(|token| self.client.post(url.clone()).bearer_auth(token).json(&body))
+            .await?;
+        Self::check(response, params).await?;
+        Ok(())
+    }
+
+    async fn create_secret<E: std::error::Error + Send + Sync>(
+        &self,
+        params: &GcpKeystoreParams,
+    ) -> Result<(), GcpKeystoreError<E>> {
+        let mut url = self
+            .base_url
+            .join(&format!("projects/{}/secrets", params.project_id))?;
+        url.query_pairs_mut()
+            .append_pair("secretId", &params.secret_id);
+        let response = self
+            .send(|token| {
+                self.client
+                    .post(url.clone())
+                    .bearer_auth(token)
+                    .json(&json!({ "replication": { "automatic": {} } }))
+            })
+            .await?;
+        Self::check(response, params).await?;
+        Ok(())
     }
 }
 
-impl<Keypair: Encryptable> EncryptedKeystore<Keypair> for GcpEncrypedKeystore {
-    type StorageError = GcpKeystoreError;
+impl<Keypair: Encryptable + Send + Sync> AsyncEncryptedKeystore<Keypair> for GcpEncrypedKeystore {
+    type Params = GcpKeystoreParams;
+    type StorageError = GcpKeystoreError<Keypair::EncryptionError>;
+
+    async fn store(
+        &self,
+        keypair: &Keypair,
+        passphrase: &str,
+        params: &GcpKeystoreParams,
+    ) -> Result<(), Self::StorageError> {
+        params.validate()?;
+        let encrypted_keypair = keypair
+            .encrypt_with_params(passphrase, &self.encryption_params)
+            .map_err(GcpKeystoreError::EncryptionError)?;
+
+        match self.add_version(&encrypted_keypair, params).await {
+            Err(GcpKeystoreError::SecretNotFound(_)) => {
+                self.create_secret(params).await?;
+                self.add_version(&encrypted_keypair, params).await
+            }
+            result => result,
+        }
+    }
+
+    async fn retrieve(
+        &self,
+        passphrase: &str,
+        params: &GcpKeystoreParams,
+    ) -> Result<Keypair, Self::StorageError> {
+        params.validate()?;
+        let url = self.base_url.join(&format!(
+            "{}/versions/{}:access",
+            params.secret_path(),
+            params.version.as_deref().unwrap_or("latest")
+        ))?;
+        let response = self
+            .send(|token| self.client.get(url.clone()).bearer_auth(token))
+            .await?;
+        let response: AccessSecretVersionResponse =
+            Self::check(response, params).await?.json().await?;
+
+        let encrypted_keypair = STANDARD.decode(response.payload.data)?;
+        Keypair::decrypt(&encrypted_keypair, passphrase).map_err(GcpKeystoreError::EncryptionError)
+    }
+
+    // Always rekeys the latest version, adding a version rekeyed from an older one would roll the
+    // key back. Adds a new version, so the previous one stays accessible by its version number under the
+    // old passphrase until it is destroyed, e.g. with `gcloud secrets versions destroy`
+    async fn rekey(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+        params: &GcpKeystoreParams,
+    ) -> Result<(), Self::StorageError> {
+        if let Some(version) = &params.version {
+            return Err(GcpKeystoreError::PinnedVersion(version.clone()));
+        }
+        let keypair: Keypair = self.retrieve(old_passphrase, params).await?;
+        let encrypted_keypair = keypair
+            .encrypt_with_params(new_passphrase, new_params)
+            .map_err(GcpKeystoreError::EncryptionError)?;
+
+        self.add_version(&encrypted_keypair, params).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use axum::{
+        extract::{Path, Query, State},
+        http::{HeaderMap, StatusCode},
+        routing::{get, post},
+        Json, Router,
+    };
+    use serde_json::Value;
+
+    use crate::{
+        encryption::KdfParams,
+        keypair::{bn254, traits::Keypair as _},
+        test_utils::{spawn_mock, LIGHT_KDF},
+    };
+
+    use super::*;
+
+    type Secrets = Arc<Mutex<HashMap<String, Vec<String>>>>;
+
+    fn authorized(headers: &HeaderMap) -> Result<(), (StatusCode, Json<Value>)> {
+        match headers.get("authorization") {
+            Some(value) if value == "Bearer token" => Ok(()),
+            _ => Err((
+                StatusCode::UNAUTHORIZED,
+                Json(json!({ "error": { "code": 401, "message": "Invalid credentials" } })),
+            )),
+        }
+    }
+
+    fn not_found() -> (StatusCode, Json<Value>) {
+        (
+            StatusCode::NOT_FOUND,
+            Json(json!({ "error": { "code": 404, "message": "Secret not found" } })),
+        )
+    }
+
+    // Axum cannot route on the `:verb` suffix, so the last segment is matched by hand
+    async fn secret_action(
+        State(secrets): State<Secrets>,
+        Path((project, action)): Path<(String, String)>,
+        headers: HeaderMap,
+        Json(body): Json<Value>,
+    ) -> (StatusCode, Json<Value>) {
+        if let Err(err) = authorized(&headers) {
+            return err;
+        }
+        let Some(secret) = action.strip_suffix(":addVersion") else {
+            return not_found();
+        };
+        let mut secrets = secrets.lock().unwrap();
+        let Some(versions) = secrets.get_mut(&format!("{project}/{secret}")) else {
+            return not_found();
+        };
+        versions.push(body["payload"]["data"].as_str().unwrap().to_string());
+        (
+            StatusCode::OK,
+            Json(json!({ "name": versions.len().to_string() })),
+        )
+    }
 
-    fn store(&self, _keypair: &Keypair, _passphrase: &str) -> Result<(), Self::StorageError> {
-        todo!()
+    async fn create_secret(
+        State(secrets): State<Secrets>,
+        Path(project): Path<String>,
+        Query(query): Query<HashMap<String, String>>,
+        headers: HeaderMap,
+    ) -> (StatusCode, Json<Value>) {
+        if let Err(err) = authorized(&headers) {
+            return err;
+        }
+        secrets
+            .lock()
+            .unwrap()
+            .insert(format!("{project}/{}", query["secretId"]), vec![]);
+        (StatusCode::OK, Json(json!({})))
     }
 
-    fn retrieve(&self, _passphrase: &str) -> Result<Keypair, Self::StorageError> {
-        todo!()
+    async fn access_version(
+        State(secrets): State<Secrets>,
+        Path((project, secret, version)): Path<(String, String, String)>,
+        headers: HeaderMap,
+    ) -> (StatusCode, Json<Value>) {
+        if let Err(err) = authorized(&headers) {
+            return err;
+        }
+        let secrets = secrets.lock().unwrap();
+        let Some(versions) = secrets.get(&format!("{project}/{secret}")) else {
+            return not_found();
+        };
+        let data = match version.strip_suffix(":access") {
+            Some("latest") => versions.last(),
+            Some(number) => number
+                .parse::<usize>()
+                .ok()
+                .and_then(|number| versions.get(number.wrapping_sub(1))),
+            None => None,
+        };
+        match data {
+            Some(data) => (
+                StatusCode::OK,
+                Json(json!({ "name": version, "payload": { "data": data } })),
+            ),
+            None => not_found(),
+        }
+    }
+
+    async fn secret_manager() -> (Url, Secrets) {
+        let secrets = Secrets::default();
+        let app = Router::new()
+            .route("/v1/projects/:project/secrets", post(create_secret))
+            .route("/v1/projects/:project/secrets/:action", post(secret_action))
+            .route(
+                "/v1/projects/:project/secrets/:secret/versions/:version",
+                get(access_version),
+            )
+            .with_state(secrets.clone());
+
+        let url = spawn_mock(app).await.join("v1/").unwrap();
+        (url, secrets)
+    }
+
+    #[tokio::test]
+    async fn test_store_retrieve_and_rekey() {
+        let (url, secrets) = secret_manager().await;
+        let keystore = GcpEncrypedKeystore::new("token")
+            .with_base_url(url)
+            .with_encryption_params(LIGHT_KDF.into());
+        let params = GcpKeystoreParams::new("karak", "operator-bls");
+        let keypair = bn254::Keypair::generate();
+
+        // The secret does not exist yet and is created
+        keystore
+            .store(&keypair, "passphrase", &params)
+            .await
+            .unwrap();
+        keystore
+            .store(&keypair, "passphrase", &params)
+            .await
+            .unwrap();
+        assert_eq!(secrets.lock().unwrap()["karak/operator-bls"].len(), 2);
+
+        let retrieved: bn254::Keypair = keystore.retrieve("passphrase", &params).await.unwrap();
+        assert_eq!(retrieved.public_key(), keypair.public_key());
+
+        AsyncEncryptedKeystore::<bn254::Keypair>::rekey(
+            &keystore,
+            "passphrase",
+            "new passphrase",
+            &KdfParams::Pbkdf2 { iterations: 2_000 }.into(),
+            &params,
+        )
+        .await
+        .unwrap();
+        let retrieved: bn254::Keypair = keystore.retrieve("new passphrase", &params).await.unwrap();
+        assert_eq!(retrieved.public_key(), keypair.public_key());
+
+        // Older versions are still encrypted with the old passphrase
+        let first_version = GcpKeystoreParams {
+            version: Some("1".to_string()),
+            ..GcpKeystoreParams::new("karak", "operator-bls")
+        };
+        let retrieved: bn254::Keypair = keystore
+            .retrieve("passphrase", &first_version)
+            .await
+            .unwrap();
+        assert_eq!(retrieved.public_key(), keypair.public_key());
+
+        // Rekeying an older version would roll the key back
+        let result = AsyncEncryptedKeystore::<bn254::Keypair>::rekey(
+            &keystore,
+            "passphrase",
+            "new passphrase",
+            &LIGHT_KDF.into(),
+            &first_version,
+        )
+        .await;
+        assert!(matches!(result, Err(GcpKeystoreError::PinnedVersion(version)) if version == "1"));
+        assert_eq!(secrets.lock().unwrap()["karak/operator-bls"].len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_refreshes_expired_token() {
+        let (url, _) = secret_manager().await;
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let keystore = GcpEncrypedKeystore::new("expired")
+            .with_base_url(url)
+            .with_encryption_params(LIGHT_KDF.into())
+            .with_token_source({
+                let refreshes = refreshes.clone();
+                move || {
+                    refreshes.fetch_add(1, Ordering::SeqCst);
+                    Ok("token".to_string())
+                }
+            });
+        let params = GcpKeystoreParams::new("karak", "operator-bls");
+        let keypair = bn254::Keypair::generate();
+
+        keystore
+            .store(&keypair, "passphrase", &params)
+            .await
+            .unwrap();
+        let retrieved: bn254::Keypair = keystore.retrieve("passphrase", &params).await.unwrap();
+        assert_eq!(retrieved.public_key(), keypair.public_key());
+        // The refreshed token is kept
+        assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let (url, _) = secret_manager().await;
+        let keystore = GcpEncrypedKeystore::new("token").with_base_url(url.clone());
+
+        let result: Result<bn254::Keypair, _> = keystore
+            .retrieve("passphrase", &GcpKeystoreParams::new("karak", "missing"))
+            .await;
+        assert!(matches!(result, Err(GcpKeystoreError::SecretNotFound(_))));
+
+        let unauthorized = GcpEncrypedKeystore::new("expired")
+            .with_base_url(url)
+            .with_encryption_params(LIGHT_KDF.into());
+        let result = unauthorized
+            .store(
+                &bn254::Keypair::generate(),
+                "passphrase",
+                &GcpKeystoreParams::new("karak", "operator-bls"),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(GcpKeystoreError::ApiError { status: StatusCode::UNAUTHORIZED, message }) if message == "Invalid credentials"
+        ));
+
+        for params in [
+            GcpKeystoreParams::new("karak/secrets/other", "operator-bls"),
+            GcpKeystoreParams::new("karak", "../../other/secrets/operator-bls"),
+            GcpKeystoreParams::new("karak", ""),
+            GcpKeystoreParams {
+                version: Some("latest:access?".to_string()),
+                ..GcpKeystoreParams::new("karak", "operator-bls")
+            },
+        ] {
+            let result: Result<bn254::Keypair, _> = keystore.retrieve("passphrase", &params).await;
+            assert!(matches!(result, Err(GcpKeystoreError::InvalidId { .. })));
+        }
     }
 }
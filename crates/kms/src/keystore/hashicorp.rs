@@ -0,0 +1,600 @@
+use std::fmt;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use thiserror::Error;
+use tokio::sync::RwLock;
+use url::Url;
+
+use crate::{encryption::EncryptionParams, keypair::traits::Encryptable};
+
+use super::traits::AsyncEncryptedKeystore;
+
+pub const DEFAULT_KV_MOUNT: &str = "secret";
+pub const DEFAULT_APPROLE_MOUNT: &str = "approle";
+/// The key of the secret's data the encrypted keypair is stored under
+const KEYPAIR_FIELD: &str = "keypair";
+
+#[derive(Debug, Error)]
+pub enum VaultKeystoreError<E: std::error::Error + Send + Sync> {
+    #[error("Encryption error: {0}")]
+    EncryptionError(E),
+    #[error("Vault request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Vault error ({status}): {message}")]
+    ApiError { status: StatusCode, message: String },
+    #[error("Vault secret {0} not found")]
+    SecretNotFound(String),
+    #[error("Vault secret {0} has no {KEYPAIR_FIELD} field")]
+    KeypairMissing(String),
+    #[error("Invalid URL: {0}")]
+    UrlError(#[from] url::ParseError),
+    #[error("Failed to decode secret data: {0}")]
+    DecodingError(#[from] base64::DecodeError),
+}
+
+#[derive(Clone)]
+pub enum VaultAuth {
+    Token(String),
+    /// Logs in on the first request, and again once the token is rejected
+    AppRole {
+        role_id: String,
+        secret_id: String,
+        mount: String,
+    },
+}
+
+impl VaultAuth {
+    /// `VAULT_TOKEN`, or else AppRole with `VAULT_ROLE_ID` and `VAULT_SECRET_ID`
+    pub fn from_env() -> Option<Self> {
+        if let Ok(token) = std::env::var("VAULT_TOKEN") {
+            return Some(VaultAuth::Token(token));
+        }
+        match (
+            std::env::var("VAULT_ROLE_ID"),
+            std::env::var("VAULT_SECRET_ID"),
+        ) {
+            (Ok(role_id), Ok(secret_id)) => Some(VaultAuth::approle(role_id, secret_id)),
+            _ => None,
+        }
+    }
+
+    pub fn approle(role_id: impl Into<String>, secret_id: impl Into<String>) -> Self {
+        VaultAuth::AppRole {
+            role_id: role_id.into(),
+            secret_id: secret_id.into(),
+            mount: DEFAULT_APPROLE_MOUNT.to_string(),
+        }
+    }
+}
+
+// The token and secret ID are credentials, so they are never printed
+impl fmt::Debug for VaultAuth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VaultAuth::Token(_) => f.write_str("Token(<redacted>)"),
+            VaultAuth::AppRole { role_id, mount, .. } => f
+                .debug_struct("AppRole")
+                .field("role_id", role_id)
+                .field("secret_id", &format_args!("<redacted>"))
+                .field("mount", mount)
+                .finish(),
+        }
+    }
+}
+
+/// A keystore over Vault's KV v2 secrets engine, every store writes a new version of the secret
+pub struct VaultEncryptedKeystore {
+    client: Client,
+    address: Url,
+    auth: VaultAuth,
+    namespace: Option<String>,
+    token: RwLock<Option<String>>,
+    encryption_params: EncryptionParams,
+}
+
+impl VaultEncryptedKeystore {
+    /// `address` is the Vault server, e.g. `https://vault.example.com:8200`
+    pub fn new(address: Url, auth: VaultAuth) -> Self {
+        Self {
+            client: Client::new(),
+            address,
+            auth,
+            namespace: None,
+            token: RwLock::new(None),
+            encryption_params: EncryptionParams::default(),
+        }
+    }
+
+    /// Sets the Vault Enterprise namespace requests are made in
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn with_encryption_params(mut self, encryption_params: EncryptionParams) -> Self {
+        self.encryption_params = encryption_params;
+        self
+    }
+}
+
+pub struct VaultKeystoreParams {
+    /// The mount path of the KV v2 engine
+    pub mount: String,
+    pub path: String,
+    /// The version to retrieve, `None` for the latest one
+    pub version: Option<u64>,
+}
+
+impl VaultKeystoreParams {
+    pub fn new(mount: impl Into<String>, path: impl Into<String>) -> Self {
+        Self {
+            mount: mount.into(),
+            path: path.into(),
+            version: None,
+        }
+    }
+
+    fn data_path(&self) -> String {
+        format!(
+            "v1/{}/data/{}",
+            self.mount.trim_matches('/'),
+            self.path.trim_matches('/')
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct LoginResponse {
+    auth: LoginAuth,
+}
+
+#[derive(Deserialize)]
+struct LoginAuth {
+    client_token: String,
+}
+
+#[derive(Deserialize)]
+struct ReadResponse {
+    data: ReadData,
+}
+
+#[derive(Deserialize)]
+struct ReadData {
+    data: Option<serde_json::Map<String, serde_json::Value>>,
+    metadata: ReadMetadata,
+}
+
+#[derive(Deserialize)]
+struct ReadMetadata {
+    version: u64,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorResponse {
+    errors: Vec<String>,
+}
+
+impl VaultEncryptedKeystore {
+    async fn check<E: std::error::Error + Send + Sync>(
+        response: Response,
+        path: &str,
+    ) -> Result<Response, VaultKeystoreError<E>> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        if status == StatusCode::NOT_FOUND {
+            return Err(VaultKeystoreError::SecretNotFound(path.to_string()));
+        }
+
+        let body = response.text().await?;
+        let message = serde_json::from_str::<ApiErrorResponse>(&body)
+            .map(|response| response.errors.join(", "))
+            .unwrap_or(body);
+        Err(VaultKeystoreError::ApiError { status, message })
+    }
+
+    async fn token<E: std::error::Error + Send + Sync>(
+        &self,
+    ) -> Result<String, VaultKeystoreError<E>> {
+        let (role_id, secret_id, mount) = match &self.auth {
+            VaultAuth::Token(token) => return Ok(token.clone()),
+            VaultAuth::AppRole {
+                role_id,
+                secret_id,
+                mount,
+            } => (role_id, secret_id, mount),
+        };
+        if let Some(token) = self.token.read().await.as_ref() {
+            return Ok(token.clone());
+        }
+
+        let mut token = self.token.write().await;
+        if let Some(token) = token.as_ref() {
+            return Ok(token.clone());
+        }
+        let path = format!("v1/auth/{}/login", mount.trim_matches('/'));
+        let request = self
+            .client
+            .post(self.address.join(&path)?)
+            .json(&json!({ "role_id": role_id, "secret_id": secret_id }));
+        let response = self.namespaced(request).send().await?;
+        let response: LoginResponse = Self::check(response, &path).await?.json().await?;
+        Ok(token.insert(response.auth.client_token).clone())
+    }
+
+    /// Sends the request with the current token. AppRole tokens expire, if the token is rejected
+    /// the keystore logs in again and retries once
+    async fn send<E: std::error::Error + Send + Sync>(
+        &self,
+        request: impl Fn(&str) -> RequestBuilder,
+    ) -> Result<Response, VaultKeystoreError<E>> {
+        let token = self.token().await?;
+        let response = self.namespaced(request(&token)).send().await?;
+        if response.status() != StatusCode::FORBIDDEN
+            || !matches!(self.auth, VaultAuth::AppRole { .. })
+        {
+            return Ok(response);
+        }
+
+        {
+            // Another request may have logged in again already
+            let mut cached = self.token.write().await;
+            if cached.as_deref() == Some(token.as_str()) {
+                *cached = None;
+            }
+        }
+        let token = self.token().await?;
+        Ok(self.namespaced(request(&token)).send().await?)
+    }
+
+    fn namespaced(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.namespace {
+            Some(namespace) => request.header("X-Vault-Namespace", namespace),
+            None => request,
+        }
+    }
+
+    async fn read<E: std::error::Error + Send + Sync>(
+        &self,
+        params: &VaultKeystoreParams,
+    ) -> Result<(Vec<u8>, u64), VaultKeystoreError<E>> {
+        let path = params.data_path();
+        let mut url = self.address.join(&path)?;
+        if let Some(version) = params.version {
+            url.query_pairs_mut()
+                .append_pair("version", &version.to_string());
+        }
+
+        let response = self
+            .send(|token| self.client.get(url.clone()).header("X-Vault-Token", token))
+            .await?;
+        let response: ReadResponse = Self::check(response, &path).await?.json().await?;
+
+        // Deleted versions are returned with null data
+        let encoded = response
+            .data
+            .data
+            .as_ref()
+            .and_then(|data| data.get(KEYPAIR_FIELD))
+            .and_then(|value| value.as_str())
+            .ok_or_else(|| VaultKeystoreError::KeypairMissing(path.clone()))?;
+        Ok((STANDARD.decode(encoded)?, response.data.metadata.version))
+    }
+
+    /// `cas` makes the write fail if the latest version is no longer `cas`
+    async fn write<E: std::error::Error + Send + Sync>(
+        &self,
+        encrypted_keypair: &[u8],
+        params: &VaultKeystoreParams,
+        cas: Option<u64>,
+    ) -> Result<(), VaultKeystoreError<E>> {
+        let path = params.data_path();
+        let mut body = json!({ "data": { KEYPAIR_FIELD: STANDARD.encode(encrypted_keypair) } });
+        if let Some(cas) = cas {
+            body["options"] = json!({ "cas": cas });
+        }
+
+        let url = self.address.join(&path)?;
+        let response = self
+            .send(|token| {
+                self.client
+                    .post(url.clone())
+                    .header("X-Vault-Token", token)
+                    .json(&body)
+            })
+            .await?;
+        Self::check(response, &path).await?;
+        Ok(())
+    }
+}
+
+impl<Keypair: Encryptable + Send + Sync> AsyncEncryptedKeystore<Keypair>
+    for VaultEncryptedKeystore
+{
+    type Params = VaultKeystoreParams;
+    type StorageError = VaultKeystoreError<Keypair::EncryptionError>;
+
+    async fn store(
+        &self,
+        keypair: &Keypair,
+        passphrase: &str,
+        params: &VaultKeystoreParams,
+    ) -> Result<(), Self::StorageError> {
+        let encrypted_keypair = keypair
+            .encrypt_with_params(passphrase, &self.encryption_params)
+            .map_err(VaultKeystoreError::EncryptionError)?;
+
+        self.write(&encrypted_keypair, params, None).await
+    }
+
+    async fn retrieve(
+        &self,
+        passphrase: &str,
+        params: &VaultKeystoreParams,
+    ) -> Result<Keypair, Self::StorageError> {
+        let (encrypted_keypair, _) = self.read(params).await?;
+        Keypair::decrypt(&encrypted_keypair, passphrase)
+            .map_err(VaultKeystoreError::EncryptionError)
+    }
+
+    // Writes a new version on top of the one that was read, failing if it changed in between.
+    // The previous version stays readable under the old passphrase until it is destroyed, e.g.
+    // with `vault kv destroy -versions=<n>`
+    async fn rekey(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+        params: &VaultKeystoreParams,
+    ) -> Result<(), Self::StorageError> {
+        let (encrypted_keypair, version) = self.read(params).await?;
+        let keypair = Keypair::decrypt(&encrypted_keypair, old_passphrase)
+            .map_err(VaultKeystoreError::EncryptionError)?;
+        let encrypted_keypair = keypair
+            .encrypt_with_params(new_passphrase, new_params)
+            .map_err(VaultKeystoreError::EncryptionError)?;
+
+        self.write(&encrypted_keypair, params, Some(version)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use axum::{
+        extract::{Path, Query, State},
+        http::HeaderMap,
+        routing::{get, post},
+        Json, Router,
+    };
+    use serde_json::Value;
+
+    use crate::{
+        encryption::KdfParams,
+        keypair::{bn254, traits::Keypair as _},
+        test_utils::{spawn_mock, LIGHT_KDF},
+    };
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Vault {
+        secrets: Arc<Mutex<HashMap<String, Vec<Value>>>>,
+        logins: Arc<Mutex<usize>>,
+        /// Rejects the AppRole token until the next login
+        expired: Arc<Mutex<bool>>,
+    }
+
+    fn error(status: StatusCode, message: &str) -> (StatusCode, Json<Value>) {
+        (status, Json(json!({ "errors": [message] })))
+    }
+
+    fn authorized(vault: &Vault, headers: &HeaderMap) -> Result<(), (StatusCode, Json<Value>)> {
+        let expired = *vault.expired.lock().unwrap();
+        match headers.get("x-vault-token") {
+            Some(value) if value == "root" || (value == "approle-token" && !expired) => Ok(()),
+            _ => Err(error(StatusCode::FORBIDDEN, "permission denied")),
+        }
+    }
+
+    async fn login(
+        State(vault): State<Vault>,
+        Json(body): Json<Value>,
+    ) -> (StatusCode, Json<Value>) {
+        if body["role_id"] != "role" || body["secret_id"] != "secret" {
+            return error(StatusCode::BAD_REQUEST, "invalid role or secret ID");
+        }
+        *vault.logins.lock().unwrap() += 1;
+        *vault.expired.lock().unwrap() = false;
+        (
+            StatusCode::OK,
+            Json(json!({ "auth": { "client_token": "approle-token" } })),
+        )
+    }
+
+    async fn write(
+        State(vault): State<Vault>,
+        Path((mount, path)): Path<(String, String)>,
+        headers: HeaderMap,
+        Json(body): Json<Value>,
+    ) -> (StatusCode, Json<Value>) {
+        if let Err(err) = authorized(&vault, &headers) {
+            return err;
+        }
+        let mut secrets = vault.secrets.lock().unwrap();
+        let versions = secrets.entry(format!("{mount}/{path}")).or_default();
+        if let Some(cas) = body["options"]["cas"].as_u64() {
+            if cas != versions.len() as u64 {
+                return error(
+                    StatusCode::BAD_REQUEST,
+                    "check-and-set parameter did not match the current version",
+                );
+            }
+        }
+        versions.push(body["data"].clone());
+        (
+            StatusCode::OK,
+            Json(json!({ "data": { "version": versions.len() } })),
+        )
+    }
+
+    async fn read(
+        State(vault): State<Vault>,
+        Path((mount, path)): Path<(String, String)>,
+        Query(query): Query<HashMap<String, u64>>,
+        headers: HeaderMap,
+    ) -> (StatusCode, Json<Value>) {
+        if let Err(err) = authorized(&vault, &headers) {
+            return err;
+        }
+        let secrets = vault.secrets.lock().unwrap();
+        let Some(versions) = secrets.get(&format!("{mount}/{path}")) else {
+            return (StatusCode::NOT_FOUND, Json(json!({ "errors": [] })));
+        };
+        let version = query
+            .get("version")
+            .copied()
+            .unwrap_or(versions.len() as u64);
+        match versions.get((version as usize).wrapping_sub(1)) {
+            Some(data) => (
+                StatusCode::OK,
+                Json(json!({ "data": { "data": data, "metadata": { "version": version } } })),
+            ),
+            None => (StatusCode::NOT_FOUND, Json(json!({ "errors": [] }))),
+        }
+    }
+
+    async fn vault() -> (Url, Vault) {
+        let vault = Vault::default();
+        let app = Router::new()
+            .route("/v1/auth/approle/login", post(login))
+            .route("/v1/:mount/data/*path", get(read).post(write))
+            .with_state(vault.clone());
+
+        (spawn_mock(app).await, vault)
+    }
+
+    #[tokio::test]
+    async fn test_store_retrieve_and_rekey() {
+        let (url, vault) = vault().await;
+        let keystore = VaultEncryptedKeystore::new(url, VaultAuth::approle("role", "secret"))
+            .with_encryption_params(LIGHT_KDF.into());
+        let params = VaultKeystoreParams::new(DEFAULT_KV_MOUNT, "karak/operator.bls");
+        let keypair = bn254::Keypair::generate();
+
+        keystore
+            .store(&keypair, "passphrase", &params)
+            .await
+            .unwrap();
+        let retrieved: bn254::Keypair = keystore.retrieve("passphrase", &params).await.unwrap();
+        assert_eq!(retrieved.public_key(), keypair.public_key());
+
+        AsyncEncryptedKeystore::<bn254::Keypair>::rekey(
+            &keystore,
+            "passphrase",
+            "new passphrase",
+            &KdfParams::Pbkdf2 { iterations: 2_000 }.into(),
+            &params,
+        )
+        .await
+        .unwrap();
+        let retrieved: bn254::Keypair = keystore.retrieve("new passphrase", &params).await.unwrap();
+        assert_eq!(retrieved.public_key(), keypair.public_key());
+
+        let first_version = VaultKeystoreParams {
+            version: Some(1),
+            ..VaultKeystoreParams::new(DEFAULT_KV_MOUNT, "karak/operator.bls")
+        };
+        let retrieved: bn254::Keypair = keystore
+            .retrieve("passphrase", &first_version)
+            .await
+            .unwrap();
+        assert_eq!(retrieved.public_key(), keypair.public_key());
+
+        assert_eq!(*vault.logins.lock().unwrap(), 1);
+        assert_eq!(
+            vault.secrets.lock().unwrap()["secret/karak/operator.bls"].len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_logs_in_again_when_token_expires() {
+        let (url, vault) = vault().await;
+        let keystore = VaultEncryptedKeystore::new(url, VaultAuth::approle("role", "secret"))
+            .with_encryption_params(LIGHT_KDF.into());
+        let params = VaultKeystoreParams::new(DEFAULT_KV_MOUNT, "karak/operator.bls");
+        let keypair = bn254::Keypair::generate();
+
+        keystore
+            .store(&keypair, "passphrase", &params)
+            .await
+            .unwrap();
+        *vault.expired.lock().unwrap() = true;
+        let retrieved: bn254::Keypair = keystore.retrieve("passphrase", &params).await.unwrap();
+        assert_eq!(retrieved.public_key(), keypair.public_key());
+        assert_eq!(*vault.logins.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let (url, _) = vault().await;
+        let keystore = VaultEncryptedKeystore::new(url.clone(), VaultAuth::Token("root".into()))
+            .with_encryption_params(LIGHT_KDF.into());
+
+        let result: Result<bn254::Keypair, _> = keystore
+            .retrieve("passphrase", &VaultKeystoreParams::new("secret", "missing"))
+            .await;
+        assert!(matches!(result, Err(VaultKeystoreError::SecretNotFound(_))));
+
+        let forbidden =
+            VaultEncryptedKeystore::new(url.clone(), VaultAuth::Token("expired".into()))
+                .with_encryption_params(LIGHT_KDF.into());
+        let result = forbidden
+            .store(
+                &bn254::Keypair::generate(),
+                "passphrase",
+                &VaultKeystoreParams::new("secret", "operator"),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(VaultKeystoreError::ApiError { status: StatusCode::FORBIDDEN, message }) if message == "permission denied"
+        ));
+
+        let bad_login = VaultEncryptedKeystore::new(url, VaultAuth::approle("role", "wrong"))
+            .with_encryption_params(LIGHT_KDF.into());
+        let result: Result<bn254::Keypair, _> = bad_login
+            .retrieve(
+                "passphrase",
+                &VaultKeystoreParams::new("secret", "operator"),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(VaultKeystoreError::ApiError {
+                status: StatusCode::BAD_REQUEST,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_auth_debug_redacts_credentials() {
+        let token = format!("{:?}", VaultAuth::Token("s.token".to_string()));
+        assert_eq!(token, "Token(<redacted>)");
+
+        let approle = format!("{:?}", VaultAuth::approle("role", "secret"));
+        assert!(approle.contains("role_id: \"role\""));
+        assert!(approle.contains("secret_id: <redacted>"));
+        assert!(!approle.contains("\"secret\""));
+    }
+}
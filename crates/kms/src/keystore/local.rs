@@ -1,12 +1,20 @@
 use std::{
-    fs::File,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
 };
 
-use crate::keypair::traits::Encryptable;
+use crate::{
+    encryption::{Cipher, EncryptionParams, KdfParams},
+    keypair::traits::Encryptable,
+};
 
-use super::traits::EncryptedKeystore;
+use super::{
+    eip2335::{Eip2335Error, Eip2335Keypair, Eip2335Keystore},
+    traits::{self, AsyncEncryptedKeystore},
+};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -19,45 +27,533 @@ pub enum LocalKeystoreError<Keypair: Encryptable + Send + Sync> {
 
     #[error("Decoding error: {0}")]
     DecodingError(#[from] bs58::decode::Error),
+
+    #[error("EIP-2335 keystore error: {0}")]
+    Eip2335Error(#[from] Eip2335Error),
+
+    #[error("EIP-2335 keystores always use AES-128-CTR, {0:?} is not supported")]
+    UnsupportedCipher(Cipher),
 }
 
+/// The on-disk encoding of a local keystore
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LocalKeystoreFormat {
+    /// bs58 encoded bincode of the `Encryptable` payload
+    #[default]
+    Legacy,
+    /// EIP-2335 JSON
+    Eip2335,
+}
+
+impl LocalKeystoreFormat {
+    /// JSON always starts with `{`, which is not in the bs58 alphabet
+    pub fn detect(contents: &[u8]) -> Self {
+        match contents.iter().find(|byte| !byte.is_ascii_whitespace()) {
+            Some(b'{') => LocalKeystoreFormat::Eip2335,
+            _ => LocalKeystoreFormat::Legacy,
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct LocalEncryptedKeystore {
     file_path: PathBuf,
+    format: LocalKeystoreFormat,
+    encryption_params: EncryptionParams,
 }
 
 impl LocalEncryptedKeystore {
     pub fn new(file_path: PathBuf) -> Self {
-        Self { file_path }
+        Self {
+            file_path,
+            format: LocalKeystoreFormat::default(),
+            encryption_params: EncryptionParams::default(),
+        }
+    }
+
+    /// Sets the format keypairs are stored in, retrieval detects the format of the file
+    pub fn with_format(mut self, format: LocalKeystoreFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Sets the KDF used when storing, EIP-2335 keystores do not support Argon2id
+    pub fn with_kdf_params(mut self, kdf_params: KdfParams) -> Self {
+        self.encryption_params.kdf = kdf_params;
+        self
+    }
+
+    /// Sets the cipher of legacy format keystores, EIP-2335 keystores always use AES-128-CTR
+    pub fn with_cipher(mut self, cipher: Cipher) -> Self {
+        self.encryption_params.cipher = Some(cipher);
+        self
     }
 }
 
-impl<Keypair: Encryptable + Send + Sync + std::fmt::Debug> EncryptedKeystore<Keypair>
-    for LocalEncryptedKeystore
+impl LocalEncryptedKeystore {
+    fn read(&self) -> Result<Vec<u8>, std::io::Error> {
+        let mut file = File::open(&self.file_path)?;
+        let mut buf = vec![];
+        file.read_to_end(&mut buf)?;
+        Ok(buf)
+    }
+
+    /// Writes to a temporary file next to the keystore and renames it over the keystore, so that
+    /// a failed write never leaves a truncated keystore behind
+    fn write_atomically(&self, contents: &[u8]) -> Result<(), std::io::Error> {
+        static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        let file_name = self
+            .file_path
+            .file_name()
+            .ok_or_else(|| std::io::Error::other("Keystore path has no file name"))?;
+        // Unique per write, so concurrent writers never share a temporary file
+        let mut tmp_name = OsString::from(".");
+        tmp_name.push(file_name);
+        tmp_name.push(format!(
+            ".{}.{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let tmp_path = self.file_path.with_file_name(tmp_name);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let result = options
+            .open(&tmp_path)
+            .and_then(|mut file| {
+                file.write_all(contents)?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&tmp_path, &self.file_path));
+        if result.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        result?;
+
+        sync_dir(self.file_path.parent())
+    }
+}
+
+// Persists the rename, a no-op where directories cannot be opened
+fn sync_dir(dir: Option<&Path>) -> Result<(), std::io::Error> {
+    #[cfg(unix)]
+    {
+        let dir = match dir {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+fn encode<Keypair: Encryptable + Eip2335Keypair + Send + Sync>(
+    keypair: &Keypair,
+    passphrase: &str,
+    format: LocalKeystoreFormat,
+    params: &EncryptionParams,
+) -> Result<Vec<u8>, LocalKeystoreError<Keypair>> {
+    Ok(match format {
+        LocalKeystoreFormat::Legacy => {
+            let encrypted_keypair = keypair
+                .encrypt_with_params(passphrase, params)
+                // TODO: Handle this error better. There has to be a more idiomatic way. cc @johanan
+                .map_err(|err| LocalKeystoreError::EncryptionError(err))?;
+            bs58::encode(&encrypted_keypair).into_vec()
+        }
+        LocalKeystoreFormat::Eip2335 => {
+            check_eip2335_cipher(params)?;
+            Eip2335Keystore::encrypt(keypair, passphrase, "", &params.kdf)?
+                .to_json()?
+                .into_bytes()
+        }
+    })
+}
+
+fn check_eip2335_cipher<Keypair: Encryptable + Send + Sync>(
+    params: &EncryptionParams,
+) -> Result<(), LocalKeystoreError<Keypair>> {
+    match params.cipher {
+        Some(cipher) => Err(LocalKeystoreError::UnsupportedCipher(cipher)),
+        None => Ok(()),
+    }
+}
+
+fn decode<Keypair: Encryptable + Eip2335Keypair + Send + Sync>(
+    contents: &[u8],
+    passphrase: &str,
+) -> Result<Keypair, LocalKeystoreError<Keypair>>
+where
+    Keypair::KeypairError: 'static,
+{
+    match LocalKeystoreFormat::detect(contents) {
+        LocalKeystoreFormat::Eip2335 => {
+            Ok(Eip2335Keystore::from_json(contents)?.decrypt(passphrase)?)
+        }
+        LocalKeystoreFormat::Legacy => {
+            let encrypted_keypair = bs58::decode(contents).into_vec()?;
+
+            Keypair::decrypt(&encrypted_keypair, passphrase)
+                // TODO: Handle this error better. There has to be a more idiomatic way. cc @johanan
+                .map_err(|err| LocalKeystoreError::EncryptionError(err))
+        }
+    }
+}
+
+impl LocalEncryptedKeystore {
+    fn store_blocking<Keypair: Encryptable + Eip2335Keypair + Send + Sync>(
+        &self,
+        keypair: &Keypair,
+        passphrase: &str,
+    ) -> Result<(), LocalKeystoreError<Keypair>> {
+        let contents = encode(keypair, passphrase, self.format, &self.encryption_params)?;
+        Ok(self.write_atomically(&contents)?)
+    }
+
+    fn retrieve_blocking<Keypair: Encryptable + Eip2335Keypair + Send + Sync>(
+        &self,
+        passphrase: &str,
+    ) -> Result<Keypair, LocalKeystoreError<Keypair>>
+    where
+        Keypair::KeypairError: 'static,
+    {
+        decode(&self.read()?, passphrase)
+    }
+
+    // The keystore keeps the format it is in, EIP-2335 keystores keep their path, uuid and pubkey
+    // and cannot change cipher
+    fn rekey_blocking<Keypair: Encryptable + Eip2335Keypair + Send + Sync>(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+    ) -> Result<(), LocalKeystoreError<Keypair>>
+    where
+        Keypair::KeypairError: 'static,
+    {
+        let contents = self.read()?;
+
+        let contents = match LocalKeystoreFormat::detect(&contents) {
+            LocalKeystoreFormat::Eip2335 => {
+                check_eip2335_cipher(new_params)?;
+                Eip2335Keystore::from_json(&contents)?
+                    .rekey::<Keypair>(old_passphrase, new_passphrase, &new_params.kdf)?
+                    .to_json()?
+                    .into_bytes()
+            }
+            format @ LocalKeystoreFormat::Legacy => {
+                let keypair: Keypair = decode(&contents, old_passphrase)?;
+                encode(&keypair, new_passphrase, format, new_params)?
+            }
+        };
+        Ok(self.write_atomically(&contents)?)
+    }
+}
+
+/// Runs the file IO and the KDF on the blocking thread pool
+async fn spawn_blocking<T, Keypair>(
+    task: impl FnOnce() -> Result<T, LocalKeystoreError<Keypair>> + Send + 'static,
+) -> Result<T, LocalKeystoreError<Keypair>>
+where
+    T: Send + 'static,
+    Keypair: Encryptable + Send + Sync + 'static,
+    LocalKeystoreError<Keypair>: Send,
+{
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|err| LocalKeystoreError::IoError(std::io::Error::other(err)))?
+}
+
+#[allow(deprecated)]
+impl<Keypair: Encryptable + Eip2335Keypair + Send + Sync + std::fmt::Debug>
+    traits::EncryptedKeystore<Keypair> for LocalEncryptedKeystore
+where
+    Keypair::KeypairError: 'static,
 {
     type StorageError = LocalKeystoreError<Keypair>;
 
     fn store(&self, keypair: &Keypair, passphrase: &str) -> Result<(), Self::StorageError> {
-        let encrypted_keypair = keypair
-            .encrypt(passphrase)
-            // TODO: Handle this error better. There has to be a more idiomatic way. cc @johanan
-            .map_err(|err| LocalKeystoreError::EncryptionError(err))?;
+        self.store_blocking(keypair, passphrase)
+    }
 
-        let mut file = File::create(&self.file_path)?;
+    fn retrieve(&self, passphrase: &str) -> Result<Keypair, Self::StorageError> {
+        self.retrieve_blocking(passphrase)
+    }
 
-        file.write_all(bs58::encode(&encrypted_keypair).into_vec().as_slice())?;
+    fn rekey(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+    ) -> Result<(), Self::StorageError> {
+        self.rekey_blocking(old_passphrase, new_passphrase, new_params)
+    }
+}
 
-        Ok(())
+/// The keystore is its own file, so there are no params to locate the keypair with
+impl<Keypair> AsyncEncryptedKeystore<Keypair> for LocalEncryptedKeystore
+where
+    Keypair: Encryptable + Eip2335Keypair + Clone + Send + Sync + std::fmt::Debug + 'static,
+    Keypair::KeypairError: 'static,
+{
+    type Params = ();
+    type StorageError = LocalKeystoreError<Keypair>;
+
+    async fn store(
+        &self,
+        keypair: &Keypair,
+        passphrase: &str,
+        _: &(),
+    ) -> Result<(), Self::StorageError> {
+        let (keystore, keypair, passphrase) =
+            (self.clone(), keypair.clone(), passphrase.to_string());
+        spawn_blocking(move || keystore.store_blocking(&keypair, &passphrase)).await
     }
 
-    fn retrieve(&self, passphrase: &str) -> Result<Keypair, Self::StorageError> {
-        let mut file = File::open(&self.file_path)?;
-        let mut buf = vec![];
-        file.read_to_end(&mut buf)?;
+    async fn retrieve(&self, passphrase: &str, _: &()) -> Result<Keypair, Self::StorageError> {
+        let (keystore, passphrase) = (self.clone(), passphrase.to_string());
+        spawn_blocking(move || keystore.retrieve_blocking(&passphrase)).await
+    }
+
+    async fn rekey(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+        _: &(),
+    ) -> Result<(), Self::StorageError> {
+        let (keystore, old_passphrase, new_passphrase, new_params) = (
+            self.clone(),
+            old_passphrase.to_string(),
+            new_passphrase.to_string(),
+            *new_params,
+        );
+        spawn_blocking(move || {
+            keystore.rekey_blocking::<Keypair>(&old_passphrase, &new_passphrase, &new_params)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encryption::EncryptedPayload,
+        keypair::{bn254, traits::Keypair as _},
+    };
+
+    use super::*;
+
+    fn keystore_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("karak-kms-{}-{name}", std::process::id()))
+    }
+
+    #[tokio::test]
+    async fn test_store_and_retrieve_both_formats() {
+        let keypair = bn254::Keypair::generate();
+
+        for (format, name) in [
+            (LocalKeystoreFormat::Legacy, "legacy.bls"),
+            (LocalKeystoreFormat::Eip2335, "eip2335.json"),
+        ] {
+            let path = keystore_path(name);
+            let keystore = LocalEncryptedKeystore::new(path.clone())
+                .with_format(format)
+                .with_kdf_params(KdfParams::Pbkdf2 { iterations: 1_000 });
+            keystore.store(&keypair, "passphrase", &()).await.unwrap();
+
+            let contents = std::fs::read(&path).unwrap();
+            assert_eq!(LocalKeystoreFormat::detect(&contents), format);
+
+            // A keystore configured for the other format still reads the file
+            let retrieved: bn254::Keypair = LocalEncryptedKeystore::new(path.clone())
+                .retrieve("passphrase", &())
+                .await
+                .unwrap();
+            assert_eq!(retrieved.public_key(), keypair.public_key());
+
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rekey() {
+        let keypair = bn254::Keypair::generate();
+        let kdf = KdfParams::Pbkdf2 { iterations: 2_000 };
+
+        for (format, name, cipher) in [
+            (
+                LocalKeystoreFormat::Legacy,
+                "rekey.bls",
+                Some(Cipher::XChaCha20Poly1305),
+            ),
+            (LocalKeystoreFormat::Eip2335, "rekey.json", None),
+        ] {
+            let new_params = EncryptionParams { kdf, cipher };
+            let path = keystore_path(name);
+            let keystore = LocalEncryptedKeystore::new(path.clone())
+                .with_format(format)
+                .with_kdf_params(KdfParams::Pbkdf2 { iterations: 1_000 });
+            keystore
+                .store(&keypair, "old passphrase", &())
+                .await
+                .unwrap();
+
+            AsyncEncryptedKeystore::<bn254::Keypair>::rekey(
+                &keystore,
+                "old passphrase",
+                "new passphrase",
+                &new_params,
+                &(),
+            )
+            .await
+            .unwrap();
+
+            let contents = std::fs::read(&path).unwrap();
+            assert_eq!(LocalKeystoreFormat::detect(&contents), format);
+            if format == LocalKeystoreFormat::Legacy {
+                let payload =
+                    EncryptedPayload::from_bytes(&bs58::decode(&contents).into_vec().unwrap())
+                        .unwrap();
+                assert!(
+                    matches!(payload, EncryptedPayload::V4(payload) if payload.kdf == kdf && Some(payload.cipher) == cipher)
+                );
+            }
+
+            let retrieved: bn254::Keypair = keystore.retrieve("new passphrase", &()).await.unwrap();
+            assert_eq!(retrieved.public_key(), keypair.public_key());
+            let result: Result<bn254::Keypair, _> = keystore.retrieve("old passphrase", &()).await;
+            assert!(result.is_err());
+
+            // A wrong passphrase leaves the keystore untouched
+            assert!(AsyncEncryptedKeystore::<bn254::Keypair>::rekey(
+                &keystore,
+                "old passphrase",
+                "other passphrase",
+                &new_params,
+                &(),
+            )
+            .await
+            .is_err());
+            assert_eq!(std::fs::read(&path).unwrap(), contents);
+
+            let tmp_files = std::fs::read_dir(std::env::temp_dir())
+                .unwrap()
+                .filter(|entry| {
+                    entry
+                        .as_ref()
+                        .unwrap()
+                        .file_name()
+                        .to_string_lossy()
+                        .starts_with(&format!(
+                            ".{}.",
+                            path.file_name().unwrap().to_string_lossy()
+                        ))
+                })
+                .count();
+            assert_eq!(tmp_files, 0);
+
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rekey_eip2335_keeps_metadata() {
+        let keypair = bn254::Keypair::generate();
+        let path = keystore_path("rekey-metadata.json");
+        let original = Eip2335Keystore::encrypt(
+            &keypair,
+            "old passphrase",
+            "m/2333/0/0",
+            &KdfParams::Pbkdf2 { iterations: 1_000 },
+        )
+        .unwrap();
+        std::fs::write(&path, original.to_json().unwrap()).unwrap();
+        let keystore = LocalEncryptedKeystore::new(path.clone());
+
+        let result = AsyncEncryptedKeystore::<bn254::Keypair>::rekey(
+            &keystore,
+            "old passphrase",
+            "new passphrase",
+            &EncryptionParams {
+                kdf: KdfParams::Pbkdf2 { iterations: 2_000 },
+                cipher: Some(Cipher::Aes256Gcm),
+            },
+            &(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(LocalKeystoreError::UnsupportedCipher(Cipher::Aes256Gcm))
+        ));
+
+        AsyncEncryptedKeystore::<bn254::Keypair>::rekey(
+            &keystore,
+            "old passphrase",
+            "new passphrase",
+            &KdfParams::Pbkdf2 { iterations: 2_000 }.into(),
+            &(),
+        )
+        .await
+        .unwrap();
+
+        let rekeyed = Eip2335Keystore::from_json(std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(rekeyed.path, original.path);
+        assert_eq!(rekeyed.uuid, original.uuid);
+        assert_eq!(rekeyed.pubkey, original.pubkey);
+        assert_ne!(rekeyed.crypto, original.crypto);
+        let retrieved: bn254::Keypair = rekeyed.decrypt("new passphrase").unwrap();
+        assert_eq!(retrieved.public_key(), keypair.public_key());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_sync_rekey() {
+        use traits::EncryptedKeystore;
+
+        let keypair = bn254::Keypair::generate();
+        let path = keystore_path("sync-rekey.bls");
+        let keystore = LocalEncryptedKeystore::new(path.clone())
+            .with_kdf_params(KdfParams::Pbkdf2 { iterations: 1_000 });
+        EncryptedKeystore::store(&keystore, &keypair, "old passphrase").unwrap();
+
+        EncryptedKeystore::<bn254::Keypair>::rekey(
+            &keystore,
+            "old passphrase",
+            "new passphrase",
+            &KdfParams::Pbkdf2 { iterations: 1_000 }.into(),
+        )
+        .unwrap();
+        let retrieved: bn254::Keypair =
+            EncryptedKeystore::retrieve(&keystore, "new passphrase").unwrap();
+        assert_eq!(retrieved.public_key(), keypair.public_key());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_store_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = keystore_path("permissions.bls");
+        LocalEncryptedKeystore::new(path.clone())
+            .with_kdf_params(KdfParams::Pbkdf2 { iterations: 1_000 })
+            .store(&bn254::Keypair::generate(), "passphrase", &())
+            .await
+            .unwrap();
 
-        let encrypted_keypair = bs58::decode(buf).into_vec()?;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
 
-        Keypair::decrypt(&encrypted_keypair, passphrase)
-            // TODO: Handle this error better. There has to be a more idiomatic way. cc @johanan
-            .map_err(|err| LocalKeystoreError::EncryptionError(err))
+        std::fs::remove_file(path).unwrap();
     }
 }
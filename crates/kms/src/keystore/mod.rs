@@ -1,4 +1,8 @@
 pub mod aws;
+pub mod dynamic;
+pub mod eip2335;
 pub mod gcp;
+pub mod hashicorp;
 pub mod local;
 pub mod traits;
+pub mod uri;
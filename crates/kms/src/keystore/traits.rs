@@ -1,27 +1,51 @@
-use crate::keypair::traits::Encryptable;
-use std::{error::Error, future::Future};
+use crate::{encryption::EncryptionParams, keypair::traits::Encryptable};
+use std::error::Error;
 
+/// A synchronous keystore bound to a single keypair, only the local keystore implements it
+#[deprecated(note = "use `AsyncEncryptedKeystore`, which every backend implements")]
 pub trait EncryptedKeystore<Keypair: Encryptable> {
     type StorageError: Error;
 
     fn store(&self, keypair: &Keypair, passphrase: &str) -> Result<(), Self::StorageError>;
     fn retrieve(&self, passphrase: &str) -> Result<Keypair, Self::StorageError>;
+    /// Re-encrypts the stored keypair under a new passphrase and parameters, without changing it
+    fn rekey(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+    ) -> Result<(), Self::StorageError>;
 }
 
-// TODO: We should add the other params to the sync trait as well (this would require a small refactor of the local keystore)
-pub trait AsyncEncryptedKeystore<Keypair: Encryptable + Send + Sync, OtherParams: Send + Sync> {
+/// A keystore backend, `Params` locates the keypair within it
+#[trait_variant::make(Send)]
+pub trait AsyncEncryptedKeystore<Keypair: Encryptable + Send + Sync> {
+    type Params: Send + Sync;
     type StorageError: Error + Send + Sync;
 
-    fn store(
+    async fn store(
         &self,
         keypair: &Keypair,
         passphrase: &str,
-        other_params: &OtherParams,
-    ) -> impl Future<Output = Result<(), Self::StorageError>>;
+        params: &Self::Params,
+    ) -> Result<(), Self::StorageError>;
 
-    fn retrieve(
+    async fn retrieve(
         &self,
         passphrase: &str,
-        other_params: &OtherParams,
-    ) -> impl Future<Output = Result<Keypair, Self::StorageError>>;
+        params: &Self::Params,
+    ) -> Result<Keypair, Self::StorageError>;
+
+    /// Re-encrypts the stored keypair under a new passphrase and parameters, without changing it
+    ///
+    /// Backends that version their secrets write the result as a new version. Earlier versions are
+    /// left in place and can still be decrypted with the old passphrase, so after rotating a
+    /// compromised passphrase they have to be destroyed with the backend's own tooling.
+    async fn rekey(
+        &self,
+        old_passphrase: &str,
+        new_passphrase: &str,
+        new_params: &EncryptionParams,
+        params: &Self::Params,
+    ) -> Result<(), Self::StorageError>;
 }
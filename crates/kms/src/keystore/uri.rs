@@ -0,0 +1,390 @@
+use std::{fmt, path::PathBuf, str::FromStr};
+
+use thiserror::Error;
+use url::Url;
+
+use crate::keypair::traits::Encryptable;
+
+use super::{
+    aws::{AwsEncryptedKeystore, AwsKeystoreParams},
+    dynamic::DynKeystore,
+    eip2335::Eip2335Keypair,
+    gcp::{self, GcpEncrypedKeystore, GcpKeystoreParams},
+    hashicorp::{VaultAuth, VaultEncryptedKeystore, VaultKeystoreParams},
+    local::LocalEncryptedKeystore,
+};
+
+#[derive(Debug, Error)]
+pub enum KeystoreUriError {
+    #[error("Keystore URI {0} has no scheme, e.g. file:// or aws-sm://")]
+    MissingScheme(String),
+    #[error("Unsupported keystore URI scheme {0}")]
+    UnsupportedScheme(String),
+    #[error("Invalid keystore URI {uri}: {reason}")]
+    Invalid { uri: String, reason: &'static str },
+    #[error("Invalid URL: {0}")]
+    UrlError(#[from] url::ParseError),
+    #[error("Failed to get a GCP access token: {0}")]
+    GcpAuthError(std::io::Error),
+    #[error("No Vault credentials, set VAULT_TOKEN, or VAULT_ROLE_ID and VAULT_SECRET_ID")]
+    MissingVaultCredentials,
+}
+
+/// The location of a keypair in any keystore backend
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeystoreUri {
+    /// `file://<path>`, the path may be relative
+    Local { path: PathBuf },
+    /// `aws-sm://<profile>/<secret name>[?version=<version id>]`
+    Aws {
+        profile: String,
+        secret_name: String,
+        version_id: Option<String>,
+    },
+    /// `gcp-sm://<project>/<secret>[?version=<version>]`
+    Gcp {
+        project_id: String,
+        secret_id: String,
+        version: Option<String>,
+    },
+    /// `vault://<host>[:<port>]/<mount>/<path>[?version=<version>]` over HTTPS, or
+    /// `vault+http://` for a server without TLS such as the dev server
+    Vault {
+        address: Url,
+        mount: String,
+        path: String,
+        version: Option<u64>,
+    },
+}
+
+pub const FILE_SCHEME: &str = "file";
+pub const AWS_SCHEME: &str = "aws-sm";
+pub const GCP_SCHEME: &str = "gcp-sm";
+pub const VAULT_SCHEME: &str = "vault";
+pub const VAULT_HTTP_SCHEME: &str = "vault+http";
+
+/// Splits at the first `/`, both parts must be non-empty
+fn split_first(location: &str) -> Option<(&str, &str)> {
+    match location.split_once('/') {
+        Some((first, rest)) if !first.is_empty() && !rest.is_empty() => Some((first, rest)),
+        _ => None,
+    }
+}
+
+fn version(query: Option<&str>) -> Result<Option<&str>, &'static str> {
+    match query {
+        None => Ok(None),
+        Some(query) => match query.strip_prefix("version=") {
+            Some(version) if !version.is_empty() => Ok(Some(version)),
+            _ => Err("the only supported query is version=<version>"),
+        },
+    }
+}
+
+impl FromStr for KeystoreUri {
+    type Err = KeystoreUriError;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason| KeystoreUriError::Invalid {
+            uri: uri.to_string(),
+            reason,
+        };
+
+        let (scheme, rest) = uri
+            .split_once("://")
+            .ok_or_else(|| KeystoreUriError::MissingScheme(uri.to_string()))?;
+        let (location, query) = match rest.split_once('?') {
+            Some((location, query)) => (location, Some(query)),
+            None => (rest, None),
+        };
+
+        match scheme {
+            // Paths are taken as is, they may contain `?`
+            FILE_SCHEME if rest.is_empty() => Err(invalid("missing path")),
+            FILE_SCHEME => Ok(KeystoreUri::Local { path: rest.into() }),
+            AWS_SCHEME => {
+                let (profile, secret_name) =
+                    split_first(location).ok_or_else(|| invalid("expected <profile>/<secret>"))?;
+                Ok(KeystoreUri::Aws {
+                    profile: profile.to_string(),
+                    secret_name: secret_name.to_string(),
+                    version_id: version(query).map_err(invalid)?.map(String::from),
+                })
+            }
+            GCP_SCHEME => {
+                let (project_id, secret_id) =
+                    split_first(location).ok_or_else(|| invalid("expected <project>/<secret>"))?;
+                Ok(KeystoreUri::Gcp {
+                    project_id: project_id.to_string(),
+                    secret_id: secret_id.to_string(),
+                    version: version(query).map_err(invalid)?.map(String::from),
+                })
+            }
+            VAULT_SCHEME | VAULT_HTTP_SCHEME => {
+                let (host, secret) = split_first(location)
+                    .ok_or_else(|| invalid("expected <host>/<mount>/<path>"))?;
+                let (mount, path) =
+                    split_first(secret).ok_or_else(|| invalid("expected <host>/<mount>/<path>"))?;
+                let http_scheme = match scheme {
+                    VAULT_HTTP_SCHEME => "http",
+                    _ => "https",
+                };
+                let version = version(query)
+                    .map_err(invalid)?
+                    .map(|version| version.parse())
+                    .transpose()
+                    .map_err(|_| invalid("Vault versions are integers"))?;
+
+                Ok(KeystoreUri::Vault {
+                    address: Url::parse(&format!("{http_scheme}://{host}/"))?,
+                    mount: mount.to_string(),
+                    path: path.to_string(),
+                    version,
+                })
+            }
+            _ => Err(KeystoreUriError::UnsupportedScheme(scheme.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for KeystoreUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeystoreUri::Local { path } => write!(f, "{FILE_SCHEME}://{}", path.display()),
+            KeystoreUri::Aws {
+                profile,
+                secret_name,
+                version_id,
+            } => {
+                write!(f, "{AWS_SCHEME}://{profile}/{secret_name}")?;
+                if let Some(version_id) = version_id {
+                    write!(f, "?version={version_id}")?;
+                }
+                Ok(())
+            }
+            KeystoreUri::Gcp {
+                project_id,
+                secret_id,
+                version,
+            } => {
+                write!(f, "{GCP_SCHEME}://{project_id}/{secret_id}")?;
+                if let Some(version) = version {
+                    write!(f, "?version={version}")?;
+                }
+                Ok(())
+            }
+            KeystoreUri::Vault {
+                address,
+                mount,
+                path,
+                version,
+            } => {
+                let scheme = match address.scheme() {
+                    "http" => VAULT_HTTP_SCHEME,
+                    _ => VAULT_SCHEME,
+                };
+                let host = address.host_str().unwrap_or_default();
+                write!(f, "{scheme}://{host}")?;
+                if let Some(port) = address.port() {
+                    write!(f, ":{port}")?;
+                }
+                write!(f, "/{mount}/{path}")?;
+                if let Some(version) = version {
+                    write!(f, "?version={version}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl KeystoreUri {
+    /// Builds the keystore the URI points to, with credentials from the environment: the AWS
+    /// profile, [`gcp::access_token_from_env`] and [`VaultAuth::from_env`]
+    pub async fn resolve<Keypair>(&self) -> Result<DynKeystore<Keypair>, KeystoreUriError>
+    where
+        Keypair: Encryptable + Eip2335Keypair + Clone + Send + Sync + fmt::Debug + 'static,
+    {
+        Ok(match self {
+            KeystoreUri::Local { path } => {
+                DynKeystore::new(LocalEncryptedKeystore::new(path.clone()), ())
+            }
+            KeystoreUri::Aws {
+                profile,
+                secret_name,
+                version_id,
+            } => {
+                let config = aws_config::from_env().profile_name(profile).load().await;
+                DynKeystore::new(
+                    AwsEncryptedKeystore::new(&config),
+                    AwsKeystoreParams {
+                        version_id: version_id.clone(),
+                        ..AwsKeystoreParams::new(secret_name)
+                    },
+                )
+            }
+            KeystoreUri::Gcp {
+                project_id,
+                secret_id,
+                version,
+            } => {
+                // Runs gcloud when no token is set in the environment
+                let access_token = tokio::task::spawn_blocking(gcp::access_token_from_env)
+                    .await
+                    .map_err(|err| KeystoreUriError::GcpAuthError(std::io::Error::other(err)))?
+                    .map_err(KeystoreUriError::GcpAuthError)?;
+                DynKeystore::new(
+                    GcpEncrypedKeystore::new(access_token)
+                        .with_token_source(gcp::access_token_from_env),
+                    GcpKeystoreParams {
+                        version: version.clone(),
+                        ..GcpKeystoreParams::new(project_id, secret_id)
+                    },
+                )
+            }
+            KeystoreUri::Vault {
+                address,
+                mount,
+                path,
+                version,
+            } => {
+                let auth =
+                    VaultAuth::from_env().ok_or(KeystoreUriError::MissingVaultCredentials)?;
+                let mut keystore = VaultEncryptedKeystore::new(address.clone(), auth);
+                if let Ok(namespace) = std::env::var("VAULT_NAMESPACE") {
+                    keystore = keystore.with_namespace(namespace);
+                }
+                DynKeystore::new(
+                    keystore,
+                    VaultKeystoreParams {
+                        version: *version,
+                        ..VaultKeystoreParams::new(mount, path)
+                    },
+                )
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        encryption::KdfParams,
+        keypair::{bn254, traits::Keypair as _},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        for (uri, expected) in [
+            (
+                "file:///home/operator/.karak/operator.bls",
+                KeystoreUri::Local {
+                    path: "/home/operator/.karak/operator.bls".into(),
+                },
+            ),
+            (
+                "file://operator.bls",
+                KeystoreUri::Local {
+                    path: "operator.bls".into(),
+                },
+            ),
+            (
+                "aws-sm://default/karak/operator.bls",
+                KeystoreUri::Aws {
+                    profile: "default".to_string(),
+                    secret_name: "karak/operator.bls".to_string(),
+                    version_id: None,
+                },
+            ),
+            (
+                "aws-sm://prod/operator.ecdsa?version=EXAMPLE1-90ab-cdef-fedc-ba987EXAMPLE",
+                KeystoreUri::Aws {
+                    profile: "prod".to_string(),
+                    secret_name: "operator.ecdsa".to_string(),
+                    version_id: Some("EXAMPLE1-90ab-cdef-fedc-ba987EXAMPLE".to_string()),
+                },
+            ),
+            (
+                "gcp-sm://my-project/operator-bls?version=3",
+                KeystoreUri::Gcp {
+                    project_id: "my-project".to_string(),
+                    secret_id: "operator-bls".to_string(),
+                    version: Some("3".to_string()),
+                },
+            ),
+            (
+                "vault://vault.example.com/secret/karak/operator.bls",
+                KeystoreUri::Vault {
+                    address: Url::parse("https://vault.example.com/").unwrap(),
+                    mount: "secret".to_string(),
+                    path: "karak/operator.bls".to_string(),
+                    version: None,
+                },
+            ),
+            (
+                "vault+http://127.0.0.1:8200/kv/operator?version=2",
+                KeystoreUri::Vault {
+                    address: Url::parse("http://127.0.0.1:8200/").unwrap(),
+                    mount: "kv".to_string(),
+                    path: "operator".to_string(),
+                    version: Some(2),
+                },
+            ),
+        ] {
+            let parsed: KeystoreUri = uri.parse().unwrap();
+            assert_eq!(parsed, expected);
+            assert_eq!(parsed.to_string(), uri);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            "operator.bls".parse::<KeystoreUri>(),
+            Err(KeystoreUriError::MissingScheme(_))
+        ));
+        assert!(matches!(
+            "s3://bucket/operator.bls".parse::<KeystoreUri>(),
+            Err(KeystoreUriError::UnsupportedScheme(scheme)) if scheme == "s3"
+        ));
+        for uri in [
+            "file://",
+            "aws-sm://default",
+            "aws-sm://default/secret?stage=AWSPREVIOUS",
+            "gcp-sm:///secret",
+            "gcp-sm://project/secret?latest",
+            "vault://127.0.0.1:8200/secret",
+            "vault://127.0.0.1:8200/secret/operator?version=latest",
+        ] {
+            assert!(
+                matches!(
+                    uri.parse::<KeystoreUri>(),
+                    Err(KeystoreUriError::Invalid { .. })
+                ),
+                "{uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_local() {
+        let path = std::env::temp_dir().join(format!("karak-kms-{}-uri.bls", std::process::id()));
+        let keypair = bn254::Keypair::generate();
+        let local = LocalEncryptedKeystore::new(path.clone())
+            .with_kdf_params(KdfParams::Pbkdf2 { iterations: 1_000 });
+        DynKeystore::new(local, ())
+            .store(&keypair, "passphrase")
+            .await
+            .unwrap();
+
+        let uri: KeystoreUri = format!("file://{}", path.display()).parse().unwrap();
+        let keystore = uri.resolve::<bn254::Keypair>().await.unwrap();
+        let retrieved = keystore.retrieve("passphrase").await.unwrap();
+        assert_eq!(retrieved.public_key(), keypair.public_key());
+        assert!(keystore.retrieve("wrong passphrase").await.is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
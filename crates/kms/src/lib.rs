@@ -3,3 +3,6 @@ pub mod keypair;
 pub mod keystore;
 pub mod signer;
 pub mod web3;
+
+#[cfg(test)]
+mod test_utils;
@@ -1 +1,3 @@
+pub mod protected;
+pub mod remote;
 pub mod traits;
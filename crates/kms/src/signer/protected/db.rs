@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use alloy::primitives::{Address, B256};
+use fs4::fs_std::FileExt;
+use serde::{Deserialize, Serialize};
+
+use crate::keypair::bn254::PublicKey;
+
+use super::{
+    interchange::{Interchange, InterchangeKey, InterchangeMetadata, INTERCHANGE_FORMAT_VERSION},
+    SlashingProtectionError,
+};
+
+/// A message hash signed for a DSS task
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedTask {
+    pub dss: Address,
+    pub task_id: B256,
+    pub message_hash: B256,
+}
+
+/// One line of the database file
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Record {
+    public_key: PublicKey,
+    #[serde(flatten)]
+    task: SignedTask,
+}
+
+type RecordKey = (PublicKey, Address, B256);
+
+struct State {
+    file: File,
+    /// In the order they were signed, for exports
+    records: Vec<Record>,
+    /// The message hash signed for each task
+    signed: HashMap<RecordKey, B256>,
+}
+
+impl State {
+    /// The message hash already signed for the record's task, if it is a different one
+    fn conflict(&self, record: &Record) -> Option<SlashingProtectionError> {
+        let signed = self.signed.get(&key(record))?;
+        (*signed != record.task.message_hash).then(|| {
+            SlashingProtectionError::ConflictingSignature {
+                dss: record.task.dss,
+                task_id: record.task.task_id,
+                signed: *signed,
+                requested: record.task.message_hash,
+            }
+        })
+    }
+
+    /// Appends the records and syncs them to disk before they are considered signed
+    fn append(&mut self, records: Vec<Record>) -> Result<(), SlashingProtectionError> {
+        let mut lines = Vec::new();
+        for record in &records {
+            serde_json::to_writer(&mut lines, record)?;
+            lines.push(b'\n');
+        }
+        self.file.write_all(&lines)?;
+        self.file.sync_data()?;
+
+        for record in records {
+            self.signed.insert(key(&record), record.task.message_hash);
+            self.records.push(record);
+        }
+        Ok(())
+    }
+}
+
+fn key(record: &Record) -> RecordKey {
+    (
+        record.public_key.clone(),
+        record.task.dss,
+        record.task.task_id,
+    )
+}
+
+/// A durable history of the tasks each BN254 key signed, stored as an append-only file of JSON
+/// lines. A task is never signed with two different message hashes, while signing the same hash
+/// again is allowed
+///
+/// The file is exclusively locked while the database is open, so two signers cannot share it
+/// without seeing each other's records
+pub struct SlashingProtectionDb {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+impl SlashingProtectionDb {
+    /// Opens the database at `path`, creating it if needed. Fails if it is already open
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SlashingProtectionError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        // Released when the file is closed, along with the database
+        if let Err(e) = file.try_lock_exclusive() {
+            if e.kind() == fs4::lock_contended_error().kind() {
+                return Err(SlashingProtectionError::Locked(path));
+            }
+            return Err(e.into());
+        }
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        // A record without its newline was cut short by a crash before it was synced, so its
+        // signature was never released
+        let complete = contents
+            .iter()
+            .rposition(|byte| *byte == b'\n')
+            .map_or(0, |newline| newline + 1);
+        if complete < contents.len() {
+            file.set_len(complete as u64)?;
+        }
+
+        let mut records = Vec::new();
+        let mut signed = HashMap::new();
+        for (index, line) in contents[..complete]
+            .split(|byte| *byte == b'\n')
+            .enumerate()
+        {
+            if line.is_empty() {
+                continue;
+            }
+            let record: Record = serde_json::from_slice(line).map_err(|source| {
+                SlashingProtectionError::CorruptRecord {
+                    line: index + 1,
+                    source,
+                }
+            })?;
+            signed.insert(key(&record), record.task.message_hash);
+            records.push(record);
+        }
+
+        Ok(Self {
+            path,
+            state: Mutex::new(State {
+                file,
+                records,
+                signed,
+            }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records that `public_key` is about to sign the task, unless it already signed a different
+    /// message hash for it
+    pub fn check_and_record(
+        &self,
+        public_key: &PublicKey,
+        task: &SignedTask,
+    ) -> Result<(), SlashingProtectionError> {
+        let record = Record {
+            public_key: public_key.clone(),
+            task: task.clone(),
+        };
+        let mut state = self.lock();
+        if let Some(conflict) = state.conflict(&record) {
+            return Err(conflict);
+        }
+        if state.signed.contains_key(&key(&record)) {
+            return Ok(());
+        }
+        state.append(vec![record])
+    }
+
+    /// The tasks `public_key` signed, in the order they were signed
+    pub fn signed_tasks(&self, public_key: &PublicKey) -> Vec<SignedTask> {
+        self.lock()
+            .records
+            .iter()
+            .filter(|record| &record.public_key == public_key)
+            .map(|record| record.task.clone())
+            .collect()
+    }
+
+    pub fn export(&self) -> Interchange {
+        let state = self.lock();
+        let mut data: Vec<InterchangeKey> = Vec::new();
+        for record in &state.records {
+            match data
+                .iter_mut()
+                .find(|key| key.public_key == record.public_key)
+            {
+                Some(key) => key.signed_tasks.push(record.task.clone()),
+                None => data.push(InterchangeKey {
+                    public_key: record.public_key.clone(),
+                    signed_tasks: vec![record.task.clone()],
+                }),
+            }
+        }
+
+        Interchange {
+            metadata: InterchangeMetadata {
+                interchange_format_version: INTERCHANGE_FORMAT_VERSION.to_string(),
+            },
+            data,
+        }
+    }
+
+    /// Merges another machine's history into this one and returns the number of new records.
+    /// Nothing is imported if any record conflicts with this history or with another record
+    pub fn import(&self, interchange: &Interchange) -> Result<usize, SlashingProtectionError> {
+        let version = &interchange.metadata.interchange_format_version;
+        if version != INTERCHANGE_FORMAT_VERSION {
+            return Err(SlashingProtectionError::UnsupportedInterchangeVersion(
+                version.clone(),
+            ));
+        }
+
+        let mut state = self.lock();
+        let mut new_records: Vec<Record> = Vec::new();
+        let mut new_signed = HashMap::new();
+        for key in &interchange.data {
+            for task in &key.signed_tasks {
+                let record = Record {
+                    public_key: key.public_key.clone(),
+                    task: task.clone(),
+                };
+                if let Some(conflict) = state.conflict(&record) {
+                    return Err(conflict);
+                }
+                if state.signed.contains_key(&self::key(&record)) {
+                    continue;
+                }
+                match new_signed.insert(self::key(&record), task.message_hash) {
+                    None => new_records.push(record),
+                    Some(signed) if signed == task.message_hash => {}
+                    Some(signed) => {
+                        return Err(SlashingProtectionError::ConflictingSignature {
+                            dss: task.dss,
+                            task_id: task.task_id,
+                            signed,
+                            requested: task.message_hash,
+                        })
+                    }
+                }
+            }
+        }
+
+        let imported = new_records.len();
+        if imported > 0 {
+            state.append(new_records)?;
+        }
+        Ok(imported)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        // Records are only added once they are on disk, so a panic elsewhere cannot leave the
+        // state inconsistent
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
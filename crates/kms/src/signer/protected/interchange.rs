@@ -0,0 +1,40 @@
+//! The interchange format moves signing history between machines, modelled on EIP-3076
+//!
+//! ```json
+//! {
+//!   "metadata": { "interchangeFormatVersion": "1" },
+//!   "data": [
+//!     {
+//!       "publicKey": { "g1": ..., "g2": ... },
+//!       "signedTasks": [{ "dss": "0x...", "taskId": "0x...", "messageHash": "0x..." }]
+//!     }
+//!   ]
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use crate::keypair::bn254::PublicKey;
+
+use super::db::SignedTask;
+
+pub const INTERCHANGE_FORMAT_VERSION: &str = "1";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interchange {
+    pub metadata: InterchangeMetadata,
+    pub data: Vec<InterchangeKey>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterchangeMetadata {
+    pub interchange_format_version: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InterchangeKey {
+    pub public_key: PublicKey,
+    pub signed_tasks: Vec<SignedTask>,
+}
@@ -0,0 +1,278 @@
+use std::{path::PathBuf, sync::Arc};
+
+use alloy::primitives::{Address, B256};
+use thiserror::Error;
+
+use crate::keypair::bn254::{bls::signature::Signature, PublicKey};
+
+use super::traits::Bn254Signer;
+
+pub mod db;
+pub mod interchange;
+
+pub use db::{SignedTask, SlashingProtectionDb};
+pub use interchange::Interchange;
+
+#[derive(Debug, Error)]
+pub enum SlashingProtectionError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Serialization error: {0}")]
+    SerializationError(#[from] serde_json::Error),
+    #[error("Corrupt slashing protection record on line {line}: {source}")]
+    CorruptRecord {
+        line: usize,
+        source: serde_json::Error,
+    },
+    #[error(
+        "Refusing to sign {requested} for task {task_id} of DSS {dss}, {signed} was already signed"
+    )]
+    ConflictingSignature {
+        dss: Address,
+        task_id: B256,
+        signed: B256,
+        requested: B256,
+    },
+    #[error("Unsupported interchange format version {0}")]
+    UnsupportedInterchangeVersion(String),
+    #[error("Slashing protection database {0} is already open, possibly by another process")]
+    Locked(PathBuf),
+}
+
+#[derive(Debug, Error)]
+pub enum ProtectedSignerError<E> {
+    #[error("Signer error: {0}")]
+    SignerError(E),
+    #[error(transparent)]
+    SlashingProtectionError(#[from] SlashingProtectionError),
+}
+
+/// Signs DSS task responses with a BN254 signer, refusing to sign two different message hashes
+/// for the same task. Every task is recorded in the [`SlashingProtectionDb`] before it is signed.
+///
+/// The wrapped signer is deliberately not exposed, signing through it bypasses the protection
+pub struct ProtectedSigner<Signer> {
+    signer: Signer,
+    public_key: PublicKey,
+    db: Arc<SlashingProtectionDb>,
+}
+
+impl<Signer: Bn254Signer> ProtectedSigner<Signer> {
+    /// The database may be shared by the signers of several keys
+    pub async fn new(
+        signer: Signer,
+        db: Arc<SlashingProtectionDb>,
+    ) -> Result<Self, ProtectedSignerError<Signer::Error>> {
+        let public_key = signer
+            .get_public_key()
+            .await
+            .map_err(ProtectedSignerError::SignerError)?;
+        Ok(Self {
+            signer,
+            public_key,
+            db,
+        })
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    pub fn db(&self) -> &SlashingProtectionDb {
+        &self.db
+    }
+
+    /// Caller is responsible for ensuring `hash` is a 32-byte hash of the task response
+    pub async fn sign_task(
+        &self,
+        dss: Address,
+        task_id: B256,
+        hash: &[u8; 32],
+    ) -> Result<Signature, ProtectedSignerError<Signer::Error>> {
+        // Recording syncs the database to disk
+        let (db, public_key) = (self.db.clone(), self.public_key.clone());
+        let task = SignedTask {
+            dss,
+            task_id,
+            message_hash: (*hash).into(),
+        };
+        tokio::task::spawn_blocking(move || db.check_and_record(&public_key, &task))
+            .await
+            .map_err(|e| SlashingProtectionError::IoError(std::io::Error::other(e)))??;
+        self.signer
+            .sign_hash(hash)
+            .await
+            .map_err(ProtectedSignerError::SignerError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, path::PathBuf};
+
+    use crate::keypair::{bn254, traits::Keypair as _};
+
+    use super::*;
+
+    fn db_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("karak-kms-{}-{name}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    fn task(task_id: u8, message_hash: u8) -> SignedTask {
+        SignedTask {
+            dss: Address::repeat_byte(0x11),
+            task_id: B256::repeat_byte(task_id),
+            message_hash: B256::repeat_byte(message_hash),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refuses_conflicting_signatures() {
+        let path = db_path("conflicts");
+        let keypair = bn254::Keypair::generate();
+        let db = Arc::new(SlashingProtectionDb::open(&path).unwrap());
+        let signer = ProtectedSigner::new(keypair.clone(), db).await.unwrap();
+        let dss = Address::repeat_byte(0x11);
+
+        let signature = signer
+            .sign_task(dss, B256::repeat_byte(1), &[0xaa; 32])
+            .await
+            .unwrap();
+        assert_eq!(signature, keypair.sign_hash(&[0xaa; 32]).await.unwrap());
+        // Signing the same response again is safe
+        signer
+            .sign_task(dss, B256::repeat_byte(1), &[0xaa; 32])
+            .await
+            .unwrap();
+        assert!(matches!(
+            signer
+                .sign_task(dss, B256::repeat_byte(1), &[0xbb; 32])
+                .await,
+            Err(ProtectedSignerError::SlashingProtectionError(
+                SlashingProtectionError::ConflictingSignature { .. }
+            ))
+        ));
+        // Other tasks, DSSs and keys are independent
+        signer
+            .sign_task(dss, B256::repeat_byte(2), &[0xbb; 32])
+            .await
+            .unwrap();
+        signer
+            .sign_task(
+                Address::repeat_byte(0x22),
+                B256::repeat_byte(1),
+                &[0xbb; 32],
+            )
+            .await
+            .unwrap();
+        let other = ProtectedSigner::new(bn254::Keypair::generate(), signer.db.clone())
+            .await
+            .unwrap();
+        other
+            .sign_task(dss, B256::repeat_byte(1), &[0xbb; 32])
+            .await
+            .unwrap();
+
+        // The history survives a restart
+        drop((signer, other));
+        let db = Arc::new(SlashingProtectionDb::open(&path).unwrap());
+        assert_eq!(db.signed_tasks(keypair.public_key()).len(), 3);
+        let signer = ProtectedSigner::new(keypair, db).await.unwrap();
+        assert!(signer
+            .sign_task(dss, B256::repeat_byte(1), &[0xbb; 32])
+            .await
+            .is_err());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_ignores_torn_record() {
+        let path = db_path("torn");
+        let public_key = bn254::Keypair::generate().public_key().clone();
+        let db = SlashingProtectionDb::open(&path).unwrap();
+        db.check_and_record(&public_key, &task(1, 0xaa)).unwrap();
+        drop(db);
+
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(br#"{"publicKey":"#).unwrap();
+        drop(file);
+
+        let db = SlashingProtectionDb::open(&path).unwrap();
+        assert_eq!(db.signed_tasks(&public_key), [task(1, 0xaa)]);
+        db.check_and_record(&public_key, &task(2, 0xbb)).unwrap();
+        drop(db);
+
+        let db = SlashingProtectionDb::open(&path).unwrap();
+        assert_eq!(db.signed_tasks(&public_key), [task(1, 0xaa), task(2, 0xbb)]);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_locked_while_open() {
+        let path = db_path("locked");
+        let db = SlashingProtectionDb::open(&path).unwrap();
+        assert!(matches!(
+            SlashingProtectionDb::open(&path),
+            Err(SlashingProtectionError::Locked(locked)) if locked == path
+        ));
+
+        drop(db);
+        SlashingProtectionDb::open(&path).unwrap();
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_export_and_import() {
+        let (source_path, target_path) = (db_path("export"), db_path("import"));
+        let key = bn254::Keypair::generate().public_key().clone();
+        let other_key = bn254::Keypair::generate().public_key().clone();
+
+        let source = SlashingProtectionDb::open(&source_path).unwrap();
+        source.check_and_record(&key, &task(1, 0xaa)).unwrap();
+        source.check_and_record(&other_key, &task(1, 0xbb)).unwrap();
+        source.check_and_record(&key, &task(2, 0xcc)).unwrap();
+
+        let interchange: Interchange =
+            serde_json::from_str(&serde_json::to_string(&source.export()).unwrap()).unwrap();
+        assert_eq!(interchange.data.len(), 2);
+        assert_eq!(
+            interchange.data[0].signed_tasks,
+            [task(1, 0xaa), task(2, 0xcc)]
+        );
+
+        let target = SlashingProtectionDb::open(&target_path).unwrap();
+        target.check_and_record(&key, &task(1, 0xaa)).unwrap();
+        assert_eq!(target.import(&interchange).unwrap(), 2);
+        assert_eq!(target.import(&interchange).unwrap(), 0);
+        assert!(target.check_and_record(&key, &task(2, 0xdd)).is_err());
+
+        // A conflicting history is rejected as a whole
+        let mut conflicting = interchange.clone();
+        conflicting.data[0].signed_tasks.push(task(3, 0xaa));
+        conflicting.data[1].signed_tasks[0].message_hash = B256::repeat_byte(0xee);
+        assert!(matches!(
+            target.import(&conflicting),
+            Err(SlashingProtectionError::ConflictingSignature { .. })
+        ));
+        assert_eq!(target.signed_tasks(&key).len(), 2);
+
+        let mut unsupported = interchange;
+        unsupported.metadata.interchange_format_version = "2".to_string();
+        assert!(matches!(
+            target.import(&unsupported),
+            Err(SlashingProtectionError::UnsupportedInterchangeVersion(_))
+        ));
+
+        std::fs::remove_file(source_path).unwrap();
+        std::fs::remove_file(target_path).unwrap();
+    }
+}
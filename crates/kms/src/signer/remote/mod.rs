@@ -0,0 +1,350 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use reqwest::{Certificate, Client, Identity, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use signature::Verifier;
+use thiserror::Error;
+use tokio::sync::OnceCell;
+use url::Url;
+
+use crate::keypair::bn254::{bls::signature::Signature, PublicKey};
+
+use super::traits::Bn254Signer;
+
+pub mod protocol;
+
+use protocol::{
+    JsonRpcError, JsonRpcRequest, JsonRpcResponse, KeyInfo, KeyParams, SignHashParams,
+    GET_PUBLIC_KEY, JSON_RPC_VERSION, LIST_KEYS, SIGN_HASH,
+};
+
+/// How long a request to the signing service may take before it fails
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Error)]
+pub enum RemoteSignerError {
+    #[error("Request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Invalid TLS configuration: {0}")]
+    TlsError(reqwest::Error),
+    #[error("Signing service error ({status}): {body}")]
+    HttpError { status: StatusCode, body: String },
+    #[error("Signing service error: {0}")]
+    RpcError(#[from] JsonRpcError),
+    #[error("Signing service returned neither a result nor an error")]
+    EmptyResponse,
+    #[error("Signing service returned a signature that does not verify with key {0}")]
+    InvalidSignature(String),
+}
+
+/// PEM encoded certificates to reach a signing service over TLS
+#[derive(Clone, Debug, Default)]
+pub struct RemoteSignerTls {
+    /// Trusted in addition to the system's roots, e.g. the service's self-signed CA
+    pub ca_certificate_pem: Option<Vec<u8>>,
+    /// Client certificate and PKCS #8 key, for services that require mutual TLS
+    pub client_identity_pem: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+/// A BN254 signer whose key is held by a signing service, so the secret key never enters this
+/// process. Signatures are checked against the key's public key before they are returned
+pub struct RemoteBn254Signer {
+    client: Client,
+    url: Url,
+    key_id: String,
+    bearer_token: Option<String>,
+    timeout: Duration,
+    public_key: OnceCell<PublicKey>,
+    next_id: AtomicU64,
+}
+
+impl RemoteBn254Signer {
+    /// `key_id` names the key within the service, see [`RemoteBn254Signer::list_keys`]
+    pub fn new(url: Url, key_id: impl Into<String>) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+            key_id: key_id.into(),
+            bearer_token: None,
+            timeout: DEFAULT_REQUEST_TIMEOUT,
+            public_key: OnceCell::new(),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    pub fn with_bearer_token(mut self, bearer_token: impl Into<String>) -> Self {
+        self.bearer_token = Some(bearer_token.into());
+        self
+    }
+
+    /// Defaults to [`DEFAULT_REQUEST_TIMEOUT`]
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_tls(mut self, tls: &RemoteSignerTls) -> Result<Self, RemoteSignerError> {
+        let mut builder = Client::builder();
+        if let Some(pem) = &tls.ca_certificate_pem {
+            builder = builder.add_root_certificate(
+                Certificate::from_pem(pem).map_err(RemoteSignerError::TlsError)?,
+            );
+        }
+        if let Some((certificate_pem, key_pem)) = &tls.client_identity_pem {
+            builder = builder.identity(
+                Identity::from_pkcs8_pem(certificate_pem, key_pem)
+                    .map_err(RemoteSignerError::TlsError)?,
+            );
+        }
+        self.client = builder.build().map_err(RemoteSignerError::TlsError)?;
+        Ok(self)
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// The keys the service can sign with
+    pub async fn list_keys(&self) -> Result<Vec<KeyInfo>, RemoteSignerError> {
+        self.call(LIST_KEYS, ()).await
+    }
+
+    async fn call<Params: Serialize, Result: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Params,
+    ) -> std::result::Result<Result, RemoteSignerError> {
+        let request = JsonRpcRequest {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            id: self.next_id.fetch_add(1, Ordering::Relaxed).into(),
+            method: method.to_string(),
+            params,
+        };
+
+        let mut builder = self
+            .client
+            .post(self.url.clone())
+            .timeout(self.timeout)
+            .json(&request);
+        if let Some(token) = &self.bearer_token {
+            builder = builder.bearer_auth(token);
+        }
+        let response = builder.send().await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(RemoteSignerError::HttpError {
+                status,
+                body: response.text().await?,
+            });
+        }
+
+        let response: JsonRpcResponse<Result> = response.json().await?;
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(error.into()),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(RemoteSignerError::EmptyResponse),
+        }
+    }
+}
+
+impl Bn254Signer for RemoteBn254Signer {
+    type Error = RemoteSignerError;
+
+    async fn get_public_key(&self) -> Result<PublicKey, Self::Error> {
+        let public_key = self
+            .public_key
+            .get_or_try_init(|| {
+                self.call(
+                    GET_PUBLIC_KEY,
+                    KeyParams {
+                        key_id: self.key_id.clone(),
+                    },
+                )
+            })
+            .await?;
+        Ok(public_key.clone())
+    }
+
+    async fn sign_hash(&self, hash: &[u8; 32]) -> Result<Signature, Self::Error> {
+        let public_key = self.get_public_key().await?;
+        let signature: Signature = self
+            .call(
+                SIGN_HASH,
+                SignHashParams {
+                    key_id: self.key_id.clone(),
+                    hash: (*hash).into(),
+                },
+            )
+            .await?;
+
+        public_key
+            .verify(hash, &signature)
+            .map_err(|_| RemoteSignerError::InvalidSignature(self.key_id.clone()))?;
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+    use serde_json::{json, Value};
+
+    use crate::{
+        keypair::{bn254, traits::Keypair as _},
+        test_utils::spawn_mock,
+    };
+
+    use super::{protocol::*, *};
+
+    #[derive(Clone)]
+    struct Service {
+        keys: Arc<HashMap<String, bn254::Keypair>>,
+        /// Signs every hash with this key instead, like a misconfigured service
+        imposter: Option<Arc<bn254::Keypair>>,
+    }
+
+    fn key(service: &Service, params: &Value) -> Result<bn254::Keypair, JsonRpcError> {
+        let key_id = params["keyId"].as_str().unwrap_or_default();
+        service
+            .keys
+            .get(key_id)
+            .cloned()
+            .ok_or_else(|| JsonRpcError::new(KEY_NOT_FOUND, format!("No key {key_id}")))
+    }
+
+    async fn handle(
+        State(service): State<Service>,
+        headers: HeaderMap,
+        Json(request): Json<JsonRpcRequest<Value>>,
+    ) -> Result<Json<JsonRpcResponse<Value>>, StatusCode> {
+        if headers
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            != Some("Bearer token")
+        {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+
+        let result = match request.method.as_str() {
+            LIST_KEYS => Ok(json!(service
+                .keys
+                .iter()
+                .map(|(key_id, keypair)| KeyInfo {
+                    key_id: key_id.clone(),
+                    public_key: keypair.public_key().clone(),
+                })
+                .collect::<Vec<_>>())),
+            GET_PUBLIC_KEY => key(&service, &request.params).map(|key| json!(key.public_key())),
+            SIGN_HASH => key(&service, &request.params).map(|key| {
+                let params: SignHashParams = serde_json::from_value(request.params).unwrap();
+                let signer = service.imposter.as_deref().unwrap_or(&key);
+                json!(signature::Signer::<Signature>::sign(
+                    signer,
+                    params.hash.as_slice()
+                ))
+            }),
+            method => Err(JsonRpcError::new(METHOD_NOT_FOUND, method)),
+        };
+
+        Ok(Json(match result {
+            Ok(result) => JsonRpcResponse::result(request.id, result),
+            Err(error) => JsonRpcResponse::error(request.id, error),
+        }))
+    }
+
+    async fn service(keypair: &bn254::Keypair, imposter: Option<bn254::Keypair>) -> Url {
+        let service = Service {
+            keys: Arc::new(HashMap::from([("operator".to_string(), keypair.clone())])),
+            imposter: imposter.map(Arc::new),
+        };
+        let app = Router::new().route("/", post(handle)).with_state(service);
+
+        spawn_mock(app).await
+    }
+
+    #[tokio::test]
+    async fn test_sign_hash_matches_local_signer() {
+        let keypair = bn254::Keypair::generate();
+        let url = service(&keypair, None).await;
+        let signer = RemoteBn254Signer::new(url, "operator").with_bearer_token("token");
+
+        let keys = signer.list_keys().await.unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].key_id, "operator");
+        assert_eq!(&keys[0].public_key, keypair.public_key());
+        assert_eq!(
+            &signer.get_public_key().await.unwrap(),
+            keypair.public_key()
+        );
+
+        let hash = [42u8; 32];
+        assert_eq!(
+            signer.sign_hash(&hash).await.unwrap(),
+            keypair.sign_hash(&hash).await.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let keypair = bn254::Keypair::generate();
+        let url = service(&keypair, Some(bn254::Keypair::generate())).await;
+
+        let unauthorized = RemoteBn254Signer::new(url.clone(), "operator");
+        assert!(matches!(
+            unauthorized.list_keys().await,
+            Err(RemoteSignerError::HttpError {
+                status: StatusCode::UNAUTHORIZED,
+                ..
+            })
+        ));
+
+        let unknown = RemoteBn254Signer::new(url.clone(), "unknown").with_bearer_token("token");
+        assert!(matches!(
+            unknown.sign_hash(&[42u8; 32]).await,
+            Err(RemoteSignerError::RpcError(JsonRpcError {
+                code: KEY_NOT_FOUND,
+                ..
+            }))
+        ));
+
+        let signer = RemoteBn254Signer::new(url, "operator").with_bearer_token("token");
+        assert!(matches!(
+            signer.sign_hash(&[42u8; 32]).await,
+            Err(RemoteSignerError::InvalidSignature(_))
+        ));
+
+        let tls = RemoteSignerTls {
+            ca_certificate_pem: Some(b"not a certificate".to_vec()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            RemoteBn254Signer::new(Url::parse("https://127.0.0.1/").unwrap(), "operator")
+                .with_tls(&tls),
+            Err(RemoteSignerError::TlsError(_))
+        ));
+
+        // A service that never answers
+        let url = spawn_mock(Router::new().route(
+            "/",
+            post(|| async {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                ""
+            }),
+        ))
+        .await;
+        let hanging = RemoteBn254Signer::new(url, "operator")
+            .with_timeout(Duration::from_millis(100))
+            .with_tls(&RemoteSignerTls::default())
+            .unwrap();
+        assert!(matches!(
+            hanging.list_keys().await,
+            Err(RemoteSignerError::RequestError(err)) if err.is_timeout()
+        ));
+    }
+}
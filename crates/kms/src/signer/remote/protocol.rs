@@ -0,0 +1,102 @@
+//! The signing service protocol: JSON-RPC 2.0 requests POSTed to the service URL, authenticated
+//! with an optional `Authorization: Bearer <token>` header
+
+use alloy::primitives::B256;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::keypair::bn254::PublicKey;
+
+/// Lists the keys the service signs with, returns `Vec<KeyInfo>`
+pub const LIST_KEYS: &str = "bn254_listKeys";
+/// Takes `KeyParams`, returns the `PublicKey` of the key
+pub const GET_PUBLIC_KEY: &str = "bn254_getPublicKey";
+/// Takes `SignHashParams`, returns the `Signature` of the hash
+pub const SIGN_HASH: &str = "bn254_signHash";
+
+pub const JSON_RPC_VERSION: &str = "2.0";
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// No key with the requested id
+pub const KEY_NOT_FOUND: i64 = -32000;
+/// The service refused to sign, e.g. because of its slashing protection
+pub const SIGNING_REFUSED: i64 = -32001;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JsonRpcRequest<Params> {
+    pub jsonrpc: String,
+    pub id: Value,
+    pub method: String,
+    pub params: Params,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JsonRpcResponse<Result> {
+    pub jsonrpc: String,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Result>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+}
+
+impl<Result> JsonRpcResponse<Result> {
+    pub fn result(id: Value, result: Result) -> Self {
+        Self {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: Value, error: JsonRpcError) -> Self {
+        Self {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Error, Serialize, Deserialize)]
+#[error("{message} (code {code})")]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl JsonRpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyParams {
+    pub key_id: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignHashParams {
+    pub key_id: String,
+    pub hash: B256,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyInfo {
+    pub key_id: String,
+    pub public_key: PublicKey,
+}
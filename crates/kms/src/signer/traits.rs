@@ -1,6 +1,36 @@
+use std::convert::Infallible;
+
+use crate::keypair::{
+    bn254::{self, bls::signature::Signature, PublicKey},
+    traits::Keypair,
+};
+
 pub trait Signer<M: Sized> {
     type Error;
     type Signature;
 
     fn sign_message(&self, message: M) -> Result<Self::Signature, Self::Error>;
 }
+
+/// Signs message hashes with a BN254 key that is either held in memory or by a signing service
+#[trait_variant::make(Send)]
+pub trait Bn254Signer {
+    type Error: std::error::Error + Send + Sync;
+
+    async fn get_public_key(&self) -> Result<PublicKey, Self::Error>;
+
+    /// Caller is responsible for ensuring `hash` is a 32-byte hash of some arbitrary sized message
+    async fn sign_hash(&self, hash: &[u8; 32]) -> Result<Signature, Self::Error>;
+}
+
+impl Bn254Signer for bn254::Keypair {
+    type Error = Infallible;
+
+    async fn get_public_key(&self) -> Result<PublicKey, Self::Error> {
+        Ok(self.public_key().clone())
+    }
+
+    async fn sign_hash(&self, hash: &[u8; 32]) -> Result<Signature, Self::Error> {
+        Ok(signature::Signer::sign(self, hash))
+    }
+}
@@ -0,0 +1,17 @@
+//! Scaffolding shared by the unit tests of the keystore backends and remote signers
+
+use axum::Router;
+use url::Url;
+
+use crate::encryption::KdfParams;
+
+/// Cheap enough that tests do not spend their time in the KDF
+pub const LIGHT_KDF: KdfParams = KdfParams::Pbkdf2 { iterations: 1_000 };
+
+/// Serves `app` on a free local port, the URL ends with `/`
+pub async fn spawn_mock(app: Router) -> Url {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    url
+}
@@ -1,17 +1,53 @@
 use alloy::{
-    consensus::{SignableTransaction, TxLegacy},
+    consensus::{SignableTransaction, TxEnvelope, TxType},
+    dyn_abi::TypedData,
+    eips::{eip2718::Decodable2718, eip2930::AccessList},
     network::TxSigner,
-    primitives::{Address, Bytes, TxKind, U256},
-    rpc::client::{ClientBuilder, ReqwestClient},
-    signers::Signature,
+    primitives::{Address, Bytes, ChainId, TxKind, B256, U256},
+    rpc::{
+        client::{ClientBuilder, ReqwestClient},
+        json_rpc::RpcParam,
+    },
+    signers::{Signature, Signer, UnsupportedSignerOperation},
+    sol_types::{Eip712Domain, SolStruct},
+    transports::TransportError,
 };
 use async_trait::async_trait;
+use reqwest::StatusCode;
 use serde::Serialize;
+use thiserror::Error;
 use url::Url;
 
+#[derive(Debug, Error)]
+pub enum Web3SignerError {
+    #[error("Transaction type {0} is not supported by Web3Signer")]
+    UnsupportedTransactionType(u8),
+    #[error(
+        "Web3Signer signed a different transaction (signature hash {signed}, expected {expected})"
+    )]
+    TransactionMismatch { expected: B256, signed: B256 },
+    #[error("Web3Signer signature was made by {recovered}, expected {expected}")]
+    SignerMismatch {
+        expected: Address,
+        recovered: Address,
+    },
+    #[error("Web3Signer does not hold a key for {0}")]
+    UnknownAccount(Address),
+    #[error("Web3Signer is not up ({0})")]
+    NotUp(StatusCode),
+    #[error("Web3Signer URL {0} cannot be a base URL")]
+    InvalidUrl(Url),
+    #[error("Request error: {0}")]
+    RequestError(#[from] reqwest::Error),
+    #[error("RPC error: {0}")]
+    RpcError(#[from] TransportError),
+}
+
 /// A signer that sends an RPC request to sign a transaction remotely
 /// Implements `eth_signTransaction` method of Consensys Web3 Signer
 /// Reference: <https://docs.web3signer.consensys.io/reference/api/json-rpc#eth_signtransaction>
+///
+/// Messages and EIP-712 typed data are signed with `eth_sign` and `eth_signTypedData_v4`
 #[derive(Debug)]
 pub struct Web3Signer {
     /// Client used to send an RPC request
@@ -19,6 +55,10 @@ pub struct Web3Signer {
     /// Address of the account that intends to sign a transaction.
     /// It must match the `from` field in the transaction.
     pub address: Address,
+    /// Base URL of the Web3Signer, for its non JSON-RPC endpoints
+    pub url: Url,
+    /// Transactions for other chains are refused when set
+    pub chain_id: Option<ChainId>,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -35,17 +75,200 @@ struct SignTransactionParams {
         with = "alloy_serde::quantity::opt"
     )]
     gas_price: Option<u128>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "alloy_serde::quantity::opt"
+    )]
+    max_fee_per_gas: Option<u128>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "alloy_serde::quantity::opt"
+    )]
+    max_priority_fee_per_gas: Option<u128>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    access_list: Option<AccessList>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        with = "alloy_serde::quantity::opt"
+    )]
+    chain_id: Option<ChainId>,
+    #[serde(rename = "type", with = "alloy_serde::quantity")]
+    transaction_type: u8,
     #[serde(with = "alloy_serde::quantity")]
     nonce: u64,
     data: Bytes,
 }
 
+impl SignTransactionParams {
+    fn new(
+        from: Address,
+        tx: &dyn SignableTransaction<Signature>,
+    ) -> Result<Self, Web3SignerError> {
+        let transaction_type = tx.ty();
+        let max_priority_fee_per_gas = match TxType::try_from(transaction_type) {
+            Ok(TxType::Legacy | TxType::Eip2930) => None,
+            Ok(TxType::Eip1559) => tx.max_priority_fee_per_gas(),
+            _ => {
+                return Err(Web3SignerError::UnsupportedTransactionType(
+                    transaction_type,
+                ))
+            }
+        };
+
+        Ok(Self {
+            from,
+            to: tx.to(),
+            value: tx.value(),
+            gas: tx.gas_limit(),
+            gas_price: tx.gas_price(),
+            // For legacy transactions this is the gas price, which is already sent
+            max_fee_per_gas: max_priority_fee_per_gas.map(|_| tx.max_fee_per_gas()),
+            max_priority_fee_per_gas,
+            access_list: tx.access_list().cloned(),
+            chain_id: tx.chain_id(),
+            transaction_type,
+            nonce: tx.nonce(),
+            data: Bytes::copy_from_slice(tx.input()),
+        })
+    }
+}
+
 impl Web3Signer {
     pub fn new(address: Address, url: Url) -> Self {
         Web3Signer {
-            client: ClientBuilder::default().http(url),
+            client: ClientBuilder::default().http(url.clone()),
             address,
+            url,
+            chain_id: None,
+        }
+    }
+
+    /// Fails unless Web3Signer's `/upcheck` endpoint reports it is up
+    pub async fn upcheck(&self) -> Result<(), Web3SignerError> {
+        // Appended rather than joined, so a URL without a trailing slash keeps its last segment
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .map_err(|()| Web3SignerError::InvalidUrl(self.url.clone()))?
+            .pop_if_empty()
+            .push("upcheck");
+        // The RPC client's connection pool is reused
+        let status = self
+            .client
+            .transport()
+            .client()
+            .get(url)
+            .send()
+            .await?
+            .status();
+        if !status.is_success() {
+            return Err(Web3SignerError::NotUp(status));
+        }
+        Ok(())
+    }
+
+    /// The addresses Web3Signer holds keys for
+    pub async fn accounts(&self) -> Result<Vec<Address>, Web3SignerError> {
+        Ok(self.client.request_noparams("eth_accounts").await?)
+    }
+
+    /// Checks that Web3Signer is up and holds the key for the configured address, to fail at
+    /// startup rather than on the first signature
+    pub async fn validate(&self) -> Result<(), Web3SignerError> {
+        self.upcheck().await?;
+        if !self.accounts().await?.contains(&self.address) {
+            return Err(Web3SignerError::UnknownAccount(self.address));
+        }
+        Ok(())
+    }
+
+    /// Signs an EIP-712 struct, which must serialize to the JSON form of its fields
+    pub async fn sign_struct<T: SolStruct + Serialize + Sync>(
+        &self,
+        payload: &T,
+        domain: &Eip712Domain,
+    ) -> alloy::signers::Result<Signature> {
+        self.sign_dynamic_typed_data(&TypedData::from_struct(payload, Some(domain.clone())))
+            .await
+    }
+
+    async fn request_signature<Params: RpcParam + 'static>(
+        &self,
+        method: &'static str,
+        params: Params,
+        signing_hash: B256,
+    ) -> alloy::signers::Result<Signature> {
+        let signature: Bytes = self
+            .client
+            .request(method, params)
+            .await
+            .map_err(alloy::signers::Error::other)?;
+        let signature = Signature::try_from(signature.as_ref())?;
+
+        let recovered = signature.recover_address_from_prehash(&signing_hash)?;
+        if recovered != self.address {
+            return Err(alloy::signers::Error::other(
+                Web3SignerError::SignerMismatch {
+                    expected: self.address,
+                    recovered,
+                },
+            ));
         }
+        Ok(signature)
+    }
+}
+
+#[async_trait]
+impl Signer<Signature> for Web3Signer {
+    /// Web3Signer only signs EIP-191 prefixed messages, never bare hashes
+    async fn sign_hash(&self, _hash: &B256) -> alloy::signers::Result<Signature> {
+        Err(alloy::signers::Error::UnsupportedOperation(
+            UnsupportedSignerOperation::SignHash,
+        ))
+    }
+
+    async fn sign_message(&self, message: &[u8]) -> alloy::signers::Result<Signature> {
+        self.request_signature(
+            "eth_sign",
+            (self.address, Bytes::copy_from_slice(message)),
+            alloy::primitives::eip191_hash_message(message),
+        )
+        .await
+    }
+
+    /// Web3Signer needs the struct's fields, which a bare [`SolStruct`] cannot provide, use
+    /// [`Web3Signer::sign_struct`] or [`Signer::sign_dynamic_typed_data`] instead
+    async fn sign_typed_data<T: SolStruct + Send + Sync>(
+        &self,
+        _payload: &T,
+        _domain: &Eip712Domain,
+    ) -> alloy::signers::Result<Signature> {
+        Err(alloy::signers::Error::UnsupportedOperation(
+            UnsupportedSignerOperation::SignTypedData,
+        ))
+    }
+
+    async fn sign_dynamic_typed_data(
+        &self,
+        payload: &TypedData,
+    ) -> alloy::signers::Result<Signature> {
+        self.request_signature(
+            "eth_signTypedData_v4",
+            (self.address, payload.clone()),
+            payload.eip712_signing_hash()?,
+        )
+        .await
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> Option<ChainId> {
+        self.chain_id
+    }
+
+    fn set_chain_id(&mut self, chain_id: Option<ChainId>) {
+        self.chain_id = chain_id;
     }
 }
 
@@ -59,15 +282,18 @@ impl TxSigner<Signature> for Web3Signer {
         &self,
         tx: &mut dyn SignableTransaction<Signature>,
     ) -> alloy::signers::Result<Signature> {
-        let params = SignTransactionParams {
-            from: self.address,
-            to: tx.to(),
-            value: tx.value(),
-            gas: tx.gas_limit(),
-            gas_price: tx.gas_price(),
-            nonce: tx.nonce(),
-            data: Bytes::copy_from_slice(tx.input()),
-        };
+        if let Some(chain_id) = self.chain_id {
+            if !tx.set_chain_id_checked(chain_id) {
+                return Err(alloy::signers::Error::TransactionChainIdMismatch {
+                    signer: chain_id,
+                    // set_chain_id_checked only fails when the transaction has a chain ID
+                    tx: tx.chain_id().unwrap_or_default(),
+                });
+            }
+        }
+
+        let params =
+            SignTransactionParams::new(self.address, tx).map_err(alloy::signers::Error::other)?;
 
         let response = self
             .client
@@ -75,9 +301,303 @@ impl TxSigner<Signature> for Web3Signer {
             .await
             .map_err(alloy::signers::Error::other)?;
 
-        let signed_tx = TxLegacy::decode_signed_fields(&mut response.as_ref())
+        // Legacy transactions come back as a bare RLP list, typed ones as an EIP-2718 envelope
+        let signed_tx = TxEnvelope::decode_2718(&mut response.as_ref())
             .map_err(alloy::signers::Error::other)?;
 
-        Ok(*signed_tx.signature())
+        let (expected, signed) = (tx.signature_hash(), signed_tx.signature_hash());
+        if expected != signed {
+            return Err(alloy::signers::Error::other(
+                Web3SignerError::TransactionMismatch { expected, signed },
+            ));
+        }
+
+        Ok(match &signed_tx {
+            TxEnvelope::Legacy(signed) => *signed.signature(),
+            TxEnvelope::Eip2930(signed) => *signed.signature(),
+            TxEnvelope::Eip1559(signed) => *signed.signature(),
+            other => {
+                return Err(alloy::signers::Error::other(
+                    Web3SignerError::UnsupportedTransactionType(other.tx_type() as u8),
+                ))
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use alloy::{
+        consensus::{TxEip1559, TxEip2930, TxEip7702, TxLegacy},
+        eips::{eip2718::Encodable2718, eip2930::AccessListItem},
+        network::{EthereumWallet, TransactionBuilder},
+        rpc::types::TransactionRequest,
+        signers::{local::PrivateKeySigner, SignerSync},
+        sol_types::eip712_domain,
+    };
+    use axum::{
+        extract::State,
+        routing::{get, post},
+        Json, Router,
+    };
+    use serde_json::{json, Value};
+
+    use crate::{
+        signer::remote::protocol::{JsonRpcRequest, JsonRpcResponse},
+        test_utils::spawn_mock,
+    };
+
+    use super::*;
+
+    alloy::sol! {
+        #[derive(serde::Serialize)]
+        struct Attestation {
+            uint256 taskId;
+            address dss;
+            bytes32 responseHash;
+            string note;
+        }
+    }
+
+    /// Signs like Web3Signer, optionally bumping the nonce of transactions first
+    #[derive(Clone)]
+    struct Mock {
+        signer: PrivateKeySigner,
+        tamper: bool,
+        /// The `eth_signTransaction` parameters
+        requests: Arc<Mutex<Vec<Value>>>,
+    }
+
+    async fn rpc(
+        State(mock): State<Mock>,
+        Json(request): Json<JsonRpcRequest<Option<Vec<Value>>>>,
+    ) -> Json<JsonRpcResponse<Value>> {
+        let params = request.params.unwrap_or_default();
+        let result = match request.method.as_str() {
+            "eth_accounts" => json!([mock.signer.address()]),
+            "eth_sign" => {
+                let message: Bytes = serde_json::from_value(params[1].clone()).unwrap();
+                let signature = mock.signer.sign_message_sync(&message).unwrap();
+                json!(Bytes::from(signature.as_bytes()))
+            }
+            "eth_signTypedData_v4" => {
+                let typed_data: TypedData = serde_json::from_value(params[1].clone()).unwrap();
+                let signature = mock
+                    .signer
+                    .sign_dynamic_typed_data_sync(&typed_data)
+                    .unwrap();
+                json!(Bytes::from(signature.as_bytes()))
+            }
+            "eth_signTransaction" => {
+                let params = params[0].clone();
+                mock.requests.lock().unwrap().push(params.clone());
+
+                let mut tx: TransactionRequest = serde_json::from_value(params).unwrap();
+                if mock.tamper {
+                    tx.nonce = tx.nonce.map(|nonce| nonce + 1);
+                }
+                let signed = tx
+                    .build(&EthereumWallet::from(mock.signer.clone()))
+                    .await
+                    .unwrap();
+                json!(Bytes::from(signed.encoded_2718()))
+            }
+            method => panic!("Unexpected method {method}"),
+        };
+        Json(JsonRpcResponse::result(request.id, result))
+    }
+
+    async fn mock(signer: PrivateKeySigner, tamper: bool) -> (Url, Arc<Mutex<Vec<Value>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let app = Router::new()
+            .route("/", post(rpc))
+            .route("/upcheck", get(|| async { "OK" }))
+            .with_state(Mock {
+                signer,
+                tamper,
+                requests: requests.clone(),
+            });
+
+        (spawn_mock(app).await, requests)
+    }
+
+    fn transactions() -> Vec<Box<dyn SignableTransaction<Signature>>> {
+        let to = TxKind::Call(Address::repeat_byte(0x11));
+        let access_list = AccessList(vec![AccessListItem {
+            address: Address::repeat_byte(0x22),
+            storage_keys: vec![B256::repeat_byte(0x33)],
+        }]);
+        let input = Bytes::from_static(&[0xde, 0xad, 0xbe, 0xef]);
+
+        let transactions: [Box<dyn SignableTransaction<Signature>>; 3] = [
+            Box::new(TxLegacy {
+                chain_id: Some(1),
+                nonce: 1,
+                gas_price: 20_000_000_000,
+                gas_limit: 21_000,
+                to,
+                value: U256::from(1),
+                input: input.clone(),
+            }),
+            Box::new(TxEip2930 {
+                chain_id: 1,
+                nonce: 2,
+                gas_price: 20_000_000_000,
+                gas_limit: 50_000,
+                to,
+                value: U256::from(2),
+                access_list: access_list.clone(),
+                input: input.clone(),
+            }),
+            Box::new(TxEip1559 {
+                chain_id: 1,
+                nonce: 3,
+                gas_limit: 50_000,
+                max_fee_per_gas: 30_000_000_000,
+                max_priority_fee_per_gas: 1_000_000_000,
+                to,
+                value: U256::from(3),
+                access_list,
+                input,
+            }),
+        ];
+        transactions.into()
+    }
+
+    #[tokio::test]
+    async fn test_sign_typed_transactions() {
+        let signer = PrivateKeySigner::random();
+        let (url, requests) = mock(signer.clone(), false).await;
+        let web3_signer = Web3Signer::new(signer.address(), url);
+
+        for mut tx in transactions() {
+            let signature = web3_signer.sign_transaction(tx.as_mut()).await.unwrap();
+            assert_eq!(
+                signature
+                    .recover_address_from_prehash(&tx.signature_hash())
+                    .unwrap(),
+                signer.address()
+            );
+        }
+
+        let requests = requests.lock().unwrap();
+        let types: Vec<_> = requests
+            .iter()
+            .map(|params| params["type"].clone())
+            .collect();
+        assert_eq!(types, ["0x0", "0x1", "0x2"]);
+
+        let eip1559 = &requests[2];
+        assert_eq!(eip1559["chainId"], "0x1");
+        assert_eq!(eip1559["maxFeePerGas"], "0x6fc23ac00");
+        assert_eq!(eip1559["maxPriorityFeePerGas"], "0x3b9aca00");
+        assert_eq!(eip1559["accessList"].as_array().unwrap().len(), 1);
+        assert!(eip1559.get("gasPrice").is_none());
+        assert!(requests[0].get("accessList").is_none());
+        assert!(requests[0].get("maxFeePerGas").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sign_transaction_errors() {
+        let signer = PrivateKeySigner::random();
+        let (url, _) = mock(signer.clone(), true).await;
+        let web3_signer = Web3Signer::new(signer.address(), url);
+
+        let mut tx = transactions().remove(2);
+        let error = web3_signer.sign_transaction(tx.as_mut()).await.unwrap_err();
+        assert!(error.to_string().contains("signed a different transaction"));
+
+        let mut tx = TxEip7702::default();
+        let error = web3_signer.sign_transaction(&mut tx).await.unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Transaction type 4 is not supported"));
+
+        let web3_signer = web3_signer.with_chain_id(Some(5));
+        let mut tx = transactions().remove(2);
+        assert!(matches!(
+            web3_signer.sign_transaction(tx.as_mut()).await,
+            Err(alloy::signers::Error::TransactionChainIdMismatch { signer: 5, tx: 1 })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let signer = PrivateKeySigner::random();
+        let (url, _) = mock(signer.clone(), false).await;
+
+        let web3_signer = Web3Signer::new(signer.address(), url.clone());
+        assert_eq!(web3_signer.accounts().await.unwrap(), [signer.address()]);
+        web3_signer.validate().await.unwrap();
+
+        let unknown = Address::repeat_byte(0x55);
+        assert!(matches!(
+            Web3Signer::new(unknown, url).validate().await,
+            Err(Web3SignerError::UnknownAccount(address)) if address == unknown
+        ));
+
+        // Behind a reverse proxy, with and without a trailing slash
+        let proxy = spawn_mock(Router::new().nest(
+            "/web3signer",
+            Router::new().route("/upcheck", get(|| async { "OK" })),
+        ))
+        .await;
+        for path in ["web3signer", "web3signer/"] {
+            Web3Signer::new(signer.address(), proxy.join(path).unwrap())
+                .upcheck()
+                .await
+                .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_message_and_typed_data() {
+        let signer = PrivateKeySigner::random();
+        let (url, _) = mock(signer.clone(), false).await;
+        let web3_signer = Web3Signer::new(signer.address(), url.clone());
+
+        let message = b"karak attestation";
+        assert_eq!(
+            web3_signer.sign_message(message).await.unwrap().as_bytes(),
+            signer.sign_message_sync(message).unwrap().as_bytes()
+        );
+
+        let attestation = Attestation {
+            taskId: U256::from(42),
+            dss: Address::repeat_byte(0x22),
+            responseHash: B256::repeat_byte(0x33),
+            note: "ok".to_string(),
+        };
+        let domain = eip712_domain! {
+            name: "Karak",
+            version: "1",
+            chain_id: 1,
+            verifying_contract: Address::repeat_byte(0x44),
+        };
+        assert_eq!(
+            web3_signer
+                .sign_struct(&attestation, &domain)
+                .await
+                .unwrap()
+                .as_bytes(),
+            signer
+                .sign_typed_data_sync(&attestation, &domain)
+                .unwrap()
+                .as_bytes()
+        );
+
+        assert!(matches!(
+            web3_signer.sign_hash(&B256::ZERO).await,
+            Err(alloy::signers::Error::UnsupportedOperation(
+                UnsupportedSignerOperation::SignHash
+            ))
+        ));
+
+        // Signatures by any other key are rejected
+        let web3_signer = Web3Signer::new(Address::repeat_byte(0x55), url);
+        let error = web3_signer.sign_message(message).await.unwrap_err();
+        assert!(error.to_string().contains("signature was made by"));
     }
 }
@@ -0,0 +1,100 @@
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::B256;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditOutcome {
+    Signed,
+    /// The key's policy does not allow the client
+    Denied,
+    KeyNotFound,
+}
+
+/// One signing request, see [`AuditLog`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// `None` for anonymous clients
+    pub client: Option<String>,
+    pub key_id: String,
+    pub hash: B256,
+    pub outcome: AuditOutcome,
+}
+
+impl AuditRecord {
+    pub fn new(client: Option<&str>, key_id: &str, hash: B256, outcome: AuditOutcome) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            client: client.map(str::to_string),
+            key_id: key_id.to_string(),
+            hash,
+            outcome,
+        }
+    }
+}
+
+/// Records every signing request, whether or not it was signed. Records are always emitted as
+/// `tracing` events with the `audit` target, and also appended as JSON lines to a writer when one
+/// is configured
+#[derive(Default)]
+pub struct AuditLog {
+    writer: Option<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends to the file at `path`, creating it if needed
+    pub fn to_file(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::to_writer(file))
+    }
+
+    pub fn to_writer(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Some(Mutex::new(Box::new(writer))),
+        }
+    }
+
+    /// Fails if the record could not be written, the request must then not be served
+    pub fn record(&self, record: &AuditRecord) -> std::io::Result<()> {
+        tracing::info!(
+            target: "audit",
+            client = record.client.as_deref().unwrap_or("anonymous"),
+            key_id = record.key_id,
+            hash = %record.hash,
+            outcome = ?record.outcome,
+            "signing request"
+        );
+
+        let Some(writer) = &self.writer else {
+            return Ok(());
+        };
+        // A poisoned lock only means another request panicked mid-write, keep auditing
+        let mut writer = writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let written = serde_json::to_writer(&mut *writer, record)
+            .map_err(std::io::Error::from)
+            .and_then(|()| writer.write_all(b"\n"))
+            .and_then(|()| writer.flush());
+        if let Err(e) = &written {
+            tracing::error!(target: "audit", "Failed to write audit record: {e}");
+        }
+        written
+    }
+}
@@ -0,0 +1,125 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::SignerError;
+
+/// The `karak-signer` configuration file, in YAML
+///
+/// ```yaml
+/// listen:
+///   tcp: 127.0.0.1:9000
+/// audit_log: /var/log/karak-signer/audit.jsonl
+/// clients:
+///   - name: operator-node
+///     token_env: OPERATOR_NODE_TOKEN
+/// keys:
+///   - id: operator
+///     keystore: file:///etc/karak-signer/operator.bls
+///     passphrase_env: OPERATOR_PASSPHRASE
+///     policy:
+///       allowed_clients: [operator-node]
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignerConfig {
+    #[serde(with = "serde_yaml::with::singleton_map")]
+    pub listen: Listen,
+    /// Every signing request is appended to this file as a JSON line
+    #[serde(default)]
+    pub audit_log: Option<PathBuf>,
+    /// Clients authenticate with a bearer token. Without clients every request is anonymous, which
+    /// is only allowed when listening on a Unix socket
+    #[serde(default)]
+    pub clients: Vec<ClientConfig>,
+    pub keys: Vec<KeyConfig>,
+}
+
+impl SignerConfig {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SignerError> {
+        let config = std::fs::read_to_string(path)?;
+        Ok(serde_yaml::from_str(&config)?)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Listen {
+    Tcp(SocketAddr),
+    /// A stale socket at the path is replaced, any other file is refused
+    Unix(PathBuf),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientConfig {
+    pub name: String,
+    /// The environment variable holding the client's bearer token
+    pub token_env: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyConfig {
+    pub id: String,
+    /// A keystore URI, e.g. `file://` or `aws-sm://`
+    pub keystore: String,
+    /// The environment variable holding the keystore passphrase
+    pub passphrase_env: String,
+    #[serde(default)]
+    pub policy: KeyPolicy,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyPolicy {
+    /// The clients that may use the key, any client (including anonymous ones) when unset
+    #[serde(default)]
+    pub allowed_clients: Option<Vec<String>>,
+}
+
+impl KeyPolicy {
+    pub fn allows(&self, client: Option<&str>) -> bool {
+        match (&self.allowed_clients, client) {
+            (None, _) => true,
+            (Some(allowed), Some(client)) => allowed.iter().any(|allowed| allowed == client),
+            (Some(_), None) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: SignerConfig = serde_yaml::from_str(
+            r#"
+listen:
+  unix: /run/karak-signer.sock
+keys:
+  - id: operator
+    keystore: aws-sm://default/operator.bls
+    passphrase_env: OPERATOR_PASSPHRASE
+    policy:
+      allowed_clients: [operator-node]
+  - id: backup
+    keystore: file://backup.bls
+    passphrase_env: BACKUP_PASSPHRASE
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.listen,
+            Listen::Unix(PathBuf::from("/run/karak-signer.sock"))
+        );
+        assert_eq!(config.audit_log, None);
+        assert!(config.clients.is_empty());
+        assert_eq!(config.keys.len(), 2);
+        assert!(config.keys[0].policy.allows(Some("operator-node")));
+        assert!(!config.keys[0].policy.allows(Some("other")));
+        assert!(!config.keys[0].policy.allows(None));
+        assert!(config.keys[1].policy.allows(None));
+    }
+}
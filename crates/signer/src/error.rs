@@ -0,0 +1,30 @@
+use karak_kms::keystore::{dynamic::DynKeystoreError, uri::KeystoreUriError};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SignerError {
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("Invalid configuration: {0}")]
+    ConfigError(#[from] serde_yaml::Error),
+    #[error("Environment variable {0} is not set")]
+    MissingEnv(String),
+    #[error("Key {0} is configured more than once")]
+    DuplicateKey(String),
+    #[error("Client {0} is configured more than once, or shares its token with another client")]
+    DuplicateClient(String),
+    #[error("No clients are configured, anonymous access is only allowed over a Unix socket")]
+    AnonymousTcp,
+    #[error("Key {key} allows unknown client {client}")]
+    UnknownClient { key: String, client: String },
+    #[error("Invalid keystore for key {key}: {source}")]
+    KeystoreUriError {
+        key: String,
+        source: KeystoreUriError,
+    },
+    #[error("Failed to load key {key}: {source}")]
+    KeystoreError {
+        key: String,
+        source: DynKeystoreError,
+    },
+}
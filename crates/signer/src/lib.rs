@@ -0,0 +1,6 @@
+pub mod audit;
+pub mod config;
+pub mod error;
+pub mod server;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
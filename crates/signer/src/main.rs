@@ -0,0 +1,33 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use karak_signer::{config::SignerConfig, server::SigningServer};
+use tracing_subscriber::EnvFilter;
+
+/// Serves BN254 signatures for keys loaded from karak-kms keystores
+#[derive(Parser)]
+#[command(version, about)]
+struct Args {
+    /// Path to the YAML configuration file
+    #[arg(short, long, env = "KARAK_SIGNER_CONFIG")]
+    config: PathBuf,
+}
+
+#[tokio::main]
+async fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
+        .init();
+
+    let args = Args::parse();
+    let config = SignerConfig::from_file(&args.config)?;
+    let server = SigningServer::from_config(&config).await?;
+
+    tokio::select! {
+        served = server.serve(&config.listen) => served?,
+        _ = tokio::signal::ctrl_c() => tracing::info!("Shutting down"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,380 @@
+use std::{
+    collections::HashMap,
+    fs::Permissions,
+    io,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
+
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::conn::auto,
+    service::TowerToHyperService,
+};
+use karak_kms::{
+    keypair::{bn254, traits::Keypair as _},
+    keystore::uri::KeystoreUri,
+    signer::{
+        remote::protocol::{
+            JsonRpcError, JsonRpcRequest, JsonRpcResponse, KeyInfo, KeyParams, SignHashParams,
+            GET_PUBLIC_KEY, INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, JSON_RPC_VERSION,
+            KEY_NOT_FOUND, LIST_KEYS, METHOD_NOT_FOUND, PARSE_ERROR, SIGN_HASH,
+        },
+        traits::Bn254Signer,
+    },
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use subtle::ConstantTimeEq;
+use tokio::net::{TcpListener, UnixListener};
+
+use crate::{
+    audit::{AuditLog, AuditOutcome, AuditRecord},
+    config::{KeyPolicy, Listen, SignerConfig},
+    error::SignerError,
+};
+
+struct Key {
+    keypair: bn254::Keypair,
+    policy: KeyPolicy,
+}
+
+struct Client {
+    name: String,
+    token: String,
+}
+
+/// Serves the signing service protocol of [`karak_kms::signer::remote`] for a set of BN254 keys
+#[derive(Default)]
+pub struct SigningServer {
+    keys: HashMap<String, Key>,
+    clients: Vec<Client>,
+    audit: AuditLog,
+}
+
+impl SigningServer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Loads every configured key from its keystore, with the passphrases and client tokens taken
+    /// from the environment
+    pub async fn from_config(config: &SignerConfig) -> Result<Self, SignerError> {
+        if config.clients.is_empty() && matches!(config.listen, Listen::Tcp(_)) {
+            return Err(SignerError::AnonymousTcp);
+        }
+
+        let mut server = Self::new();
+        if let Some(path) = &config.audit_log {
+            server = server.with_audit_log(AuditLog::to_file(path)?);
+        }
+
+        for client in &config.clients {
+            let token = env(&client.token_env)?;
+            if server
+                .clients
+                .iter()
+                .any(|existing| existing.name == client.name || existing.token == token)
+            {
+                return Err(SignerError::DuplicateClient(client.name.clone()));
+            }
+            server = server.with_client(&client.name, token);
+        }
+
+        for key in &config.keys {
+            if server.keys.contains_key(&key.id) {
+                return Err(SignerError::DuplicateKey(key.id.clone()));
+            }
+            if let Some(unknown) = key
+                .policy
+                .allowed_clients
+                .iter()
+                .flatten()
+                .find(|allowed| !server.clients.iter().any(|client| &client.name == *allowed))
+            {
+                return Err(SignerError::UnknownClient {
+                    key: key.id.clone(),
+                    client: unknown.clone(),
+                });
+            }
+
+            let keystore = KeystoreUri::from_str(&key.keystore)
+                .map_err(|source| SignerError::KeystoreUriError {
+                    key: key.id.clone(),
+                    source,
+                })?
+                .resolve::<bn254::Keypair>()
+                .await
+                .map_err(|source| SignerError::KeystoreUriError {
+                    key: key.id.clone(),
+                    source,
+                })?;
+            let keypair = keystore
+                .retrieve(&env(&key.passphrase_env)?)
+                .await
+                .map_err(|source| SignerError::KeystoreError {
+                    key: key.id.clone(),
+                    source,
+                })?;
+
+            tracing::info!("Loaded key {} ({})", key.id, keypair.public_key());
+            server = server.with_key(&key.id, keypair, key.policy.clone());
+        }
+
+        Ok(server)
+    }
+
+    pub fn with_key(
+        mut self,
+        key_id: impl Into<String>,
+        keypair: bn254::Keypair,
+        policy: KeyPolicy,
+    ) -> Self {
+        self.keys.insert(key_id.into(), Key { keypair, policy });
+        self
+    }
+
+    /// Once a client is added, requests without a known bearer token are rejected
+    pub fn with_client(mut self, name: impl Into<String>, token: impl Into<String>) -> Self {
+        self.clients.push(Client {
+            name: name.into(),
+            token: token.into(),
+        });
+        self
+    }
+
+    pub fn with_audit_log(mut self, audit: AuditLog) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// The signing service as a router that accepts JSON-RPC requests at `/`
+    pub fn router(self) -> Router {
+        Router::new()
+            .route("/", post(handle))
+            .with_state(Arc::new(self))
+    }
+
+    pub async fn serve(self, listen: &Listen) -> std::io::Result<()> {
+        let router = self.router();
+        match listen {
+            Listen::Tcp(address) => {
+                let listener = TcpListener::bind(address).await?;
+                tracing::info!("Listening on {}", listener.local_addr()?);
+                axum::serve(listener, router).await
+            }
+            Listen::Unix(path) => serve_unix(path, router).await,
+        }
+    }
+
+    /// The name of the client, `None` for anonymous clients when no clients are configured
+    fn authenticate(&self, headers: &HeaderMap) -> Result<Option<&str>, StatusCode> {
+        if self.clients.is_empty() {
+            return Ok(None);
+        }
+
+        let token = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        // Compare against every client so the response time does not reveal which one matched
+        self.clients
+            .iter()
+            .fold(None, |matched, client| {
+                let equal: bool = client.token.as_bytes().ct_eq(token.as_bytes()).into();
+                matched.or(equal.then_some(client.name.as_str()))
+            })
+            .map(Some)
+            .ok_or(StatusCode::UNAUTHORIZED)
+    }
+
+    /// Keys a client is not allowed to use are reported as not found
+    fn key(&self, key_id: &str, client: Option<&str>) -> Result<&Key, AuditOutcome> {
+        let key = self.keys.get(key_id).ok_or(AuditOutcome::KeyNotFound)?;
+        if !key.policy.allows(client) {
+            return Err(AuditOutcome::Denied);
+        }
+        Ok(key)
+    }
+
+    fn audit(
+        &self,
+        client: Option<&str>,
+        params: &SignHashParams,
+        outcome: AuditOutcome,
+    ) -> Result<(), JsonRpcError> {
+        self.audit
+            .record(&AuditRecord::new(
+                client,
+                &params.key_id,
+                params.hash,
+                outcome,
+            ))
+            .map_err(|_| JsonRpcError::new(INTERNAL_ERROR, "Failed to write the audit log"))
+    }
+
+    async fn dispatch(
+        &self,
+        client: Option<&str>,
+        method: &str,
+        params: Value,
+    ) -> Result<Value, JsonRpcError> {
+        match method {
+            LIST_KEYS => {
+                let mut keys: Vec<KeyInfo> = self
+                    .keys
+                    .iter()
+                    .filter(|(_, key)| key.policy.allows(client))
+                    .map(|(key_id, key)| KeyInfo {
+                        key_id: key_id.clone(),
+                        public_key: key.keypair.public_key().clone(),
+                    })
+                    .collect();
+                keys.sort_by(|a, b| a.key_id.cmp(&b.key_id));
+                to_value(keys)
+            }
+            GET_PUBLIC_KEY => {
+                let params: KeyParams = params_from(params)?;
+                let key = self
+                    .key(&params.key_id, client)
+                    .map_err(|_| key_not_found(&params.key_id))?;
+                to_value(key.keypair.public_key())
+            }
+            SIGN_HASH => {
+                let params: SignHashParams = params_from(params)?;
+                let key = match self.key(&params.key_id, client) {
+                    Ok(key) => key,
+                    Err(outcome) => {
+                        self.audit(client, &params, outcome)?;
+                        return Err(key_not_found(&params.key_id));
+                    }
+                };
+
+                // Nothing is signed without a record of it
+                self.audit(client, &params, AuditOutcome::Signed)?;
+                let signature = match key.keypair.sign_hash(&params.hash.0).await {
+                    Ok(signature) => signature,
+                    Err(never) => match never {},
+                };
+                to_value(signature)
+            }
+            method => Err(JsonRpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method {method}"),
+            )),
+        }
+    }
+}
+
+async fn handle(
+    State(server): State<Arc<SigningServer>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let client = match server.authenticate(&headers) {
+        Ok(client) => client,
+        Err(status) => return status.into_response(),
+    };
+
+    let request = match serde_json::from_slice::<Value>(&body) {
+        Ok(request) => request,
+        Err(e) => return rpc_error(Value::Null, JsonRpcError::new(PARSE_ERROR, e.to_string())),
+    };
+    let request: JsonRpcRequest<Value> = match serde_json::from_value(request) {
+        Ok(request) => request,
+        Err(e) => {
+            return rpc_error(
+                Value::Null,
+                JsonRpcError::new(INVALID_REQUEST, e.to_string()),
+            )
+        }
+    };
+    if request.jsonrpc != JSON_RPC_VERSION {
+        return rpc_error(
+            request.id,
+            JsonRpcError::new(INVALID_REQUEST, "Only JSON-RPC 2.0 is supported"),
+        );
+    }
+
+    match server
+        .dispatch(client, &request.method, request.params)
+        .await
+    {
+        Ok(result) => Json(JsonRpcResponse::result(request.id, result)).into_response(),
+        Err(error) => rpc_error(request.id, error),
+    }
+}
+
+async fn serve_unix(path: &Path, router: Router) -> std::io::Result<()> {
+    // Only a stale socket is replaced, never whatever else the path points at
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if !metadata.file_type().is_socket() => {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ));
+        }
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+
+    // Clients on a Unix socket may be anonymous, so only the server's user may connect. The socket
+    // is bound in a 0700 directory and only moved into place once its mode is set.
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let private_dir = tempfile::Builder::new()
+        .prefix(".karak-signer")
+        .tempdir_in(parent)?;
+    let bind_path = private_dir.path().join("signer.sock");
+    let listener = UnixListener::bind(&bind_path)?;
+    std::fs::set_permissions(&bind_path, Permissions::from_mode(0o600))?;
+    std::fs::rename(&bind_path, path)?;
+    private_dir.close()?;
+    tracing::info!("Listening on {}", path.display());
+
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let service = TowerToHyperService::new(router.clone());
+        tokio::spawn(async move {
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::warn!("Connection error: {e}");
+            }
+        });
+    }
+}
+
+fn env(name: &str) -> Result<String, SignerError> {
+    std::env::var(name).map_err(|_| SignerError::MissingEnv(name.to_string()))
+}
+
+fn params_from<Params: DeserializeOwned>(params: Value) -> Result<Params, JsonRpcError> {
+    serde_json::from_value(params).map_err(|e| JsonRpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn to_value(result: impl serde::Serialize) -> Result<Value, JsonRpcError> {
+    serde_json::to_value(result).map_err(|e| JsonRpcError::new(INTERNAL_ERROR, e.to_string()))
+}
+
+fn key_not_found(key_id: &str) -> JsonRpcError {
+    JsonRpcError::new(KEY_NOT_FOUND, format!("No key {key_id}"))
+}
+
+fn rpc_error(id: Value, error: JsonRpcError) -> Response {
+    Json(JsonRpcResponse::<Value>::error(id, error)).into_response()
+}
@@ -0,0 +1,122 @@
+//! Drives a [`SigningServer`] in-process, without binding a socket
+
+use std::{
+    io::Write,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{header, Request, StatusCode},
+    Router,
+};
+use karak_kms::signer::remote::protocol::{
+    JsonRpcError, JsonRpcRequest, JsonRpcResponse, JSON_RPC_VERSION,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+use tower::ServiceExt;
+
+use crate::{
+    audit::{AuditLog, AuditRecord},
+    server::SigningServer,
+};
+
+#[derive(Debug, Error)]
+pub enum HarnessError {
+    #[error("HTTP error: {0}")]
+    HttpError(StatusCode),
+    #[error("JSON-RPC error: {0}")]
+    RpcError(JsonRpcError),
+}
+
+pub struct TestHarness {
+    router: Router,
+}
+
+impl TestHarness {
+    pub fn new(server: SigningServer) -> Self {
+        Self {
+            router: server.router(),
+        }
+    }
+
+    pub async fn call<Params: Serialize, Result: DeserializeOwned>(
+        &self,
+        token: Option<&str>,
+        method: &str,
+        params: Params,
+    ) -> std::result::Result<Result, HarnessError> {
+        let request = JsonRpcRequest {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            id: 1.into(),
+            method: method.to_string(),
+            params,
+        };
+        let (status, response) = self
+            .call_raw(token, serde_json::to_vec(&request).unwrap())
+            .await;
+        if !status.is_success() {
+            return Err(HarnessError::HttpError(status));
+        }
+
+        let response: JsonRpcResponse<Result> = serde_json::from_value(response).unwrap();
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(HarnessError::RpcError(error)),
+            (Some(result), None) => Ok(result),
+            (None, None) => panic!("Response has neither a result nor an error"),
+        }
+    }
+
+    /// Posts `body` as is, the response body is `null` when it is not JSON
+    pub async fn call_raw(&self, token: Option<&str>, body: Vec<u8>) -> (StatusCode, Value) {
+        let mut request = Request::post("/").header(header::CONTENT_TYPE, "application/json");
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let response = self
+            .router
+            .clone()
+            .oneshot(request.body(Body::from(body)).unwrap())
+            .await
+            .unwrap();
+
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+    }
+}
+
+/// An audit log writer that keeps the records in memory
+#[derive(Clone, Default)]
+pub struct AuditCapture(Arc<Mutex<Vec<u8>>>);
+
+impl AuditCapture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn audit_log(&self) -> AuditLog {
+        AuditLog::to_writer(self.clone())
+    }
+
+    pub fn records(&self) -> Vec<AuditRecord> {
+        let buffer = self.0.lock().unwrap();
+        serde_json::Deserializer::from_slice(&buffer)
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+}
+
+impl Write for AuditCapture {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,286 @@
+use karak_kms::{
+    encryption::KdfParams,
+    keypair::{bn254, traits::Keypair},
+    keystore::{self, traits::AsyncEncryptedKeystore},
+    signer::{
+        remote::{
+            protocol::{
+                KeyInfo, KeyParams, SignHashParams, GET_PUBLIC_KEY, INTERNAL_ERROR, INVALID_PARAMS,
+                KEY_NOT_FOUND, LIST_KEYS, METHOD_NOT_FOUND, PARSE_ERROR, SIGN_HASH,
+            },
+            RemoteBn254Signer,
+        },
+        traits::Bn254Signer,
+    },
+};
+use std::{io::Write, os::unix::fs::PermissionsExt, time::Duration};
+
+use karak_signer::{
+    audit::{AuditLog, AuditOutcome},
+    config::{ClientConfig, KeyConfig, KeyPolicy, Listen, SignerConfig},
+    error::SignerError,
+    server::SigningServer,
+    testing::{AuditCapture, HarnessError, TestHarness},
+};
+use serde_json::{json, Value};
+
+fn only(clients: &[&str]) -> KeyPolicy {
+    KeyPolicy {
+        allowed_clients: Some(clients.iter().map(|client| client.to_string()).collect()),
+    }
+}
+
+fn sign_params(key_id: &str, hash: [u8; 32]) -> SignHashParams {
+    SignHashParams {
+        key_id: key_id.to_string(),
+        hash: hash.into(),
+    }
+}
+
+#[tokio::test]
+async fn test_sign_hash() {
+    let operator = bn254::Keypair::generate();
+    let audit = AuditCapture::new();
+    let harness = TestHarness::new(
+        SigningServer::new()
+            .with_client("node", "node-token")
+            .with_key("operator", operator.clone(), only(&["node"]))
+            .with_audit_log(audit.audit_log()),
+    );
+
+    let keys: Vec<KeyInfo> = harness
+        .call(Some("node-token"), LIST_KEYS, ())
+        .await
+        .unwrap();
+    assert_eq!(
+        keys,
+        vec![KeyInfo {
+            key_id: "operator".to_string(),
+            public_key: operator.public_key().clone(),
+        }]
+    );
+
+    let public_key: bn254::PublicKey = harness
+        .call(
+            Some("node-token"),
+            GET_PUBLIC_KEY,
+            KeyParams {
+                key_id: "operator".to_string(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(&public_key, operator.public_key());
+
+    let hash = [7u8; 32];
+    let signature: Value = harness
+        .call(Some("node-token"), SIGN_HASH, sign_params("operator", hash))
+        .await
+        .unwrap();
+    assert_eq!(signature, json!(operator.sign_hash(&hash).await.unwrap()));
+
+    let records = audit.records();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].client.as_deref(), Some("node"));
+    assert_eq!(records[0].key_id, "operator");
+    assert_eq!(records[0].hash, hash);
+    assert_eq!(records[0].outcome, AuditOutcome::Signed);
+}
+
+/// Fails every write, like a full disk
+struct FailingWriter;
+
+impl Write for FailingWriter {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::other("disk full"))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_refuses_to_sign_without_audit_record() {
+    let harness = TestHarness::new(
+        SigningServer::new()
+            .with_key("operator", bn254::Keypair::generate(), KeyPolicy::default())
+            .with_audit_log(AuditLog::to_writer(FailingWriter)),
+    );
+
+    let result: Result<Value, _> = harness
+        .call(None, SIGN_HASH, sign_params("operator", [7u8; 32]))
+        .await;
+    assert!(matches!(
+        result,
+        Err(HarnessError::RpcError(error)) if error.code == INTERNAL_ERROR
+    ));
+}
+
+#[tokio::test]
+async fn test_unix_socket_is_private() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("signer.sock");
+    let listen = Listen::Unix(path.clone());
+    tokio::spawn(async move { SigningServer::new().serve(&listen).await.unwrap() });
+
+    while !path.exists() {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[tokio::test]
+async fn test_unix_socket_never_replaces_a_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("signer.sock");
+    std::fs::write(&path, "not a socket").unwrap();
+
+    let result = SigningServer::new()
+        .serve(&Listen::Unix(path.clone()))
+        .await;
+    assert!(matches!(result, Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "not a socket");
+}
+
+#[tokio::test]
+async fn test_access_policies() {
+    let audit = AuditCapture::new();
+    let harness = TestHarness::new(
+        SigningServer::new()
+            .with_client("node", "node-token")
+            .with_client("other", "other-token")
+            .with_key("operator", bn254::Keypair::generate(), only(&["node"]))
+            .with_key("shared", bn254::Keypair::generate(), KeyPolicy::default())
+            .with_audit_log(audit.audit_log()),
+    );
+
+    assert!(matches!(
+        harness.call::<_, Value>(None, LIST_KEYS, ()).await,
+        Err(HarnessError::HttpError(status)) if status == 401
+    ));
+    assert!(matches!(
+        harness.call::<_, Value>(Some("wrong"), LIST_KEYS, ()).await,
+        Err(HarnessError::HttpError(status)) if status == 401
+    ));
+
+    let keys: Vec<KeyInfo> = harness
+        .call(Some("other-token"), LIST_KEYS, ())
+        .await
+        .unwrap();
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].key_id, "shared");
+
+    harness
+        .call::<_, Value>(
+            Some("other-token"),
+            SIGN_HASH,
+            sign_params("shared", [1; 32]),
+        )
+        .await
+        .unwrap();
+    for key_id in ["operator", "missing"] {
+        assert!(matches!(
+            harness
+                .call::<_, Value>(Some("other-token"), SIGN_HASH, sign_params(key_id, [2; 32]))
+                .await,
+            Err(HarnessError::RpcError(error)) if error.code == KEY_NOT_FOUND
+        ));
+    }
+
+    let outcomes: Vec<_> = audit
+        .records()
+        .into_iter()
+        .map(|record| (record.key_id, record.outcome))
+        .collect();
+    assert_eq!(
+        outcomes,
+        vec![
+            ("shared".to_string(), AuditOutcome::Signed),
+            ("operator".to_string(), AuditOutcome::Denied),
+            ("missing".to_string(), AuditOutcome::KeyNotFound),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_malformed_requests() {
+    let harness = TestHarness::new(SigningServer::new().with_key(
+        "operator",
+        bn254::Keypair::generate(),
+        KeyPolicy::default(),
+    ));
+
+    let (_, response) = harness.call_raw(None, b"{".to_vec()).await;
+    assert_eq!(response["error"]["code"], PARSE_ERROR);
+
+    assert!(matches!(
+        harness.call::<_, Value>(None, "bn254_unknown", ()).await,
+        Err(HarnessError::RpcError(error)) if error.code == METHOD_NOT_FOUND
+    ));
+    assert!(matches!(
+        harness.call::<_, Value>(None, SIGN_HASH, json!({ "keyId": "operator" })).await,
+        Err(HarnessError::RpcError(error)) if error.code == INVALID_PARAMS
+    ));
+}
+
+#[tokio::test]
+async fn test_from_config_with_remote_signer() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("operator.bls");
+    let operator = bn254::Keypair::generate();
+    keystore::local::LocalEncryptedKeystore::new(path.clone())
+        .with_kdf_params(KdfParams::Pbkdf2 { iterations: 1_000 })
+        .store(&operator, "passphrase", &())
+        .await
+        .unwrap();
+
+    std::env::set_var("KARAK_SIGNER_TEST_PASSPHRASE", "passphrase");
+    std::env::set_var("KARAK_SIGNER_TEST_TOKEN", "node-token");
+    let mut config = SignerConfig {
+        listen: Listen::Tcp("127.0.0.1:0".parse().unwrap()),
+        audit_log: Some(dir.path().join("audit.jsonl")),
+        clients: vec![ClientConfig {
+            name: "node".to_string(),
+            token_env: "KARAK_SIGNER_TEST_TOKEN".to_string(),
+        }],
+        keys: vec![KeyConfig {
+            id: "operator".to_string(),
+            keystore: format!("file://{}", path.display()),
+            passphrase_env: "KARAK_SIGNER_TEST_PASSPHRASE".to_string(),
+            policy: only(&["node"]),
+        }],
+    };
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let router = SigningServer::from_config(&config).await.unwrap().router();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+
+    let signer =
+        RemoteBn254Signer::new(url.parse().unwrap(), "operator").with_bearer_token("node-token");
+    let hash = [9u8; 32];
+    assert_eq!(
+        signer.sign_hash(&hash).await.unwrap(),
+        operator.sign_hash(&hash).await.unwrap()
+    );
+    let audit = std::fs::read_to_string(dir.path().join("audit.jsonl")).unwrap();
+    assert_eq!(audit.lines().count(), 1);
+
+    config.keys[0].policy = only(&["unknown"]);
+    assert!(matches!(
+        SigningServer::from_config(&config).await,
+        Err(SignerError::UnknownClient { .. })
+    ));
+
+    // Anonymous clients are refused over TCP
+    config.keys[0].policy = KeyPolicy::default();
+    config.clients.clear();
+    assert!(matches!(
+        SigningServer::from_config(&config).await,
+        Err(SignerError::AnonymousTcp)
+    ));
+    config.listen = Listen::Unix(dir.path().join("signer.sock"));
+    assert!(SigningServer::from_config(&config).await.is_ok());
+}